@@ -2,6 +2,11 @@ import tkinter as tk
 from tkinter import ttk
 import board_frame
 from board_frame import rank_file2index, index2rank_file, MoveType, BoardView
+import RustEngine as rst
+
+# how many candidate moves the analysis shows and how long it may think about them
+ANALYSIS_LINES = 3
+ANALYSIS_TIME = 2000
 
 
 class ChessboardForAnalysis(board_frame.ChessboardCanvas):
@@ -232,6 +237,82 @@ class AnalysisPage(ttk.Frame):
         ttk.Button(self.buttons_frame, text="test depth", command=self.chessboard.test_move_calculation).grid(row=2, column=0)
         self.create_fen_upload(self.buttons_frame)
         self.fenUploadFrame.grid(row=3, column=0)
-    
-        
-        
\ No newline at end of file
+        self.create_analysis(self.buttons_frame)
+        self.analysis_frame.grid(row=4, column=0, pady=10)
+        self.create_evaluation(self.buttons_frame)
+        self.evaluation_frame.grid(row=5, column=0, pady=10)
+
+    def create_analysis(self, parent):
+        # list the best candidate moves of the engine together with their evaluation and line
+        self.analysis_frame = ttk.Frame(parent)
+        lines_label = ttk.Label(self.analysis_frame, text="lines:")
+        lines_label.grid(row=0, column=0)
+        self.analysis_lines = tk.IntVar(value=ANALYSIS_LINES)
+        lines_spinbox = ttk.Spinbox(self.analysis_frame, from_=1, to=10, width=3, textvariable=self.analysis_lines)
+        lines_spinbox.grid(row=0, column=1)
+        ttk.Button(self.analysis_frame, text="analyse", command=self.analyse).grid(row=0, column=2)
+        self.analysis_list = tk.Listbox(self.analysis_frame, width=60, height=10)
+        self.analysis_list.grid(row=1, column=0, columnspan=3)
+
+    def analyse(self):
+        # the analysis runs in the background, the lines of every finished depth are shown as they come in
+        coordinator = self.chessboard.chessboard_coordinator
+        coordinator.set_info_callback(self.show_analysis)
+        try:
+            coordinator.start_analysis(rst.SearchLimits(movetime=ANALYSIS_TIME), max(1, self.analysis_lines.get()))
+        except (ValueError, RuntimeError) as error:
+            self.analysis_list.delete(0, tk.END)
+            self.analysis_list.insert(tk.END, str(error))
+            return
+        self.after(50, self.poll_analysis)
+
+    def poll_analysis(self):
+        coordinator = self.chessboard.chessboard_coordinator
+        # the analysis stops by itself when the board changes
+        if not coordinator.is_analysing():
+            return
+        result = coordinator.poll_analysis()
+        if result is None:
+            self.after(50, self.poll_analysis)
+        else:
+            self.show_analysis(result)
+
+    def show_analysis(self, result):
+        coordinator = self.chessboard.chessboard_coordinator
+        self.analysis_list.delete(0, tk.END)
+        # the engine scores from the side to move, we show the evaluation from whites perspective
+        sign = 1 if coordinator.get_to_move() == "player1" else -1
+        for i, line in enumerate(result.get_lines()):
+            mate = line.get_mate()
+            if mate is None:
+                evaluation = f"{sign * line.get_score() / 100:+.2f}"
+            else:
+                evaluation = f"#{sign * mate}"
+            self.analysis_list.insert(tk.END, f"{i + 1}. {evaluation}  {' '.join(line.get_pv())}")
+        self.analysis_list.insert(tk.END, f"depth {result.get_depth()}  nodes {result.get_nodes()}")
+
+    def create_evaluation(self, parent):
+        # a table with every term of the static evaluation, to see why the engine likes or dislikes a position
+        self.evaluation_frame = ttk.Frame(parent)
+        ttk.Button(self.evaluation_frame, text="evaluation", command=self.show_evaluation).grid(row=0, column=0)
+        self.evaluation_label = ttk.Label(self.evaluation_frame, text="")
+        self.evaluation_label.grid(row=0, column=1)
+        columns = ("white", "black", "score")
+        self.evaluation_table = ttk.Treeview(self.evaluation_frame, columns=columns, height=12)
+        self.evaluation_table.heading("#0", text="term")
+        self.evaluation_table.column("#0", width=110)
+        self.evaluation_table.heading("white", text="white mg/eg")
+        self.evaluation_table.heading("black", text="black mg/eg")
+        self.evaluation_table.heading("score", text="score")
+        for column in columns:
+            self.evaluation_table.column(column, width=90, anchor="e")
+        self.evaluation_table.grid(row=1, column=0, columnspan=2)
+
+    def show_evaluation(self):
+        trace = self.chessboard.chessboard_coordinator.evaluate_trace()
+        self.evaluation_table.delete(*self.evaluation_table.get_children())
+        for term in trace.get_terms():
+            white, black = term.get_white(), term.get_black()
+            values = (f"{white[0]} / {white[1]}", f"{black[0]} / {black[1]}", f"{term.get_score() / 100:+.2f}")
+            self.evaluation_table.insert("", tk.END, text=term.get_name(), values=values)
+        self.evaluation_label.config(text=f"phase {trace.get_phase()}/{trace.get_total_phase()}  total {trace.get_score() / 100:+.2f}")
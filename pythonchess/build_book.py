@@ -0,0 +1,13 @@
+import argparse
+import RustEngine as rst
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser(description="build a polyglot opening book from pgn files")
+    parser.add_argument("output", help="the .bin file the book is written to")
+    parser.add_argument("pgn_files", nargs="+")
+    parser.add_argument("--max-plies", type=int, default=24, help="only the first moves of every game go into the book")
+    parser.add_argument("--min-games", type=int, default=1, help="leave out moves played in fewer games")
+    args = parser.parse_args()
+
+    entries = rst.build_book(args.pgn_files, args.output, args.max_plies, args.min_games)
+    print(f"{entries} book entries written to {args.output}")
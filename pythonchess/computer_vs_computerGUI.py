@@ -12,8 +12,13 @@ class Chessboard_for_AIvsAI(board_frame.ChessboardCanvas):
         self.chessboard_coordinator.set_player1("basic")
         self.chessboard_coordinator.set_player2("basic")
         
+    def set_players(self, player1: str, player2: str):
+        self.chessboard_coordinator.set_player1(player1)
+        self.chessboard_coordinator.set_player2(player2)
+
     def next_move(self):
         self.chessboard_coordinator.next_computer_move()
+        self.parent.show_search_result(self.chessboard_coordinator.get_last_search_result())
         self.update_board()
 
 
@@ -30,6 +35,10 @@ class ComputerVSComputerPage(ttk.Frame):
         self.make_buttons()
         self.buttons_frame.grid(row=0, column=1)
     
+    # choose the computers that play, see RustEngine.list_computers
+    def set_players(self, player1: str, player2: str):
+        self.chessboard.set_players(player1, player2)
+
     # make all the buttons
     def make_buttons(self):
         self.buttons_frame = ttk.Frame(self)
@@ -40,6 +49,18 @@ class ComputerVSComputerPage(ttk.Frame):
         ttk.Button(self.buttons_frame, text="move", command=self.chessboard.next_move).grid(row=2, column=0)
         # reset button
         ttk.Button(self.buttons_frame, text="reset", command=self.chessboard.reset_position).grid(row=3, column=0)
+        # evaluation and principal variation of the last computer move
+        self.search_info = ttk.Label(self.buttons_frame, text="", wraplength=250, justify="left")
+        self.search_info.grid(row=4, column=0)
+
+    def show_search_result(self, result):
+        # result is None when the computer that moved does not search
+        if result is None:
+            self.search_info["text"] = ""
+            return
+        self.search_info["text"] = (f"score: {result.get_score()}  depth: {result.get_depth()}/{result.get_seldepth()}\n"
+                                    f"nodes: {result.get_nodes()}  nps: {result.get_nps()}\n"
+                                    f"pv: {' '.join(result.get_pv())}")
 
     
     def playing_thread(self):
@@ -0,0 +1,28 @@
+import argparse
+import RustEngine as rst
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser(description="generate training data from self-play games")
+    parser.add_argument("output", help="file the positions are written to")
+    parser.add_argument("--games", type=int, default=100)
+    parser.add_argument("--threads", type=int, default=1)
+    parser.add_argument("--computer", default="basic")
+    parser.add_argument("--depth", type=int)
+    parser.add_argument("--nodes", type=int)
+    parser.add_argument("--random-plies", type=int, default=8)
+    parser.add_argument("--format", choices=["jsonl", "binary"], default="jsonl")
+    parser.add_argument("--seed", type=int, default=0)
+    args = parser.parse_args()
+
+    # without any limit every search gets 5000 nodes
+    nodes = 5000 if args.depth is None and args.nodes is None else args.nodes
+    options = rst.DatagenOptions(games=args.games, threads=args.threads, computer=args.computer, depth=args.depth,
+                                 nodes=nodes, random_plies=args.random_plies, format=args.format, seed=args.seed)
+
+    def print_progress(report):
+        if report.games % 10 == 0 or report.games == args.games:
+            print(f"{report.games} of {args.games} games, {report.positions} positions")
+
+    report = rst.generate_data(args.output, options, print_progress)
+    print(f"{report.games} games, {report.positions} positions")
+    print(f"white won {report.white_wins}, black won {report.black_wins}, {report.draws} draws")
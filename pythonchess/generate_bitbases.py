@@ -0,0 +1,16 @@
+import argparse
+import os
+import RustEngine as rst
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser(description="generate the endgame bitbases the engine reads at startup")
+    parser.add_argument("endgames", nargs="*", default=["KPK", "KRK", "KQK", "KBNK"])
+    parser.add_argument("--output-dir", default="bitbases", help="the engine looks for the tables in bitbases/")
+    args = parser.parse_args()
+
+    os.makedirs(args.output_dir, exist_ok=True)
+    for name in args.endgames:
+        bitbase = rst.Bitbase.generate(name)
+        path = os.path.join(args.output_dir, f"{bitbase.get_name()}.bin")
+        bitbase.save(path)
+        print(f"{path}: longest mate in {bitbase.get_longest_mate()} plies")
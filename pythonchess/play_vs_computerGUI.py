@@ -2,24 +2,37 @@ import tkinter as tk
 from tkinter import ttk
 import board_frame
 from time import sleep
-import threading
+import RustEngine as rst
 from board_frame import rank_file2index, index2rank_file, MoveType, BoardView
 
+# how long the computer may think about a move in milliseconds
+THINK_TIME = 2000
+
 class Chessboard_for_PlayervsAI(board_frame.ChessboardCanvas):
-    def __init__(self, parent, play_as: str):
+    def __init__(self, parent, play_as: str, opponent: str = "basic"):
         super().__init__(parent)
         self.play_as = play_as
-        # initialize the two players as random agents
-        if play_as == "player1":
-            self.chessboard_coordinator.set_player1("human")
-            self.chessboard_coordinator.set_player2("basic")
-        elif play_as == "player2":
-            self.chessboard_coordinator.set_player1("basic")
-            self.chessboard_coordinator.set_player2("human")
+        self.set_opponent(opponent)
+        # show the progress of the computer while it is thinking
+        self.chessboard_coordinator.set_info_callback(self.parent.show_search_result)
             
         # bind mouse events
         self.bind_events()
     
+    def set_opponent(self, opponent: str):
+        # opponent is the name of one of the computers of RustEngine.list_computers
+        if self.play_as == "player1":
+            self.chessboard_coordinator.set_player1("human")
+            self.chessboard_coordinator.set_player2(opponent)
+        elif self.play_as == "player2":
+            self.chessboard_coordinator.set_player1(opponent)
+            self.chessboard_coordinator.set_player2("human")
+
+    def set_ponder(self, ponder: bool):
+        # the computer keeps thinking about its next move while it is our turn
+        computer = "player2" if self.play_as == "player1" else "player1"
+        self.chessboard_coordinator.set_ponder(computer, ponder)
+
     def bind_events(self):
         self.bind("<ButtonPress-1>", self.on_select)
         self.bind("<B1-Motion>", self.on_drag)
@@ -94,9 +107,25 @@ class Chessboard_for_PlayervsAI(board_frame.ChessboardCanvas):
         self.lift_pieces()
     
     def next_move(self):
+        # the computer thinks on a background thread, so the window stays responsive in the meantime
+        if self.chessboard_coordinator.is_searching():
+            return
         if (self.play_as == "player1" and self.chessboard_coordinator.get_to_move() == "player2") or (self.play_as == "player2" and self.chessboard_coordinator.get_to_move() == "player1"):
-            self.chessboard_coordinator.next_computer_move()
-            self.update_board()
+            if self.chessboard_coordinator.start_search(rst.SearchLimits(movetime=THINK_TIME)):
+                self.after(50, self.poll_search)
+
+    def poll_search(self):
+        # check whether the computer is done thinking, if so its move has already been played
+        result = self.chessboard_coordinator.poll_search()
+        if self.chessboard_coordinator.is_searching():
+            self.after(50, self.poll_search)
+            return
+        self.parent.show_search_result(result)
+        self.update_board()
+
+    def move_now(self):
+        # let the computer play the best move it has found so far
+        self.chessboard_coordinator.move_now()
 
 
 class PlayervsAIPage(ttk.Frame):
@@ -104,8 +133,10 @@ class PlayervsAIPage(ttk.Frame):
         ttk.Frame.__init__(self, parent)
         self.controller = controller
         
+        self.opponent = "basic"
+        self.ponder = tk.BooleanVar(value=False)
         # main chessboard
-        self.chessboard = Chessboard_for_PlayervsAI(self, play_as="player1")
+        self.chessboard = Chessboard_for_PlayervsAI(self, play_as="player1", opponent=self.opponent)
         self.chessboard.grid(row=0, column=0, padx=10, pady=10)
         
         # make all the buttons
@@ -114,7 +145,13 @@ class PlayervsAIPage(ttk.Frame):
     
     # set the as who we are playing
     def set_player_color(self, play_as: str):
-        self.chessboard = Chessboard_for_PlayervsAI(self, play_as)
+        self.chessboard = Chessboard_for_PlayervsAI(self, play_as, self.opponent)
+        self.chessboard.set_ponder(self.ponder.get())
+
+    # choose the computer to play against
+    def set_opponent(self, opponent: str):
+        self.chessboard.set_opponent(opponent)
+        self.opponent = opponent
     
     def create_fen_upload(self, parent):
         self.fenUploadFrame = tk.Frame(parent)
@@ -132,25 +169,39 @@ class PlayervsAIPage(ttk.Frame):
         # undo button
         ttk.Button(self.buttons_frame, text="undo", command=self.chessboard.undo).grid(row=1, column=0)
         ttk.Button(self.buttons_frame, text="move", command=self.chessboard.next_move).grid(row=2, column=0)
+        ttk.Button(self.buttons_frame, text="move now", command=self.chessboard.move_now).grid(row=2, column=1)
+        ttk.Checkbutton(self.buttons_frame, text="ponder", variable=self.ponder,
+                        command=lambda: self.chessboard.set_ponder(self.ponder.get())).grid(row=1, column=1)
         # reset button
         ttk.Button(self.buttons_frame, text="reset", command=self.chessboard.reset_position).grid(row=3, column=0)
         self.create_fen_upload(self.buttons_frame)
         self.fenUploadFrame.grid(row=3, column=0)
+        # evaluation and principal variation of the last computer move
+        self.search_info = ttk.Label(self.buttons_frame, text="", wraplength=250, justify="left")
+        self.search_info.grid(row=4, column=0)
+
+    def show_search_result(self, result):
+        # result is None when the computer that moved does not search
+        if result is None:
+            self.search_info["text"] = ""
+            return
+        self.search_info["text"] = (f"score: {result.get_score()}  depth: {result.get_depth()}/{result.get_seldepth()}\n"
+                                    f"nodes: {result.get_nodes()}  nps: {result.get_nps()}\n"
+                                    f"pv: {' '.join(result.get_pv())}")
 
     
-    def playing_thread(self):
-        while True:
-            if self.pause_thread:
-                break
-            self.chessboard.next_move()
-            self.update_idletasks()
+    def playing_loop(self):
+        # next_move returns immediately, so we can simply keep asking for it from the tkinter event loop
+        if self.pause_thread:
+            return
+        self.chessboard.next_move()
+        self.after(100, self.playing_loop)
     
     def start_stop_play(self):
         if self.start_stop_button["text"] == "start":
             self.start_stop_button["text"] = "stop"
             self.pause_thread = False
-            self.play_thread = threading.Thread(target=self.playing_thread)
-            self.play_thread.start()
+            self.playing_loop()
         else:
             self.start_stop_button["text"] = "start"
             self.pause_thread = True
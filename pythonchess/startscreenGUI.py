@@ -1,9 +1,10 @@
 import tkinter as tk
-from tkinter import ttk
+from tkinter import ttk, messagebox
 from time import sleep
 from computer_vs_computerGUI import ComputerVSComputerPage
 from analysisGUI import AnalysisPage
 from play_vs_computerGUI import PlayervsAIPage
+import RustEngine as rst
 
 class Visual(tk.Tk):
     def __init__(self, *args, **kwargs):
@@ -63,14 +64,44 @@ class StartPage(ttk.Frame):
         self.make_playervsAI_frame()
         self.playervsAI.grid(row=3, column=0)
         
+    def make_computer_menu(self, parent) -> ttk.Combobox:
+        # the menu lists every computer the engine knows, so new computers show up without changing the gui
+        names = [computer.get_name() for computer in rst.list_computers()]
+        menu = ttk.Combobox(parent, values=names, state="readonly", width=10)
+        menu.set("basic" if "basic" in names else names[0])
+        return menu
+
     def make_AIvsAI_frame(self):
         self.AIvsAI = ttk.Frame(self)
         ttk.Label(self.AIvsAI, text="watch computers fight computers!").grid(row=0, column=0)
-        ttk.Button(self.AIvsAI, text="GO!", command=lambda: self.controller.show_frame(ComputerVSComputerPage)).grid(row=0, column=1)
+        self.white_menu = self.make_computer_menu(self.AIvsAI)
+        self.white_menu.grid(row=0, column=1)
+        self.black_menu = self.make_computer_menu(self.AIvsAI)
+        self.black_menu.grid(row=0, column=2)
+        ttk.Button(self.AIvsAI, text="GO!", command=self.start_AIvsAI).grid(row=0, column=3)
+
+    def start_AIvsAI(self):
+        # a computer that needs a file, like a network, cannot play without it
+        try:
+            self.controller.frames[ComputerVSComputerPage].set_players(self.white_menu.get(), self.black_menu.get())
+        except (ValueError, OSError) as error:
+            messagebox.showerror("Chess Computer", str(error))
+            return
+        self.controller.show_frame(ComputerVSComputerPage)
     
     def make_playervsAI_frame(self):
         self.playervsAI = ttk.Frame(self)
-        ttk.Button(self.playervsAI, text="play vs a computer!", command=lambda: self.controller.show_frame(PlayervsAIPage)).grid(row=0, column=0)
+        ttk.Button(self.playervsAI, text="play vs a computer!", command=self.start_playervsAI).grid(row=0, column=0)
+        self.opponent_menu = self.make_computer_menu(self.playervsAI)
+        self.opponent_menu.grid(row=0, column=1)
+
+    def start_playervsAI(self):
+        try:
+            self.controller.frames[PlayervsAIPage].set_opponent(self.opponent_menu.get())
+        except (ValueError, OSError) as error:
+            messagebox.showerror("Chess Computer", str(error))
+            return
+        self.controller.show_frame(PlayervsAIPage)
     
 if __name__ == '__main__':
     test = Visual()
@@ -0,0 +1,26 @@
+import argparse
+import RustEngine as rst
+
+
+def print_progress(iteration, error):
+    if iteration == 0:
+        print(f"error before tuning = {error:.6f}")
+    else:
+        print(f"iteration {iteration}: error = {error:.6f}")
+
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser(description="tune the evaluation parameters on labelled positions")
+    parser.add_argument("dataset", help="file with one fen or epd and the game result per line")
+    parser.add_argument("output", help="json file the tuned parameters are written to")
+    parser.add_argument("--iterations", type=int, default=100)
+    parser.add_argument("--threads", type=int, default=1)
+    parser.add_argument("--start", help="json file with the parameters to start from")
+    parser.add_argument("--parameters", nargs="*", help="only tune the parameters starting with these names")
+    args = parser.parse_args()
+
+    start = rst.EvalParams.load(args.start) if args.start else None
+    report = rst.tune_evaluation(args.dataset, args.output, args.iterations, args.parameters, args.threads, start,
+                                 print_progress)
+    print(f"{report.positions} positions ({report.skipped} skipped), K = {report.k:.4f}")
+    print(f"error {report.error_before:.6f} -> {report.error_after:.6f} after {report.iterations} iterations")
@@ -0,0 +1,566 @@
+use pyo3::prelude::*;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::sync::{Once, OnceLock};
+use std::thread;
+use crate::chessboard::*;
+use crate::chessboard_helper::*;
+use crate::lookuptables::LoadMoves;
+
+// Distance to mate tables for the endgames of a king and a pawn, rook, queen or bishop and knight against a
+// bare king, generated by retrograde analysis. Starting from the mates we walk backwards through the moves,
+// a position of the strong side is won as soon as one move leads to a lost position of the weak side, which
+// is lost once all of its moves lead to won positions. Whatever is never reached is a draw.
+//
+// The tables always have white as the strong side, positions where black is stronger are flipped. Without
+// pawns the white king is mirrored into the a8-d5 quarter of the board, with a pawn the pawn is mirrored onto
+// the files a to d. A table is a list of bytes indexed by side to move, white king, black king and the other
+// pieces, every byte is the number of plies to mate plus one, 0 for a draw and 255 for positions that can
+// not happen. Saved tables start with the magic "RCBB", a version and the endgame.
+
+// tables are read from here when they exist, otherwise they are generated in the background once they are needed
+pub const DEFAULT_BITBASE_DIR: &str = "bitbases";
+// the score of a won endgame before the distance to mate is subtracted, far below the mate scores
+pub const KNOWN_WIN_SCORE: i32 = 20_000;
+
+const MAGIC: &[u8; 4] = b"RCBB";
+const VERSION: u8 = 1;
+const DRAW: u8 = 0;
+const ILLEGAL: u8 = 255;
+// the weak side has a move that can not lose, like taking a piece or stalemate
+const CANNOT_LOSE: u8 = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endgame {
+    Kpk,
+    Krk,
+    Kqk,
+    Kbnk
+}
+
+impl Endgame {
+    pub const ALL: [Endgame; 4] = [Endgame::Kpk, Endgame::Krk, Endgame::Kqk, Endgame::Kbnk];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Endgame::Kpk => "KPK",
+            Endgame::Krk => "KRK",
+            Endgame::Kqk => "KQK",
+            Endgame::Kbnk => "KBNK"
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Endgame> {
+        Endgame::ALL.into_iter().find(|endgame| endgame.name().eq_ignore_ascii_case(name))
+    }
+    fn pieces(&self) -> &'static [PieceType] {
+        // the pieces of the strong side next to its king
+        match self {
+            Endgame::Kpk => &[PieceType::Pawn],
+            Endgame::Krk => &[PieceType::Rook],
+            Endgame::Kqk => &[PieceType::Queen],
+            Endgame::Kbnk => &[PieceType::Bishop, PieceType::Knight]
+        }
+    }
+    fn has_pawns(&self) -> bool {
+        *self == Endgame::Kpk
+    }
+    fn king_squares(&self) -> usize {
+        if self.has_pawns() {64} else {16}
+    }
+    fn size(&self) -> usize {
+        2 * self.king_squares() * 64 * 64usize.pow(self.pieces().len() as u32)
+    }
+    fn matches(&self, strong: &Pieces) -> bool {
+        let counts = [strong.get_bb_pawns(), strong.get_bb_knights(), strong.get_bb_bishops(), strong.get_bb_rooks(),
+            strong.get_bb_queens()].map(|bitboard| bitboard.count_ones());
+        counts == match self {
+            Endgame::Kpk => [1, 0, 0, 0, 0],
+            Endgame::Krk => [0, 0, 0, 1, 0],
+            Endgame::Kqk => [0, 0, 0, 0, 1],
+            Endgame::Kbnk => [0, 1, 1, 0, 0]
+        }
+    }
+    pub fn of(position: &Position) -> Option<(Endgame, bool)> {
+        // the endgame of the position and whether white is the strong side
+        let bare = |pieces: &Pieces| pieces.get_bb_pawns() | pieces.get_bb_knights() | pieces.get_bb_bishops()
+            | pieces.get_bb_rooks() | pieces.get_bb_queens() == 0;
+        let (strong, white_strong) = if bare(&position.black_pieces) {
+            (&position.white_pieces, true)
+        }
+        else if bare(&position.white_pieces) {
+            (&position.black_pieces, false)
+        }
+        else {
+            return None
+        };
+        Endgame::ALL.into_iter().find(|endgame| endgame.matches(strong)).map(|endgame| (endgame, white_strong))
+    }
+}
+
+// the result of a position for the side to move with the plies until mate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitbaseResult {
+    Win(u8),
+    Draw,
+    Loss(u8)
+}
+
+fn squares_of(mut bitboard: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None
+        }
+        let square = bitboard.trailing_zeros() as usize;
+        bitboard &= bitboard - 1;
+        Some(square)
+    })
+}
+
+fn white_pawn_attacks(square: usize) -> u64 {
+    // white pawns move towards the lower indices
+    let mut attacks = 0;
+    if square >= 8 {
+        if square & 7 > 0 {
+            attacks |= 1 << (square - 9);
+        }
+        if square & 7 < 7 {
+            attacks |= 1 << (square - 7);
+        }
+    }
+    attacks
+}
+
+fn attacks(piece_type: PieceType, square: usize, occupied: u64, moves: &LoadMoves) -> u64 {
+    match piece_type {
+        PieceType::Pawn => white_pawn_attacks(square),
+        PieceType::Knight => moves.knight(square),
+        PieceType::Bishop => moves.bishop(square, occupied).copied().unwrap_or(0),
+        PieceType::Rook => moves.rook(square, occupied).copied().unwrap_or(0),
+        PieceType::Queen => moves.queen(square, occupied).unwrap_or(0),
+        PieceType::King => moves.king(square),
+        PieceType::EmptySquare => 0
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Placement {
+    white_king: usize,
+    black_king: usize,
+    // the other pieces of the strong side in the order of the endgame
+    pieces: [usize; 2],
+    black_to_move: bool
+}
+
+impl Placement {
+    fn decode(endgame: Endgame, mut index: usize) -> Placement {
+        let count = endgame.pieces().len();
+        let mut pieces = [0; 2];
+        for piece in pieces[..count].iter_mut().rev() {
+            *piece = index % 64;
+            index /= 64;
+        }
+        let black_king = index % 64;
+        index /= 64;
+        let king_slot = index % endgame.king_squares();
+        let white_king = if endgame.has_pawns() {king_slot} else {(king_slot / 4) * 8 + king_slot % 4};
+        Placement { white_king, black_king, pieces, black_to_move: index / endgame.king_squares() == 1 }
+    }
+    fn index(&self, endgame: Endgame) -> usize {
+        let king_slot = if endgame.has_pawns() {self.white_king} else {(self.white_king >> 3) * 4 + (self.white_king & 7)};
+        let mut index = (self.black_to_move as usize * endgame.king_squares() + king_slot) * 64 + self.black_king;
+        for piece in self.pieces[..endgame.pieces().len()].iter() {
+            index = index * 64 + piece;
+        }
+        index
+    }
+    fn canonical(mut self, endgame: Endgame) -> Placement {
+        // mirrors the position onto the part of the board the table stores
+        let mut flip = 0;
+        if endgame.has_pawns() {
+            if self.pieces[0] & 7 > 3 {
+                flip = 7;
+            }
+        }
+        else {
+            if self.white_king & 7 > 3 {
+                flip |= 7;
+            }
+            if self.white_king >> 3 > 3 {
+                flip |= 56;
+            }
+        }
+        self.white_king ^= flip;
+        self.black_king ^= flip;
+        for piece in self.pieces.iter_mut() {
+            *piece ^= flip;
+        }
+        self
+    }
+    fn occupied(&self, endgame: Endgame) -> u64 {
+        self.pieces[..endgame.pieces().len()].iter().fold(1 << self.white_king | 1 << self.black_king, |occupied, square| occupied | 1 << square)
+    }
+    fn attacked_by_white(&self, endgame: Endgame, moves: &LoadMoves, square: usize, occupied: u64, captured: Option<usize>) -> bool {
+        if moves.king(self.white_king) & 1 << square != 0 {
+            return true
+        }
+        endgame.pieces().iter().enumerate().any(|(i, piece_type)| {
+            Some(i) != captured && attacks(*piece_type, self.pieces[i], occupied, moves) & 1 << square != 0
+        })
+    }
+    fn is_legal(&self, endgame: Endgame, moves: &LoadMoves) -> bool {
+        let occupied = self.occupied(endgame);
+        if occupied.count_ones() as usize != 2 + endgame.pieces().len() || moves.king(self.white_king) & 1 << self.black_king != 0 {
+            return false
+        }
+        // pawns are never on the first or last rank, and the table only has them on the files a to d
+        if endgame.has_pawns() && (self.pieces[0] >> 3 == 0 || self.pieces[0] >> 3 == 7 || self.pieces[0] & 7 > 3) {
+            return false
+        }
+        // the side that does not move can not be in check
+        self.black_to_move || !self.attacked_by_white(endgame, moves, self.black_king, occupied, None)
+    }
+    fn black_moves(&self, endgame: Endgame, moves: &LoadMoves) -> Option<u8> {
+        // the number of legal moves of the black king, None when it can take a piece and so can not lose
+        let occupied = self.occupied(endgame) & !(1 << self.black_king);
+        let mut count = 0;
+        for square in squares_of(moves.king(self.black_king)) {
+            let captured = self.pieces[..endgame.pieces().len()].iter().position(|piece| *piece == square);
+            if self.attacked_by_white(endgame, moves, square, occupied, captured) {
+                continue
+            }
+            if captured.is_some() {
+                return None
+            }
+            count += 1;
+        }
+        Some(count)
+    }
+    fn predecessors(&self, endgame: Endgame, moves: &LoadMoves, result: &mut Vec<Placement>) {
+        // all positions that lead to this one with a move that does not capture
+        result.clear();
+        let occupied = self.occupied(endgame);
+        if self.black_to_move {
+            for square in squares_of(moves.king(self.white_king) & !occupied) {
+                result.push(Placement { white_king: square, black_to_move: false, ..*self });
+            }
+            for (i, piece_type) in endgame.pieces().iter().enumerate() {
+                let to = self.pieces[i];
+                let from = match piece_type {
+                    PieceType::Pawn => {
+                        let mut from = 0;
+                        if to + 8 < 56 && occupied & 1 << (to + 8) == 0 {
+                            from |= 1 << (to + 8);
+                            // a double step ends on the fourth rank
+                            if to >> 3 == 4 && occupied & 1 << (to + 16) == 0 {
+                                from |= 1 << (to + 16);
+                            }
+                        }
+                        from
+                    }
+                    other => attacks(*other, to, occupied, moves) & !occupied
+                };
+                for square in squares_of(from) {
+                    let mut predecessor = Placement { black_to_move: false, ..*self };
+                    predecessor.pieces[i] = square;
+                    result.push(predecessor);
+                }
+            }
+        }
+        else {
+            for square in squares_of(moves.king(self.black_king) & !occupied) {
+                result.push(Placement { black_king: square, black_to_move: true, ..*self });
+            }
+        }
+    }
+    fn promotion_plies(&self, promotions: &[&Bitbase], moves: &LoadMoves) -> Option<u8> {
+        // the plies to mate when the pawn promotes right away, if that wins
+        let pawn = self.pieces[0];
+        if pawn >> 3 != 1 || self.occupied(Endgame::Kpk) & 1 << (pawn - 8) != 0 {
+            return None
+        }
+        promotions.iter().filter_map(|table| {
+            let promoted = Placement { pieces: [pawn - 8, 0], black_to_move: true, ..*self }.canonical(table.endgame);
+            match table.values[promoted.index(table.endgame)] {
+                DRAW | ILLEGAL => None,
+                value => Some(value)
+            }
+        }).min().filter(|_| self.is_legal(Endgame::Kpk, moves))
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct Bitbase {
+    endgame: Endgame,
+    values: Vec<u8>
+}
+
+impl Bitbase {
+    pub fn compute(endgame: Endgame, moves: &LoadMoves) -> Bitbase {
+        // a pawn wins by promoting, so those tables are needed first
+        let promotions = if endgame.has_pawns() {
+            vec![shared_bitbase(Endgame::Kqk, moves), shared_bitbase(Endgame::Krk, moves)]
+        }
+        else {Vec::new()};
+        let size = endgame.size();
+        let mut values = vec![DRAW; size];
+        // the moves of the weak side that do not lose yet
+        let mut remaining = vec![0u8; size];
+        // the positions that are decided after so many plies, in the order they are found
+        let mut queue: Vec<Vec<u32>> = vec![Vec::new(); ILLEGAL as usize - 1];
+        for (index, value) in values.iter_mut().enumerate() {
+            let placement = Placement::decode(endgame, index);
+            if !placement.is_legal(endgame, moves) {
+                *value = ILLEGAL;
+                continue
+            }
+            if placement.black_to_move {
+                match placement.black_moves(endgame, moves) {
+                    Some(0) if placement.attacked_by_white(endgame, moves, placement.black_king, placement.occupied(endgame), None) => {
+                        queue[0].push(index as u32)
+                    }
+                    Some(count) if count > 0 => remaining[index] = count,
+                    _ => remaining[index] = CANNOT_LOSE
+                }
+            }
+            else if let Some(plies) = placement.promotion_plies(&promotions, moves) {
+                queue[plies as usize].push(index as u32);
+            }
+        }
+
+        let mut predecessors = Vec::new();
+        for plies in 0..queue.len() {
+            for index in std::mem::take(&mut queue[plies]) {
+                let index = index as usize;
+                if values[index] != DRAW {
+                    continue
+                }
+                values[index] = plies as u8 + 1;
+                let placement = Placement::decode(endgame, index);
+                placement.predecessors(endgame, moves, &mut predecessors);
+                for predecessor in predecessors.iter() {
+                    let predecessor = predecessor.canonical(endgame).index(endgame);
+                    if values[predecessor] != DRAW || plies + 1 >= queue.len() {
+                        continue
+                    }
+                    if placement.black_to_move {
+                        // white moves into a lost position of black
+                        queue[plies + 1].push(predecessor as u32);
+                    }
+                    else if remaining[predecessor] != CANNOT_LOSE {
+                        // every move of black has to lose
+                        remaining[predecessor] -= 1;
+                        if remaining[predecessor] == 0 {
+                            queue[plies + 1].push(predecessor as u32);
+                        }
+                    }
+                }
+            }
+        }
+        Bitbase { endgame, values }
+    }
+    pub fn load_file(filepath: &str) -> Result<Bitbase, Error> {
+        let bytes = fs::read(filepath)?;
+        if bytes.len() < 6 || &bytes[0..4] != MAGIC || bytes[4] != VERSION {
+            return Err(Error::new(ErrorKind::InvalidData, "not a bitbase file"))
+        }
+        let endgame = *Endgame::ALL.get(bytes[5] as usize).ok_or_else(|| Error::new(ErrorKind::InvalidData, "unknown endgame"))?;
+        if bytes.len() - 6 != endgame.size() {
+            return Err(Error::new(ErrorKind::InvalidData, "the bitbase has the wrong size"))
+        }
+        Ok(Bitbase { endgame, values: bytes[6..].to_vec() })
+    }
+    pub fn save_file(&self, filepath: &str) -> Result<(), Error> {
+        let mut bytes = Vec::with_capacity(6 + self.values.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(Endgame::ALL.iter().position(|endgame| *endgame == self.endgame).unwrap() as u8);
+        bytes.extend_from_slice(&self.values);
+        fs::write(filepath, bytes)
+    }
+    pub fn endgame(&self) -> Endgame {
+        self.endgame
+    }
+    pub fn probe(&self, position: &Position) -> Option<BitbaseResult> {
+        // the result for the side to move, None when the position is not of this endgame
+        let (endgame, white_strong) = Endgame::of(position)?;
+        if endgame != self.endgame {
+            return None
+        }
+        let (strong, weak, flip) = if white_strong {
+            (&position.white_pieces, &position.black_pieces, 0)
+        }
+        else {
+            (&position.black_pieces, &position.white_pieces, 56)
+        };
+        let mut pieces = [0; 2];
+        for (piece, piece_type) in pieces.iter_mut().zip(endgame.pieces()) {
+            *piece = strong.piece_type2bb(piece_type).trailing_zeros() as usize ^ flip;
+        }
+        let weak_to_move = matches!(position.to_move, ToMove::Black) == white_strong;
+        let placement = Placement {
+            white_king: strong.get_bb_king().trailing_zeros() as usize ^ flip,
+            black_king: weak.get_bb_king().trailing_zeros() as usize ^ flip,
+            pieces,
+            black_to_move: weak_to_move
+        }.canonical(endgame);
+        match self.values[placement.index(endgame)] {
+            ILLEGAL => None,
+            DRAW => Some(BitbaseResult::Draw),
+            value if weak_to_move => Some(BitbaseResult::Loss(value - 1)),
+            value => Some(BitbaseResult::Win(value - 1))
+        }
+    }
+    pub fn load_or_generate(endgame: Endgame, moves: &LoadMoves) -> Bitbase {
+        Bitbase::load_file(&format!("{}/{}.bin", DEFAULT_BITBASE_DIR, endgame.name()))
+            .ok()
+            .filter(|bitbase| bitbase.endgame == endgame)
+            .unwrap_or_else(|| Bitbase::compute(endgame, moves))
+    }
+}
+
+// the tables of the search, every one is loaded or generated once and then shared by all computers
+static BITBASES: [OnceLock<Bitbase>; 4] = [OnceLock::new(), OnceLock::new(), OnceLock::new(), OnceLock::new()];
+static PREPARE_BITBASES: Once = Once::new();
+
+fn slot(endgame: Endgame) -> usize {
+    Endgame::ALL.iter().position(|other| *other == endgame).unwrap()
+}
+
+fn shared_bitbase(endgame: Endgame, moves: &LoadMoves) -> &'static Bitbase {
+    // waits for the table when another thread is already generating it
+    BITBASES[slot(endgame)].get_or_init(|| Bitbase::load_or_generate(endgame, moves))
+}
+
+pub fn prepare_bitbases(moves: &LoadMoves) {
+    // Loads or generates every table on a background thread the first time it is called, generating the
+    // bishop and knight table takes seconds, which a search can not wait for. The smaller tables come first.
+    PREPARE_BITBASES.call_once(|| {
+        let moves = moves.clone();
+        thread::spawn(move || {
+            for endgame in [Endgame::Kqk, Endgame::Krk, Endgame::Kpk, Endgame::Kbnk] {
+                shared_bitbase(endgame, &moves);
+            }
+        });
+    });
+}
+
+pub fn bitbase_score(position: &Position) -> Option<i32> {
+    // The score of a position of one of the endgames, positive is good for white. A won position gets a
+    // higher score the closer the mate is, so that the search makes progress towards it. Tables that are
+    // not ready yet are skipped, the search never waits for them.
+    let occupied = [&position.white_pieces, &position.black_pieces].iter().fold(0, |occupied, pieces| {
+        occupied | pieces.get_bb_pawns() | pieces.get_bb_knights() | pieces.get_bb_bishops() | pieces.get_bb_rooks()
+            | pieces.get_bb_queens() | pieces.get_bb_king()
+    });
+    if occupied.count_ones() > 4 {
+        return None
+    }
+    let (endgame, _) = Endgame::of(position)?;
+    let score = match BITBASES[slot(endgame)].get()?.probe(position)? {
+        BitbaseResult::Win(plies) => KNOWN_WIN_SCORE - plies as i32,
+        BitbaseResult::Draw => 0,
+        BitbaseResult::Loss(plies) => -KNOWN_WIN_SCORE + plies as i32
+    };
+    Some(match position.to_move {
+        ToMove::White => score,
+        ToMove::Black => -score
+    })
+}
+
+#[pymethods]
+impl Bitbase {
+    #[staticmethod]
+    pub fn generate(py: Python, name: &str) -> PyResult<Bitbase> {
+        let endgame = Endgame::from_name(name)
+            .ok_or_else(|| PyValueError::new_err("the endgame must be one of KPK, KRK, KQK or KBNK"))?;
+        let moves = LoadMoves::new();
+        Ok(py.allow_threads(|| Bitbase::compute(endgame, &moves)))
+    }
+    #[staticmethod]
+    pub fn load(filepath: &str) -> PyResult<Bitbase> {
+        Bitbase::load_file(filepath).map_err(|err| PyIOError::new_err(err.to_string()))
+    }
+    pub fn save(&self, filepath: &str) -> PyResult<()> {
+        self.save_file(filepath).map_err(|err| PyIOError::new_err(err.to_string()))
+    }
+    pub fn get_name(&self) -> String {
+        self.endgame.name().to_string()
+    }
+    pub fn get_result(&self, chessboard: &Chessboard) -> Option<(i32, u8)> {
+        // 1, 0 or -1 for a win, draw or loss of the side to move and the plies until mate
+        self.probe(chessboard.get_position()).map(|result| match result {
+            BitbaseResult::Win(plies) => (1, plies),
+            BitbaseResult::Draw => (0, 0),
+            BitbaseResult::Loss(plies) => (-1, plies)
+        })
+    }
+    pub fn get_longest_mate(&self) -> u8 {
+        // in plies
+        self.values.iter().filter(|value| **value != ILLEGAL).max().map_or(0, |value| value.saturating_sub(1))
+    }
+    pub fn __len__(&self) -> usize {
+        self.values.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval_params::EvalParams;
+    use crate::evaluation::*;
+    use crate::test_helper::*;
+
+    fn result(fen: &str) -> Option<BitbaseResult> {
+        let chessboard = chessboard(fen);
+        let (endgame, _) = Endgame::of(chessboard.get_position())?;
+        shared_bitbase(endgame, chessboard.get_pseudo_moves()).probe(chessboard.get_position())
+    }
+
+    #[test]
+    fn pawn_endgames() {
+        assert!(matches!(result("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Some(BitbaseResult::Win(_))));
+        assert!(matches!(result("8/8/8/3p4/3k4/8/3K4/8 b - - 0 1"), Some(BitbaseResult::Draw)));
+        // stalemate
+        assert_eq!(result("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), Some(BitbaseResult::Draw));
+        // the king reaches the corner in front of the rook pawn
+        assert_eq!(result("k7/8/8/8/8/8/P7/7K w - - 0 1"), Some(BitbaseResult::Draw));
+    }
+
+    #[test]
+    fn longest_mates() {
+        let moves = chessboard(START_FEN).get_pseudo_moves().clone();
+        // the weak side moves first and is mated after 10 and 16 moves of the strong side
+        assert_eq!(shared_bitbase(Endgame::Kqk, &moves).get_longest_mate(), 20);
+        assert_eq!(shared_bitbase(Endgame::Krk, &moves).get_longest_mate(), 32);
+        assert_eq!(result("8/8/8/8/8/8/8/kK5Q w - - 0 1"), None);
+        assert_eq!(result("k7/8/1K6/8/8/8/7Q/8 w - - 0 1"), Some(BitbaseResult::Win(1)));
+        assert_eq!(result("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"), Some(BitbaseResult::Loss(0)));
+    }
+
+    #[test]
+    fn evaluation_and_trace_use_the_bitbase() {
+        let chessboard = chessboard("8/8/8/3k4/8/8/2K5/7q w - - 0 1");
+        let (position, moves) = (chessboard.get_position(), chessboard.get_pseudo_moves());
+        shared_bitbase(Endgame::Kqk, moves);
+        let score = bitbase_score(position).unwrap();
+        assert!(score < -KNOWN_WIN_SCORE + 64);
+        let params = EvalParams::default();
+        assert_eq!(evaluate(position, moves, &params, &mut PawnHashTable::new(), true), score);
+        assert_eq!(evaluate_trace(position, moves, &params, true).score, score);
+        assert_ne!(evaluate_trace(position, moves, &params, false).score, score);
+    }
+
+    #[test]
+    fn saved_bitbase_loads_the_same() {
+        let moves = chessboard(START_FEN).get_pseudo_moves().clone();
+        let bitbase = shared_bitbase(Endgame::Krk, &moves);
+        let filepath = std::env::temp_dir().join("rustchess_krk.bin");
+        let filepath = filepath.to_str().unwrap();
+        bitbase.save_file(filepath).unwrap();
+        let loaded = Bitbase::load_file(filepath);
+        std::fs::remove_file(filepath).unwrap();
+        assert_eq!(loaded.unwrap().values, bitbase.values);
+    }
+}
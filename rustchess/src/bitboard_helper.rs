@@ -35,8 +35,7 @@ pub fn subtract_bb(bb: u64, subtract: u64) -> u64 {
 
 pub fn get_lsb(bb: u64) -> u64 {
     // gets the Least Significant bit
-    let bb = bb as i64;
-    (bb & -bb) as u64
+    bb & bb.wrapping_neg()
 }
 
 pub fn get_lsb_index(bb: u64) -> usize {
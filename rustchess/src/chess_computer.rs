@@ -3,233 +3,1298 @@ use serde::de;
 use crate::chessboard;
 use crate::chessboard::*;
 use crate::chessboard_helper::*;
-use crate::bitboard_helper::*;
 use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 use std::cmp;
+use std::time::Instant;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use crate::search_helper::*;
+use crate::transposition_table::*;
+use crate::evaluation::*;
+use crate::eval_params::EvalParams;
+use crate::nnue::*;
+use crate::polyglot::*;
+use crate::syzygy::*;
+use crate::bitbase::*;
 
 pub trait RecieveAndReturnMove {
     // recieves a mutable reference to the current chessboard and then returns a new move 
     fn return_move(&mut self, chessboard: &mut Chessboard) -> Move;
+    // searches the current position until one of the limits is reached or the stop flag of control is set,
+    // computers that do not search simply return their move
+    fn search(&mut self, chessboard: &mut Chessboard, _limits: &SearchLimits, _control: &SearchControl) -> SearchResult {
+        let mut result = SearchResult::new();
+        result.best_move = Some(self.return_move(chessboard));
+        result.pv = result.best_move.into_iter().collect();
+        result
+    }
+    // the score, principal variation and statistics of the last search, if the computer searches at all
+    fn last_search_result(&self) -> Option<SearchResult> {
+        None
+    }
+    // sets an option of the computer by name, computers without options reject everything
+    fn set_option(&mut self, _name: &str, _value: &str) -> Result<(), InvalidOptionError> {
+        Err(InvalidOptionError)
+    }
+    // replaces the weights of the evaluation, computers without an evaluation reject this
+    fn set_eval_params(&mut self, _params: EvalParams) -> Result<(), InvalidOptionError> {
+        Err(InvalidOptionError)
+    }
+    fn eval_params(&self) -> Option<EvalParams> {
+        None
+    }
+    // forgets what was learned in the previous game, like the hash table, so that the next game does not
+    // depend on it. The options stay as they are
+    fn new_game(&mut self) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RandomPolicy {
+    // every legal move is equally likely
+    Uniform,
+    // captures and checks are more likely than quiet moves
+    Tactical,
+    // moves are sampled from a softmax over the evaluation after the move
+    Softmax
 }
 
 #[pyclass]
 pub struct RandomComputer {
+    // Plays random moves, which makes it a cheap opponent for tests and for generating data. With a seed the
+    // same position always gets the same move sequence, without one the generator is seeded from the system.
+    rng: StdRng,
+    policy: RandomPolicy,
+    // the tactical policy gives a move weight 1 plus these weights when it captures or gives check
+    capture_weight: f64,
+    check_weight: f64,
+    // in centipawns, the higher the temperature the more the softmax policy plays worse moves
+    temperature: f64,
+    params: EvalParams,
+    pawn_table: PawnHashTable
 }
 impl RecieveAndReturnMove for RandomComputer {
     fn return_move(&mut self, chessboard: &mut Chessboard) -> Move {
         // now we get all legal moves
         let moves = chessboard.all_moves();
-        // choose a random move
-        *moves.choose(&mut rand::thread_rng()).expect("No moves available.")
+        if self.policy == RandomPolicy::Uniform {
+            return *moves.choose(&mut self.rng).expect("No moves available.")
+        }
+        // choose a random move with the weights of the policy
+        let weights = self.move_weights(chessboard, &moves);
+        let weighted_moves: Vec<(Move, f64)> = moves.into_iter().zip(weights).collect();
+        weighted_moves.choose_weighted(&mut self.rng, |(_, weight)| *weight).expect("No moves available.").0
+    }
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), InvalidOptionError> {
+        let number = |value: &str| value.parse::<f64>().ok().filter(|number| number.is_finite() && *number >= 0.0).ok_or(InvalidOptionError);
+        match (name, self.policy) {
+            ("seed", _) => self.rng = StdRng::seed_from_u64(value.parse().map_err(|_| InvalidOptionError)?),
+            ("capture_weight", RandomPolicy::Tactical) => self.capture_weight = number(value)?,
+            ("check_weight", RandomPolicy::Tactical) => self.check_weight = number(value)?,
+            ("temperature", RandomPolicy::Softmax) => {
+                let temperature = number(value)?;
+                if temperature == 0.0 {
+                    return Err(InvalidOptionError)
+                }
+                self.temperature = temperature;
+            }
+            _ => return Err(InvalidOptionError)
+        }
+        Ok(())
+    }
+    fn set_eval_params(&mut self, params: EvalParams) -> Result<(), InvalidOptionError> {
+        // only the softmax policy evaluates
+        if self.policy != RandomPolicy::Softmax {
+            return Err(InvalidOptionError)
+        }
+        self.params = params;
+        Ok(())
+    }
+    fn eval_params(&self) -> Option<EvalParams> {
+        if self.policy == RandomPolicy::Softmax {Some(self.params.clone())} else {None}
+    }
+}
+impl RandomComputer {
+    pub fn with_policy(policy: RandomPolicy) -> RandomComputer {
+        RandomComputer { rng: StdRng::from_entropy(), policy, capture_weight: 4.0, check_weight: 2.0, temperature: 50.0,
+            params: EvalParams::new(), pawn_table: PawnHashTable::new() }
+    }
+    fn move_weights(&mut self, chessboard: &mut Chessboard, moves: &[Move]) -> Vec<f64> {
+        let mut scores = Vec::with_capacity(moves.len());
+        for new_move in moves {
+            let capture = BasicTreeSearchComputer::captured_piece(chessboard.get_position(), new_move) != PieceType::EmptySquare;
+            chessboard.move_piece(new_move).unwrap();
+            let score = match self.policy {
+                RandomPolicy::Tactical => {
+                    let mut weight = 1.0;
+                    if capture {
+                        weight += self.capture_weight;
+                    }
+                    if chessboard.is_in_check() {
+                        weight += self.check_weight;
+                    }
+                    weight
+                }
+                _ => {
+                    // a mate is always played and a stalemate is a draw, otherwise the score of the position
+                    // for the player that moved
+                    if chessboard.all_moves().is_empty() {
+                        if chessboard.is_in_check() {MATE_SCORE as f64} else {0.0}
+                    }
+                    else {
+                        let eval = evaluate(chessboard.get_position(), chessboard.get_pseudo_moves(), &self.params, &mut self.pawn_table, false);
+                        match chessboard.get_to_move() {
+                            ToMove::White => -eval as f64,
+                            ToMove::Black => eval as f64
+                        }
+                    }
+                }
+            };
+            chessboard.undo();
+            scores.push(score);
+        }
+        if self.policy == RandomPolicy::Tactical {
+            return scores
+        }
+        let best = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        scores.iter().map(|score| ((score - best) / self.temperature).exp()).collect()
     }
 }
 #[pymethods]
 impl RandomComputer {
     #[new]
     pub fn new() -> RandomComputer {
-        RandomComputer {}
-    }
-}
-
-const PAWN_SCORE_MASK: [i32; 64] = [
-0,  0,  0,  0,  0,  0,  0,  0,
-50, 50, 50, 50, 50, 50, 50, 50,
-10, 10, 20, 30, 30, 20, 10, 10,
- 5,  5, 10, 25, 25, 10,  5,  5,
- 0,  0,  0, 20, 20,  0,  0,  0,
- 5, -5,-10,  0,  0,-10, -5,  5,
- 5, 10, 10,-20,-20, 10, 10,  5,
- 0,  0,  0,  0,  0,  0,  0,  0];
-
-const KNIGHT_SCORE_MASK: [i32; 64] = [
--50,-40,-30,-30,-30,-30,-40,-50,
--40,-20,  0,  0,  0,  0,-20,-40,
--30,  0, 10, 15, 15, 10,  0,-30,
--30,  5, 15, 20, 20, 15,  5,-30,
--30,  0, 15, 20, 20, 15,  0,-30,
--30,  5, 10, 15, 15, 10,  5,-30,
--40,-20,  0,  5,  5,  0,-20,-40,
--50,-40,-30,-30,-30,-30,-40,-50];
-
-const BISHOP_SCORE_MASK: [i32; 64] = [
--20,-10,-10,-10,-10,-10,-10,-20,
--10,  0,  0,  0,  0,  0,  0,-10,
--10,  0,  5, 10, 10,  5,  0,-10,
--10,  5,  5, 10, 10,  5,  5,-10,
--10,  0, 10, 10, 10, 10,  0,-10,
--10, 10, 10, 10, 10, 10, 10,-10,
--10,  5,  0,  0,  0,  0,  5,-10,
--20,-10,-10,-10,-10,-10,-10,-20];
-
-const ROOK_SCORE_MASK: [i32; 64] = [  
-0,  0,  0,  0,  0,  0,  0,  0,
-5, 10, 10, 10, 10, 10, 10,  5,
--5,  0,  0,  0,  0,  0,  0, -5,
--5,  0,  0,  0,  0,  0,  0, -5,
--5,  0,  0,  0,  0,  0,  0, -5,
--5,  0,  0,  0,  0,  0,  0, -5,
--5,  0,  0,  0,  0,  0,  0, -5,
-0,  0,  0,  5,  5,  0,  0,  0];
-
-const QUEEN_SCORE_MASK: [i32; 64] = [
--20,-10,-10, -5, -5,-10,-10,-20,
--10,  0,  0,  0,  0,  0,  0,-10,
--10,  0,  5,  5,  5,  5,  0,-10,
- -5,  0,  5,  5,  5,  5,  0, -5,
-  0,  0,  5,  5,  5,  5,  0, -5,
--10,  5,  5,  5,  5,  5,  0,-10,
--10,  0,  5,  0,  0,  0,  0,-10,
--20,-10,-10, -5, -5,-10,-10,-20];
-
-const KING_SCORE_MIDDLE_GAME: [i32; 64] = [
--30,-40,-40,-50,-50,-40,-40,-30,
--30,-40,-40,-50,-50,-40,-40,-30,
--30,-40,-40,-50,-50,-40,-40,-30,
--30,-40,-40,-50,-50,-40,-40,-30,
--20,-30,-30,-40,-40,-30,-30,-20,
--10,-20,-20,-20,-20,-20,-20,-10,
- 20, 20,  0,  0,  0,  0, 20, 20,
- 20, 30, 10,  0,  0, 10, 30, 20];
-
- const KING_SCORE_END_GAME: [i32; 64] = [
--50,-40,-30,-20,-20,-30,-40,-50,
- -30,-20,-10,  0,  0,-10,-20,-30,
- -30,-10, 20, 30, 30, 20,-10,-30,
- -30,-10, 30, 40, 40, 30,-10,-30,
- -30,-10, 30, 40, 40, 30,-10,-30,
- -30,-10, 20, 30, 30, 20,-10,-30,
- -30,-30,  0,  0,  0,  0,-30,-30,
- -50,-30,-30,-30,-30,-30,-30,-50];
+        RandomComputer::with_policy(RandomPolicy::Uniform)
+    }
+}
+
+// piece values used for ordering captures, most valuable victim first and least valuable attacker second
+fn order_value(piece_type: &PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        PieceType::King => 2000,
+        PieceType::EmptySquare => 0
+    }
+}
 
 pub struct BasicTreeSearchComputer {
     final_move: Option<Move>, // when computer has already calculated move that it will play
     best_move: Option<Move>,
-    depth: u8
+    depth: u8,
+    options: SearchOptions,
+    // the number of threads that search together, every thread but the main one is a helper
+    threads: usize,
+    // shared by all threads of the search and kept between searches
+    tt: Arc<TranspositionTable>,
+    // the number of best lines the search finds, more than one is only useful for analysis
+    multipv: usize,
+    // helpers with an odd id search one ply deeper than the main thread, so the threads don't all do the same
+    helper_id: usize,
+    // bookkeeping of the current search
+    pv_table: PvTable,
+    // the principal variation of the previous iteration, which is searched first in the next one
+    previous_pv: Vec<Move>,
+    // when to stop the current search
+    control: SearchControl,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    stopped: bool,
+    // root moves that are skipped because they already lead one of the better multipv lines
+    excluded_root_moves: Vec<Move>,
+    // the weights of the evaluation, shared with the helper threads
+    params: Arc<EvalParams>,
+    // caches the evaluation of pawn structures
+    pawn_table: PawnHashTable,
+    // when there is a network it replaces the hand written evaluation, the accumulators follow the moves of the search
+    network: Option<Arc<NnueNetwork>>,
+    accumulators: AccumulatorStack,
+    // endgame tablebases, used at the root to only search moves that keep the best result and in the search
+    // to stop at positions whose result is known
+    tablebase: Option<Arc<Tablebase>>,
+    tablebase_root_moves: Vec<Move>,
+    // two quiet moves per ply that caused a beta cutoff
+    killer_moves: [[Option<Move>; 2]; MAX_PLY],
+    nodes: u64,
+    seldepth: u8,
+    last_result: Option<SearchResult>
 }
 
 impl RecieveAndReturnMove for BasicTreeSearchComputer {
     fn return_move(&mut self, chessboard: &mut Chessboard) -> Move {
-        let depth = 5;
-        self.depth = depth;
-        let eval = match chessboard.get_to_move() {
-            ToMove::White => self.minimax(chessboard, depth, -100000, 100000, true),
-            ToMove::Black => self.minimax(chessboard, depth, -100000, 100000, false)
-        };
-        println!("current evaluation: {}", eval);
-        self.best_move.unwrap()
+        let limits = SearchLimits::from_depth(self.depth);
+        self.search(chessboard, &limits, &SearchControl::without_stop()).best_move.unwrap()
+    }
+    fn search(&mut self, chessboard: &mut Chessboard, limits: &SearchLimits, control: &SearchControl) -> SearchResult {
+        self.tt.new_search();
+        if self.options.bitbases {
+            prepare_bitbases(chessboard.get_pseudo_moves());
+        }
+        let tablebase_score = self.restrict_to_tablebase_moves(chessboard);
+        let mut result = self.search_threads(chessboard, limits, control);
+        // the search can hardly ever see the end of a tablebase win, so we report what the tables say
+        if let Some(score) = tablebase_score {
+            if !is_mate_score(result.score) {
+                result.score = score;
+            }
+        }
+        self.tablebase_root_moves.clear();
+        self.last_result = Some(result.clone());
+        result
+    }
+    fn last_search_result(&self) -> Option<SearchResult> {
+        self.last_result.clone()
+    }
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), InvalidOptionError> {
+        match name {
+            "depth" => {
+                self.depth = value.parse().map_err(|_| InvalidOptionError)?;
+                Ok(())
+            }
+            "threads" => {
+                let threads: usize = value.parse().map_err(|_| InvalidOptionError)?;
+                if threads == 0 {
+                    return Err(InvalidOptionError)
+                }
+                self.threads = threads;
+                Ok(())
+            }
+            "multipv" => {
+                let multipv: usize = value.parse().map_err(|_| InvalidOptionError)?;
+                if multipv == 0 {
+                    return Err(InvalidOptionError)
+                }
+                self.multipv = multipv;
+                Ok(())
+            }
+            "eval_file" => {
+                // a json file with evaluation parameters, see EvalParams
+                let params = EvalParams::load_file(value).map_err(|_| InvalidOptionError)?;
+                self.set_eval_params(params)
+            }
+            "nnue_file" => {
+                // a network file to evaluate with, an empty value goes back to the hand written evaluation
+                if value.is_empty() {
+                    self.set_network(None);
+                    return Ok(())
+                }
+                let network = NnueNetwork::load_file(value).map_err(|_| InvalidOptionError)?;
+                self.set_network(Some(network));
+                Ok(())
+            }
+            "syzygy_path" => {
+                // directories with syzygy tables separated like the PATH variable, an empty value turns them off
+                if value.is_empty() {
+                    self.tablebase = None;
+                    return Ok(())
+                }
+                let tablebase = Tablebase::open(value).map_err(|_| InvalidOptionError)?;
+                self.tablebase = Some(Arc::new(tablebase));
+                Ok(())
+            }
+            "hash" => {
+                // size of the transposition table in megabytes
+                let size_mb: usize = value.parse().map_err(|_| InvalidOptionError)?;
+                self.tt = Arc::new(TranspositionTable::new(size_mb));
+                Ok(())
+            }
+            _ => self.options.set_option(name, value)
+        }
+    }
+    fn set_eval_params(&mut self, params: EvalParams) -> Result<(), InvalidOptionError> {
+        // the cached pawn scores and search results were computed with the old parameters
+        self.params = Arc::new(params);
+        self.pawn_table = PawnHashTable::new();
+        self.tt = Arc::new(TranspositionTable::new(self.tt.size_mb()));
+        Ok(())
+    }
+    fn eval_params(&self) -> Option<EvalParams> {
+        Some((*self.params).clone())
+    }
+    fn new_game(&mut self) {
+        self.tt.clear();
+        self.killer_moves = [[None; 2]; MAX_PLY];
+        self.previous_pv.clear();
+        self.final_move = None;
+        self.best_move = None;
+        self.last_result = None;
     }
 }
 
 impl BasicTreeSearchComputer {
     pub fn new() -> BasicTreeSearchComputer {
-        BasicTreeSearchComputer {final_move: None, best_move: None, depth: 4}
+        BasicTreeSearchComputer::with_options(SearchOptions::new())
     }
-    pub fn static_evaluate(position: &Position) -> i32 {
-        // evaluate the position it is given, positive evaluation means good for white
-        // while negative evaluation means good for black
-        let mut eval = 0;
-        // get all bitboards as vecs
-        let black_pieces = &position.black_pieces;
-        let white_pieces = &position.white_pieces;
-        let bb = bb_to_vec(black_pieces.get_bb_bishops());
-        let bn = bb_to_vec(black_pieces.get_bb_knights());
-        let br = bb_to_vec(black_pieces.get_bb_rooks());
-        let bq = bb_to_vec(black_pieces.get_bb_queens());
-        let bp = bb_to_vec(black_pieces.get_bb_pawns());
-        let bk = bb_to_vec(black_pieces.get_bb_king());
-        let wb = bb_to_vec(white_pieces.get_bb_bishops());
-        let wn = bb_to_vec(white_pieces.get_bb_knights());
-        let wr = bb_to_vec(white_pieces.get_bb_rooks());
-        let wq = bb_to_vec(white_pieces.get_bb_queens());
-        let wp = bb_to_vec(white_pieces.get_bb_pawns());
-        let wk = bb_to_vec(white_pieces.get_bb_king());
-
-        // subtract value of black pieces
-        eval -= bb.len() as i32 * 330;
-        eval -= bn.len() as i32 * 320;
-        eval -= br.len() as i32 * 500;
-        eval -= bq.len() as i32 * 900;
-        eval -= bp.len() as i32 * 100;
-        // subtract the value of all white pieces on the board
-        eval += wb.len() as i32 * 330;
-        eval += wn.len() as i32 * 320;
-        eval += wr.len() as i32 * 500;
-        eval += wq.len() as i32 * 900;
-        eval += wp.len() as i32 * 100;
-
-        let mut total_board_score = 0;
-        total_board_score += bb.len() as i32 * 330;
-        total_board_score += bn.len() as i32 * 320;
-        total_board_score += br.len() as i32 * 500;
-        total_board_score += bq.len() as i32 * 900;
-        total_board_score += bp.len() as i32 * 100;
-        total_board_score += wb.len() as i32 * 330;
-        total_board_score += wn.len() as i32 * 320;
-        total_board_score += wr.len() as i32 * 500;
-        total_board_score += wq.len() as i32 * 900;
-        total_board_score += wp.len() as i32 * 100;
-        // 8000 is the total board score at the starting position
-        let game_progress = 1.0 - (total_board_score as f64 / 8000.0);
-
-        eval += bb.iter().map(|i| BISHOP_SCORE_MASK[63 - *i as usize]).sum::<i32>();
-        eval += bn.iter().map(|i| KNIGHT_SCORE_MASK[63 - *i as usize]).sum::<i32>();
-        eval += br.iter().map(|i| ROOK_SCORE_MASK[63 - *i as usize]).sum::<i32>();
-        eval += bq.iter().map(|i| QUEEN_SCORE_MASK[63 - *i as usize]).sum::<i32>();
-        eval += bp.iter().map(|i| PAWN_SCORE_MASK[63 - *i as usize]).sum::<i32>();
-        eval += bk.iter().map(|i| (((1.0-game_progress) * KING_SCORE_MIDDLE_GAME[63 - *i as usize] as f64)
-         + (game_progress * KING_SCORE_END_GAME[63 - *i as usize] as f64)) as i32).sum::<i32>();
-
-        eval += wb.iter().map(|i| BISHOP_SCORE_MASK[*i as usize]).sum::<i32>();
-        eval += wn.iter().map(|i| KNIGHT_SCORE_MASK[*i as usize]).sum::<i32>();
-        eval += wr.iter().map(|i| ROOK_SCORE_MASK[*i as usize]).sum::<i32>();
-        eval += wq.iter().map(|i| QUEEN_SCORE_MASK[*i as usize]).sum::<i32>();
-        eval += wp.iter().map(|i| PAWN_SCORE_MASK[*i as usize]).sum::<i32>();
-        eval += wk.iter().map(|i| (((1.0-game_progress) * KING_SCORE_MIDDLE_GAME[*i as usize] as f64)
-         + (game_progress * KING_SCORE_END_GAME[*i as usize] as f64)) as i32).sum::<i32>();
-        eval
-    }
-
-    pub fn minimax(&mut self, chessboard: &mut Chessboard, depth: u8, mut alpha: i32, mut beta: i32, maximizing_player: bool) -> i32 {
-        // depth is how far ahead we want to search, maximizing_player deals with either white to move or black
-        if depth == 0 {
-            return BasicTreeSearchComputer::static_evaluate(chessboard.get_position())
-        }
-
-        if maximizing_player {
-            let mut max_eval = -100000;
-            for new_move in chessboard.all_moves().iter() {
-                chessboard.move_piece(new_move).unwrap();
-                let eval = self.minimax(chessboard, depth - 1, alpha, beta, false);
-                max_eval = cmp::max(max_eval, eval);
-                alpha = cmp::max(alpha, eval);
-                if beta <= alpha {
-                    chessboard.undo();
+    pub fn with_options(options: SearchOptions) -> BasicTreeSearchComputer {
+        BasicTreeSearchComputer {final_move: None, best_move: None, depth: 5, options, threads: 1,
+            tt: Arc::new(TranspositionTable::new(16)), multipv: 1, helper_id: 0, pv_table: PvTable::new(),
+            previous_pv: Vec::new(), control: SearchControl::without_stop(), deadline: None, node_limit: None, stopped: false,
+            excluded_root_moves: Vec::new(), params: Arc::new(EvalParams::new()), pawn_table: PawnHashTable::new(),
+            network: None, accumulators: AccumulatorStack::new(), tablebase: None, tablebase_root_moves: Vec::new(),
+            killer_moves: [[None; 2]; MAX_PLY], nodes: 0, seldepth: 0, last_result: None}
+    }
+
+    fn search_threads(&mut self, chessboard: &mut Chessboard, limits: &SearchLimits, control: &SearchControl) -> SearchResult {
+        if self.threads <= 1 {
+            return self.iterative_deepening(chessboard, limits, control)
+        }
+        // Lazy SMP: the helpers search the same position on their own copy of the board until the main thread
+        // is done. They only help through the shared transposition table, which fills up faster and lets the
+        // main thread cut off more. Only the result of the main thread is used.
+        let helper_stop = Arc::new(AtomicBool::new(false));
+        let helper_limits = SearchLimits::new(None, None, None, None, None, None, None, None, true);
+        let now = Instant::now();
+        let mut result = thread::scope(|scope| {
+            let helpers: Vec<_> = (1..self.threads).map(|helper_id| {
+                let mut helper = self.helper(helper_id);
+                let mut helper_board = chessboard.clone();
+                let helper_control = SearchControl::new(Arc::clone(&helper_stop));
+                scope.spawn(move || helper.iterative_deepening(&mut helper_board, &helper_limits, &helper_control).nodes)
+            }).collect();
+            let mut result = self.iterative_deepening(chessboard, limits, control);
+            helper_stop.store(true, Ordering::Relaxed);
+            for helper in helpers {
+                result.nodes += helper.join().unwrap_or(0);
+            }
+            result
+        });
+        result.nps = (result.nodes as f64 / now.elapsed().as_secs_f64().max(0.001)) as u64;
+        result
+    }
+
+    fn iterative_deepening(&mut self, chessboard: &mut Chessboard, limits: &SearchLimits, control: &SearchControl) -> SearchResult {
+        // the search of a single thread
+        self.best_move = None;
+        self.nodes = 0;
+        self.seldepth = 0;
+        self.pv_table = PvTable::new();
+        self.previous_pv = Vec::new();
+        self.killer_moves = [[None; 2]; MAX_PLY];
+        self.control = control.clone();
+        self.stopped = false;
+        self.node_limit = limits.node_limit();
+        self.reset_accumulators(chessboard);
+        let now = Instant::now();
+        let time_budget = limits.time_budget(chessboard.get_to_move());
+        self.deadline = time_budget.map(|budget| now + budget);
+
+        // with multipv we can never show more lines than there are legal moves
+        let multipv = cmp::max(cmp::min(self.multipv, chessboard.all_moves().len()), 1);
+
+        // iterative deepening, every iteration starts with the best lines of the previous one
+        let mut result = SearchResult::new();
+        let mut lines: Vec<PvLine> = Vec::new();
+        let mut score = 0;
+        for depth in 1..=limits.max_depth() {
+            let depth = cmp::min(depth + (self.helper_id % 2) as u8, limits.max_depth());
+            // every next line is searched without the first moves of the lines that were already found
+            let mut new_lines = Vec::new();
+            self.excluded_root_moves.clear();
+            for line_number in 0..multipv {
+                let previous_line = lines.get(line_number);
+                self.previous_pv = previous_line.map(|line| line.pv.clone()).unwrap_or_default();
+                let previous_score = previous_line.map_or(score, |line| line.score);
+                let line_score = self.aspiration_search(chessboard, depth as i32, previous_score);
+                if self.stopped {
                     break
                 }
-                if depth == self.depth && max_eval == eval {
-                    self.best_move = Some(*new_move);
+                let pv = self.pv_table.line(0);
+                match pv.first() {
+                    Some(first_move) => self.excluded_root_moves.push(*first_move),
+                    None => break
+                }
+                new_lines.push(PvLine { pv, score: line_score });
+            }
+            self.excluded_root_moves.clear();
+            // the result of an interrupted iteration cannot be trusted, so we keep the previous one
+            if self.stopped && (self.best_move.is_some() || new_lines.is_empty()) {
+                break
+            }
+            new_lines.sort_by_key(|line| -line.score);
+            lines = new_lines;
+            score = lines[0].score;
+            self.previous_pv = lines[0].pv.clone();
+            self.best_move = self.previous_pv.first().copied();
+            let elapsed = now.elapsed();
+            result.best_move = self.best_move;
+            result.pv = self.previous_pv.clone();
+            result.score = score;
+            result.lines = lines.clone();
+            result.depth = depth;
+            result.seldepth = self.seldepth;
+            result.nodes = self.nodes;
+            result.time_ms = elapsed.as_millis() as u64;
+            result.nps = (self.nodes as f64 / elapsed.as_secs_f64().max(0.001)) as u64;
+            self.control.send_info(&result);
+            if self.stopped {
+                break
+            }
+            // no need to search any deeper when we have found a forced mate, unless we are told to go on
+            // or the other lines still have to be analysed
+            if is_mate_score(score) && !limits.infinite && multipv == 1 {
+                break
+            }
+            // the next iteration takes longer than all previous ones together, so don't start it when
+            // we have already used up half of our time
+            if let Some(budget) = time_budget {
+                if elapsed > budget / 2 {
+                    break
                 }
-                chessboard.undo();
             }
-            return max_eval
         }
-        else {
-            let mut min_eval = 100000;
-            for new_move in chessboard.all_moves().iter() {
+        // when we were stopped before any iteration finished we still need to return a legal move
+        if self.best_move.is_none() {
+            self.best_move = chessboard.all_moves().first().copied();
+            result.best_move = self.best_move;
+        }
+        result.nodes = self.nodes;
+        self.last_result = Some(result.clone());
+        result
+    }
+
+    fn helper(&self, helper_id: usize) -> BasicTreeSearchComputer {
+        // a computer with the same settings that shares our transposition table
+        let mut helper = BasicTreeSearchComputer::with_options(self.options);
+        helper.tt = Arc::clone(&self.tt);
+        helper.params = Arc::clone(&self.params);
+        helper.network = self.network.clone();
+        helper.tablebase = self.tablebase.clone();
+        helper.tablebase_root_moves = self.tablebase_root_moves.clone();
+        helper.helper_id = helper_id;
+        helper
+    }
+
+    fn aspiration_search(&mut self, chessboard: &mut Chessboard, depth: i32, previous_score: i32) -> i32 {
+        // Searches the root with a small window around the score of the previous iteration. Most of the time
+        // the score hardly changes, in which case the narrow window gives many more cutoffs. When the score
+        // falls outside of the window we widen it on that side and search again.
+        if !self.options.aspiration_windows || depth < 3 || is_mate_score(previous_score) {
+            return self.negamax(chessboard, depth, 0, -INFINITY, INFINITY, true)
+        }
+        let mut delta = 50;
+        let mut alpha = previous_score - delta;
+        let mut beta = previous_score + delta;
+        loop {
+            let score = self.negamax(chessboard, depth, 0, alpha, beta, true);
+            if self.stopped {
+                return score
+            }
+            if score <= alpha {
+                alpha = cmp::max(score - delta, -INFINITY);
+            }
+            else if score >= beta {
+                beta = cmp::min(score + delta, INFINITY);
+            }
+            else {
+                return score
+            }
+            delta *= 2;
+            if delta > 1000 {
+                alpha = -INFINITY;
+                beta = INFINITY;
+            }
+        }
+    }
+
+    pub fn set_network(&mut self, network: Option<NnueNetwork>) {
+        // the search results in the transposition table were found with the old evaluation
+        self.network = network.map(Arc::new);
+        self.tt = Arc::new(TranspositionTable::new(self.tt.size_mb()));
+    }
+
+    fn restrict_to_tablebase_moves(&mut self, chessboard: &mut Chessboard) -> Option<i32> {
+        // When the root is in the tablebases only the moves with the best rank are searched, the search then
+        // picks between them. Returns the score the tables give the root. With more than one line we want to
+        // see all moves, so then the tables are only used inside the search.
+        self.tablebase_root_moves.clear();
+        let tablebase = self.tablebase.as_ref()?;
+        if self.multipv > 1 {
+            return None
+        }
+        let ranked = tablebase.rank_root_moves(chessboard)?;
+        let best_rank = ranked.iter().map(|(_, rank, _)| *rank).max()?;
+        self.tablebase_root_moves = ranked.iter().filter(|(_, rank, _)| *rank == best_rank).map(|(new_move, _, _)| *new_move).collect();
+        // the distance to zeroing is not the distance to mate, the score just prefers wins that are closer
+        let dtz = ranked.iter().filter(|(_, rank, _)| *rank == best_rank).map(|(_, _, dtz)| *dtz).min().unwrap_or(0);
+        Some(if best_rank > 0 {
+            TB_WIN_SCORE - MAX_PLY as i32 - dtz.min(MAX_PLY as i32)
+        }
+        else if best_rank < 0 {
+            -TB_WIN_SCORE + MAX_PLY as i32 - dtz.max(-(MAX_PLY as i32))
+        }
+        else {0})
+    }
+
+    fn reset_accumulators(&mut self, chessboard: &Chessboard) {
+        if let Some(network) = &self.network {
+            self.accumulators.reset(network, chessboard.get_position());
+        }
+    }
+
+    fn make_move(&mut self, chessboard: &mut Chessboard, new_move: &Move) {
+        // every move of the search goes through here so that the accumulators of the network stay up to date
+        match &self.network {
+            Some(network) => {
+                let before = *chessboard.get_position();
                 chessboard.move_piece(new_move).unwrap();
-                let eval = self.minimax(chessboard, depth - 1, alpha, beta, true);
-                min_eval = cmp::min(min_eval, eval);
-                beta = cmp::min(beta, eval);
-                if beta <= alpha {
-                    chessboard.undo();
-                    break
+                self.accumulators.push(network, &before, chessboard.get_position());
+            }
+            None => chessboard.move_piece(new_move).unwrap()
+        }
+    }
+
+    fn make_null_move(&mut self, chessboard: &mut Chessboard) {
+        let before = *chessboard.get_position();
+        chessboard.move_null();
+        if let Some(network) = &self.network {
+            self.accumulators.push(network, &before, chessboard.get_position());
+        }
+    }
+
+    fn undo_move(&mut self, chessboard: &mut Chessboard) {
+        chessboard.undo();
+        if self.network.is_some() {
+            self.accumulators.pop();
+        }
+    }
+
+    pub fn is_quiet(&mut self, chessboard: &mut Chessboard) -> bool {
+        // a position is quiet when the side to move is not in check and no sequence of captures changes
+        // the static evaluation, the tuner only learns from such positions
+        if chessboard.is_in_check() {
+            return false
+        }
+        self.control = SearchControl::without_stop();
+        self.stopped = false;
+        self.deadline = None;
+        self.node_limit = None;
+        self.reset_accumulators(chessboard);
+        let static_score = self.relative_evaluate(chessboard);
+        self.quiescence(chessboard, 0, -INFINITY, INFINITY) == static_score
+    }
+
+    fn relative_evaluate(&mut self, chessboard: &Chessboard) -> i32 {
+        // static evaluation from the perspective of the player that has to move
+        let eval = self.static_evaluate(chessboard);
+        match chessboard.get_to_move() {
+            ToMove::White => eval,
+            ToMove::Black => -eval
+        }
+    }
+
+    fn captured_piece(position: &Position, new_move: &Move) -> PieceType {
+        // returns the type of the piece that is captured by new_move, en passant included
+        let (friendly, enemy) = match position.to_move {
+            ToMove::White => (&position.white_pieces, &position.black_pieces),
+            ToMove::Black => (&position.black_pieces, &position.white_pieces)
+        };
+        match enemy.detect_piece_type(new_move.to) {
+            PieceType::EmptySquare => {
+                if position.es_target == Some(new_move.to) && friendly.detect_piece_type(new_move.from) == PieceType::Pawn {
+                    PieceType::Pawn
+                }
+                else {PieceType::EmptySquare}
+            }
+            other => other
+        }
+    }
+
+    fn has_non_pawn_material(position: &Position) -> bool {
+        // null move pruning is unsound in zugzwang, which mostly happens when a player only has a king and pawns left
+        let pieces = match position.to_move {
+            ToMove::White => &position.white_pieces,
+            ToMove::Black => &position.black_pieces
+        };
+        pieces.get_bb_knights() | pieces.get_bb_bishops() | pieces.get_bb_rooks() | pieces.get_bb_queens() != 0
+    }
+
+    fn order_moves(&self, chessboard: &Chessboard, moves: &mut [Move], ply: usize, tt_move: Option<Move>) {
+        // sort the moves such that the most promising moves are searched first, which makes alpha-beta
+        // cut off much more and is what makes principal variation search and late move reductions work
+        let position = chessboard.get_position();
+        let friendly = match position.to_move {
+            ToMove::White => &position.white_pieces,
+            ToMove::Black => &position.black_pieces
+        };
+        let pv_move = self.previous_pv.get(ply).copied();
+        moves.sort_by_cached_key(|new_move| {
+            let score = if Some(*new_move) == pv_move {
+                1_000_000
+            }
+            else if Some(*new_move) == tt_move {
+                500_000
+            }
+            else {
+                match BasicTreeSearchComputer::captured_piece(position, new_move) {
+                    PieceType::EmptySquare => {
+                        if new_move.on_promotion == Some(PiecePromotes::Queen) {
+                            90_000
+                        }
+                        else if Some(*new_move) == self.killer_moves[ply][0] {
+                            9_000
+                        }
+                        else if Some(*new_move) == self.killer_moves[ply][1] {
+                            8_000
+                        }
+                        else {0}
+                    }
+                    // captures that lose material come after the killers
+                    victim if chessboard.see_ge(new_move, 0) => 10_000 + 10 * order_value(&victim) - order_value(&friendly.detect_piece_type(new_move.from)) / 10,
+                    victim => 1_000 + 10 * order_value(&victim) - order_value(&friendly.detect_piece_type(new_move.from)) / 10
+                }
+            };
+            -score
+        });
+    }
+
+    fn store_killer(&mut self, ply: usize, new_move: Move) {
+        if self.killer_moves[ply][0] != Some(new_move) {
+            self.killer_moves[ply][1] = self.killer_moves[ply][0];
+            self.killer_moves[ply][0] = Some(new_move);
+        }
+    }
+
+    fn check_limits(&mut self) {
+        // sets stopped when the search has to end, the clock and the stop flag are only looked at every
+        // 1024 nodes because reading them is relatively slow
+        if let Some(node_limit) = self.node_limit {
+            if self.nodes >= node_limit {
+                self.stopped = true;
+            }
+        }
+        if self.nodes & 1023 == 0 {
+            if self.control.stop_requested() {
+                self.stopped = true;
+            }
+            if let Some(deadline) = self.deadline {
+                if Instant::now() >= deadline {
+                    self.stopped = true;
+                }
+            }
+        }
+    }
+
+    pub fn negamax(&mut self, chessboard: &mut Chessboard, mut depth: i32, ply: usize, mut alpha: i32, beta: i32, allow_null: bool) -> i32 {
+        // Alpha-beta search in negamax form, the returned score is always from the perspective of the player that
+        // has to move. ply is the distance to the root which we need for the pv table and for mate scores.
+        self.pv_table.clear_ply(ply);
+        self.check_limits();
+        if self.stopped {
+            return 0
+        }
+        let in_check = chessboard.is_in_check();
+        // search one ply deeper when in check, such that we do not stop right before being mated
+        if in_check && self.options.check_extensions {
+            depth += 1;
+        }
+        if depth <= 0 || ply + 1 >= MAX_PLY {
+            return if self.options.quiescence {
+                self.quiescence(chessboard, ply, alpha, beta)
+            }
+            else {
+                self.nodes += 1;
+                self.seldepth = cmp::max(self.seldepth, ply as u8);
+                self.relative_evaluate(chessboard)
+            }
+        }
+        self.nodes += 1;
+        self.seldepth = cmp::max(self.seldepth, ply as u8);
+        let is_pv_node = beta - alpha > 1;
+
+        // a result from an earlier visit of this position may already be good enough, at pv nodes we only
+        // use its move so that the principal variation stays complete
+        let hash = chessboard.get_hash();
+        let mut tt_move = None;
+        if let Some(entry) = self.tt.probe(hash, ply) {
+            tt_move = entry.best_move;
+            if !is_pv_node && ply > 0 && entry.depth as i32 >= depth {
+                match entry.bound {
+                    Bound::Exact => return entry.score,
+                    Bound::Lower if entry.score >= beta => return entry.score,
+                    Bound::Upper if entry.score <= alpha => return entry.score,
+                    _ => ()
+                }
+            }
+        }
+
+        // Positions right after a capture or pawn move with few enough pieces are looked up in the tablebases.
+        // Positions in between are found by the search, that way we do not have to care about the 50 move rule.
+        if let Some(tablebase) = &self.tablebase {
+            let position = chessboard.get_position();
+            if ply > 0 && position.halfmove_clock == 0 && tablebase.can_probe(position) {
+                if let Some(wdl) = tablebase.probe_wdl(chessboard) {
+                    let (score, bound) = match wdl {
+                        Wdl::Win => (TB_WIN_SCORE - ply as i32, Bound::Lower),
+                        Wdl::Loss => (-TB_WIN_SCORE + ply as i32, Bound::Upper),
+                        // wins and losses that the 50 move rule turns into draws are just a little better or worse
+                        draw => (draw.value(), Bound::Exact)
+                    };
+                    if bound == Bound::Exact || (bound == Bound::Lower && score >= beta) || (bound == Bound::Upper && score <= alpha) {
+                        let depth = cmp::min(depth + 6, MAX_PLY as i32 - 1) as u8;
+                        self.tt.store(hash, ply, TtEntry { best_move: None, score, depth, bound });
+                        return score
+                    }
+                }
+            }
+        }
+
+        // Null move pruning: if we pass the turn and a reduced search still fails high, our position is
+        // so good that a real move will almost certainly fail high as well.
+        if self.options.null_move_pruning && allow_null && !is_pv_node && !in_check && ply > 0 && depth >= 3
+            && !is_mate_score(beta) && BasicTreeSearchComputer::has_non_pawn_material(chessboard.get_position()) {
+            let reduction = if depth > 6 {3} else {2};
+            self.make_null_move(chessboard);
+            let score = -self.negamax(chessboard, depth - 1 - reduction, ply + 1, -beta, -beta + 1, false);
+            self.undo_move(chessboard);
+            if self.stopped {
+                return 0
+            }
+            if score >= beta {
+                return beta
+            }
+        }
+
+        let mut moves = chessboard.all_moves();
+        if moves.is_empty() {
+            // checkmate or stalemate, prefer the quickest mate
+            return if in_check {-MATE_SCORE + ply as i32} else {0}
+        }
+        if ply == 0 && !self.excluded_root_moves.is_empty() {
+            moves.retain(|new_move| !self.excluded_root_moves.contains(new_move));
+        }
+        if ply == 0 && !self.tablebase_root_moves.is_empty() {
+            moves.retain(|new_move| self.tablebase_root_moves.contains(new_move));
+        }
+        self.order_moves(chessboard, &mut moves, ply, tt_move);
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for (move_number, new_move) in moves.iter().enumerate() {
+            let is_quiet = BasicTreeSearchComputer::captured_piece(chessboard.get_position(), new_move) == PieceType::EmptySquare
+                && new_move.on_promotion.is_none();
+            self.make_move(chessboard, new_move);
+            let gives_check = chessboard.is_in_check();
+
+            let score = if move_number == 0 || !self.options.principal_variation_search {
+                -self.negamax(chessboard, depth - 1, ply + 1, -beta, -alpha, true)
+            }
+            else {
+                // Late move reductions: moves late in the ordering are rarely best, so we first search them
+                // with less depth and only search them fully when they turn out to be better than expected.
+                let reduction = if self.options.late_move_reductions && move_number >= 3 && depth >= 3
+                    && is_quiet && !in_check && !gives_check {
+                    if move_number >= 6 {2} else {1}
+                }
+                else {0};
+                // Principal variation search: we expect the first move to be best, so the other moves are only
+                // searched with a null window to prove that they are worse.
+                let mut score = -self.negamax(chessboard, depth - 1 - reduction, ply + 1, -alpha - 1, -alpha, true);
+                if score > alpha && reduction > 0 {
+                    score = -self.negamax(chessboard, depth - 1, ply + 1, -alpha - 1, -alpha, true);
+                }
+                if score > alpha && score < beta {
+                    score = -self.negamax(chessboard, depth - 1, ply + 1, -beta, -alpha, true);
+                }
+                score
+            };
+            self.undo_move(chessboard);
+            if self.stopped {
+                return 0
+            }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    best_move = Some(*new_move);
+                    // a new best line from this node onwards
+                    self.pv_table.update(ply, *new_move);
                 }
-                if depth == self.depth && min_eval == eval {
-                    self.best_move = Some(*new_move);
+            }
+            if alpha >= beta {
+                if is_quiet {
+                    self.store_killer(ply, *new_move);
+                }
+                break
+            }
+        }
+        let bound = if best_score >= beta {
+            Bound::Lower
+        }
+        else if best_score > original_alpha {
+            Bound::Exact
+        }
+        else {Bound::Upper};
+        // the root score without the excluded moves is not the real score of the position
+        if ply > 0 || self.excluded_root_moves.is_empty() {
+            self.tt.store(hash, ply, TtEntry { best_move, score: best_score, depth: depth as u8, bound });
+        }
+        best_score
+    }
+
+    fn quiescence(&mut self, chessboard: &mut Chessboard, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        // Only searches captures and queen promotions until the position is quiet, so that the static evaluation
+        // is never taken in the middle of an exchange.
+        self.check_limits();
+        if self.stopped {
+            return 0
+        }
+        self.nodes += 1;
+        self.seldepth = cmp::max(self.seldepth, ply as u8);
+        self.pv_table.clear_ply(ply);
+        let stand_pat = self.relative_evaluate(chessboard);
+        if stand_pat >= beta || ply + 1 >= MAX_PLY {
+            return stand_pat
+        }
+        alpha = cmp::max(alpha, stand_pat);
+
+        // a capture that loses material in the exchange can hardly raise alpha
+        let position = *chessboard.get_position();
+        let see_pruning = self.options.see_pruning;
+        let mut moves: Vec<Move> = chessboard.all_moves().into_iter().filter(|new_move| {
+            (BasicTreeSearchComputer::captured_piece(&position, new_move) != PieceType::EmptySquare
+                || new_move.on_promotion == Some(PiecePromotes::Queen))
+                && (!see_pruning || chessboard.see_ge(new_move, 0))
+        }).collect();
+        self.order_moves(chessboard, &mut moves, ply, None);
+
+        let mut best_score = stand_pat;
+        for new_move in moves.iter() {
+            self.make_move(chessboard, new_move);
+            let score = -self.quiescence(chessboard, ply + 1, -beta, -alpha);
+            self.undo_move(chessboard);
+            if self.stopped {
+                return 0
+            }
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    self.pv_table.update(ply, *new_move);
                 }
-                chessboard.undo();
             }
-            return min_eval
+            if alpha >= beta {
+                break
+            }
+        }
+        best_score
+    }
+
+    pub fn static_evaluate(&mut self, chessboard: &Chessboard) -> i32 {
+        // evaluate the position it is given, positive evaluation means good for white
+        // while negative evaluation means good for black
+        let position = chessboard.get_position();
+        match &self.network {
+            // the network has no bitbase term, so the endgames of the bitbases are scored before it
+            Some(network) => match bitbase_score(position).filter(|_| self.options.bitbases) {
+                Some(score) => score,
+                None => self.accumulators.evaluate(network, chessboard.get_to_move())
+            },
+            None => evaluate(position, chessboard.get_pseudo_moves(), &self.params, &mut self.pawn_table, self.options.bitbases)
+        }
+    }
+}
+
+pub struct NnueComputer {
+    // The tree search with a neural network as evaluation. It always has a network, the one it is created with
+    // or one loaded later through the nnue_file option.
+    search: BasicTreeSearchComputer,
+    // the file the network was loaded from, setting nnue_file to it again does not load it twice
+    network_file: String
+}
+
+impl NnueComputer {
+    pub fn new() -> Result<NnueComputer, std::io::Error> {
+        // fails when the default network cannot be loaded
+        NnueComputer::from_file(DEFAULT_NETWORK)
+    }
+    pub fn from_file(filepath: &str) -> Result<NnueComputer, std::io::Error> {
+        let mut search = BasicTreeSearchComputer::new();
+        search.set_network(Some(NnueNetwork::load_file(filepath)?));
+        Ok(NnueComputer { search, network_file: filepath.to_string() })
+    }
+}
+
+impl RecieveAndReturnMove for NnueComputer {
+    fn return_move(&mut self, chessboard: &mut Chessboard) -> Move {
+        self.search.return_move(chessboard)
+    }
+    fn search(&mut self, chessboard: &mut Chessboard, limits: &SearchLimits, control: &SearchControl) -> SearchResult {
+        self.search.search(chessboard, limits, control)
+    }
+    fn last_search_result(&self) -> Option<SearchResult> {
+        self.search.last_search_result()
+    }
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), InvalidOptionError> {
+        if name == "nnue_file" {
+            // without a network this would just be the basic computer
+            if value.is_empty() {
+                return Err(InvalidOptionError)
+            }
+            if value == self.network_file {
+                return Ok(())
+            }
+            self.search.set_option(name, value)?;
+            self.network_file = value.to_string();
+            return Ok(())
+        }
+        self.search.set_option(name, value)
+    }
+    fn set_eval_params(&mut self, params: EvalParams) -> Result<(), InvalidOptionError> {
+        // the weights are kept, but the network evaluates as long as this computer exists
+        self.search.set_eval_params(params)
+    }
+    fn eval_params(&self) -> Option<EvalParams> {
+        self.search.eval_params()
+    }
+    fn new_game(&mut self) {
+        self.search.new_game()
+    }
+}
+
+pub struct BookComputer {
+    // Plays a move from the opening book as long as the position is in the book, after that the fallback
+    // computer takes over. The book is shared so that copies of the computer do not load it again.
+    book: Arc<OpeningBook>,
+    // the file the book was loaded from, setting book_file to it again does not load it twice
+    book_file: String,
+    fallback: Box<dyn RecieveAndReturnMove + Send>,
+    selection: BookSelection,
+    rng: StdRng,
+    last_result: Option<SearchResult>
+}
+
+impl BookComputer {
+    pub fn new(fallback: Box<dyn RecieveAndReturnMove + Send>) -> Result<BookComputer, std::io::Error> {
+        // fails when the default book cannot be loaded
+        BookComputer::from_file(DEFAULT_BOOK, fallback)
+    }
+    pub fn from_file(filepath: &str, fallback: Box<dyn RecieveAndReturnMove + Send>) -> Result<BookComputer, std::io::Error> {
+        let book = OpeningBook::load_file(filepath)?;
+        Ok(BookComputer { book: Arc::new(book), book_file: filepath.to_string(), fallback, selection: BookSelection::Random,
+            rng: StdRng::from_entropy(), last_result: None })
+    }
+    fn book_move(&mut self, chessboard: &mut Chessboard) -> Option<Move> {
+        self.book.choose(chessboard, self.selection, &mut self.rng)
+    }
+}
+
+impl RecieveAndReturnMove for BookComputer {
+    fn return_move(&mut self, chessboard: &mut Chessboard) -> Move {
+        match self.book_move(chessboard) {
+            Some(book_move) => book_move,
+            None => self.fallback.return_move(chessboard)
         }
     }
-}
\ No newline at end of file
+    fn search(&mut self, chessboard: &mut Chessboard, limits: &SearchLimits, control: &SearchControl) -> SearchResult {
+        // a book move is played without searching, so the result only has the move
+        let result = match self.book_move(chessboard) {
+            Some(book_move) => {
+                let mut result = SearchResult::new();
+                result.best_move = Some(book_move);
+                result.pv = vec![book_move];
+                result
+            }
+            None => self.fallback.search(chessboard, limits, control)
+        };
+        self.last_result = Some(result.clone());
+        result
+    }
+    fn last_search_result(&self) -> Option<SearchResult> {
+        self.last_result.clone()
+    }
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), InvalidOptionError> {
+        match name {
+            "book_file" => {
+                if value != self.book_file {
+                    self.book = Arc::new(OpeningBook::load_file(value).map_err(|_| InvalidOptionError)?);
+                    self.book_file = value.to_string();
+                }
+                Ok(())
+            }
+            "book_selection" => {
+                // best or random
+                self.selection = BookSelection::from_name(value).ok_or(InvalidOptionError)?;
+                Ok(())
+            }
+            "book_seed" => {
+                self.rng = StdRng::seed_from_u64(value.parse().map_err(|_| InvalidOptionError)?);
+                Ok(())
+            }
+            _ => self.fallback.set_option(name, value)
+        }
+    }
+    fn set_eval_params(&mut self, params: EvalParams) -> Result<(), InvalidOptionError> {
+        self.fallback.set_eval_params(params)
+    }
+    fn eval_params(&self) -> Option<EvalParams> {
+        self.fallback.eval_params()
+    }
+    fn new_game(&mut self) {
+        self.last_result = None;
+        self.fallback.new_game()
+    }
+}
+
+pub struct SkillComputer {
+    // The tree search at a chosen strength, see SkillLevel. The random choices come from a seeded generator,
+    // so a game against the same seed goes the same way as long as the human plays the same moves.
+    search: BasicTreeSearchComputer,
+    skill: SkillLevel,
+    rng: StdRng,
+    last_result: Option<SearchResult>
+}
+
+impl SkillComputer {
+    pub fn new(skill: SkillLevel) -> SkillComputer {
+        SkillComputer { search: BasicTreeSearchComputer::new(), skill, rng: StdRng::from_entropy(), last_result: None }
+    }
+    fn choose_line(&mut self, lines: &[PvLine]) -> Option<usize> {
+        // the index of the line that is played
+        if lines.is_empty() {
+            return None
+        }
+        if self.rng.gen_range(0..100) < self.skill.blunder_chance {
+            return Some(self.rng.gen_range(0..lines.len()))
+        }
+        let noise = self.skill.score_noise;
+        (0..lines.len()).max_by_key(|index| lines[*index].score + self.rng.gen_range(-noise..=noise))
+    }
+}
+
+impl RecieveAndReturnMove for SkillComputer {
+    fn return_move(&mut self, chessboard: &mut Chessboard) -> Move {
+        let limits = SearchLimits::from_depth(self.skill.depth);
+        self.search(chessboard, &limits, &SearchControl::without_stop()).best_move.unwrap()
+    }
+    fn search(&mut self, chessboard: &mut Chessboard, limits: &SearchLimits, control: &SearchControl) -> SearchResult {
+        // the candidates are searched as extra lines, the multipv option of the search is kept for later
+        let multipv = std::mem::replace(&mut self.search.multipv, self.skill.candidates);
+        let mut result = self.search.search(chessboard, &self.skill.limit(limits), control);
+        self.search.multipv = multipv;
+        if let Some(index) = self.choose_line(&result.lines) {
+            let line = result.lines.remove(index);
+            result.best_move = line.pv.first().copied();
+            result.score = line.score;
+            result.pv = line.pv;
+            result.lines = vec![PvLine { pv: result.pv.clone(), score: result.score }];
+        }
+        self.last_result = Some(result.clone());
+        result
+    }
+    fn last_search_result(&self) -> Option<SearchResult> {
+        self.last_result.clone()
+    }
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), InvalidOptionError> {
+        // the skill level and the elo set all values at once, the other options change a single one
+        match name {
+            "skill_level" => {
+                let level: u8 = value.parse().map_err(|_| InvalidOptionError)?;
+                if level > SkillLevel::MAX_LEVEL {
+                    return Err(InvalidOptionError)
+                }
+                self.skill = SkillLevel::from_level(level);
+            }
+            "elo" => {
+                let elo: u32 = value.parse().map_err(|_| InvalidOptionError)?;
+                if !(SkillLevel::MIN_ELO..=SkillLevel::MAX_ELO).contains(&elo) {
+                    return Err(InvalidOptionError)
+                }
+                self.skill = SkillLevel::from_elo(elo);
+            }
+            "skill_depth" => {
+                let depth: u8 = value.parse().map_err(|_| InvalidOptionError)?;
+                if depth == 0 {
+                    return Err(InvalidOptionError)
+                }
+                self.skill.depth = depth;
+            }
+            "skill_nodes" => {
+                // 0 removes the node limit
+                let nodes: u64 = value.parse().map_err(|_| InvalidOptionError)?;
+                self.skill.nodes = if nodes == 0 {None} else {Some(nodes)};
+            }
+            "skill_candidates" => {
+                let candidates: usize = value.parse().map_err(|_| InvalidOptionError)?;
+                if candidates == 0 {
+                    return Err(InvalidOptionError)
+                }
+                self.skill.candidates = candidates;
+            }
+            "score_noise" => {
+                let noise: i32 = value.parse().map_err(|_| InvalidOptionError)?;
+                if noise < 0 {
+                    return Err(InvalidOptionError)
+                }
+                self.skill.score_noise = noise;
+            }
+            "blunder_chance" => {
+                // in percent
+                let chance: u32 = value.parse().map_err(|_| InvalidOptionError)?;
+                if chance > 100 {
+                    return Err(InvalidOptionError)
+                }
+                self.skill.blunder_chance = chance;
+            }
+            "skill_seed" => self.rng = StdRng::seed_from_u64(value.parse().map_err(|_| InvalidOptionError)?),
+            _ => return self.search.set_option(name, value)
+        }
+        Ok(())
+    }
+    fn set_eval_params(&mut self, params: EvalParams) -> Result<(), InvalidOptionError> {
+        self.search.set_eval_params(params)
+    }
+    fn eval_params(&self) -> Option<EvalParams> {
+        self.search.eval_params()
+    }
+    fn new_game(&mut self) {
+        self.last_result = None;
+        self.search.new_game()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::test_helper::*;
+
+    #[test]
+    fn single_thread_search_is_deterministic() {
+        // two fresh computers with one thread search exactly the same tree
+        let limits = SearchLimits::from_depth(5);
+        let results: Vec<SearchResult> = (0..2).map(|_| {
+            let mut computer = BasicTreeSearchComputer::new();
+            computer.set_option("threads", "1").unwrap();
+            let mut chessboard = chessboard(KIWIPETE_FEN);
+            computer.search(&mut chessboard, &limits, &SearchControl::without_stop())
+        }).collect();
+        assert!(results[0].best_move.is_some());
+        assert_eq!(results[0].best_move, results[1].best_move);
+        assert_eq!(results[0].pv, results[1].pv);
+        assert_eq!(results[0].score, results[1].score);
+        assert_eq!(results[0].nodes, results[1].nodes);
+    }
+
+    #[test]
+    fn deadline_ends_a_running_search() {
+        // like a ponderhit, the search starts without a limit and gets a deadline while it runs
+        let control = SearchControl::without_stop();
+        let search_control = control.clone();
+        let search = thread::spawn(move || {
+            let mut computer = BasicTreeSearchComputer::new();
+            let mut chessboard = chessboard(KIWIPETE_FEN);
+            let limits = SearchLimits::new(None, None, None, None, None, None, None, None, true);
+            computer.search(&mut chessboard, &limits, &search_control)
+        });
+        thread::sleep(Duration::from_millis(50));
+        let start = Instant::now();
+        control.set_deadline(start + Duration::from_millis(100));
+        let result = search.join().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(result.best_move.is_some());
+        // the deadline belongs to that search only
+        assert!(!SearchControl::without_stop().stop_requested());
+    }
+
+    fn random_game(computer: &mut dyn RecieveAndReturnMove, plies: usize) -> Vec<Move> {
+        let mut chessboard = chessboard(START_FEN);
+        let mut game = Vec::new();
+        while game.len() < plies && !chessboard.all_moves().is_empty() {
+            let new_move = computer.return_move(&mut chessboard);
+            chessboard.move_piece(&new_move).unwrap();
+            game.push(new_move);
+        }
+        game
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_game() {
+        for policy in [RandomPolicy::Uniform, RandomPolicy::Tactical, RandomPolicy::Softmax] {
+            let games: Vec<Vec<Move>> = ["7", "7", "8"].iter().map(|seed| {
+                let mut computer = RandomComputer::with_policy(policy);
+                computer.set_option("seed", seed).unwrap();
+                random_game(&mut computer, 40)
+            }).collect();
+            assert_eq!(games[0], games[1], "{:?}", policy);
+            assert_ne!(games[0], games[2], "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn softmax_scores_stalemate_as_a_draw() {
+        // every bishop move and Kb6 take the last square of the black king, the other king moves keep the extra
+        // bishop, so the stalemates get the smallest weight
+        let mut chessboard = chessboard("k7/B1K5/8/8/8/8/8/8 w - - 0 1");
+        let mut computer = RandomComputer::with_policy(RandomPolicy::Softmax);
+        let moves = chessboard.all_moves();
+        let weights = computer.move_weights(&mut chessboard, &moves);
+        let stalemates = ["a7b8", "a7b6", "a7c5", "a7d4", "a7e3", "a7f2", "a7g1", "c7b6"];
+        let stalemate_weight = weights[moves.iter().position(|new_move| new_move.to_string() == "a7b8").unwrap()];
+        for (new_move, weight) in moves.iter().zip(weights.iter()) {
+            if stalemates.contains(&new_move.to_string().as_str()) {
+                assert_eq!(*weight, stalemate_weight, "{}", new_move.to_string());
+            }
+            else {
+                assert!(*weight > stalemate_weight, "{}", new_move.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn skill_levels_and_elo() {
+        let beginner = SkillLevel::from_level(0);
+        assert_eq!((beginner.depth, beginner.nodes, beginner.candidates), (1, Some(200), 4));
+        assert_eq!((beginner.score_noise, beginner.blunder_chance), (300, 30));
+        let strongest = SkillLevel::from_level(SkillLevel::MAX_LEVEL);
+        assert_eq!((strongest.depth, strongest.nodes, strongest.candidates), (6, None, 1));
+        assert_eq!((strongest.score_noise, strongest.blunder_chance), (0, 0));
+        // levels above the highest one and ratings outside the range are clamped
+        assert_eq!(SkillLevel::from_level(30).nodes, None);
+        assert_eq!(SkillLevel::from_elo(100).nodes, beginner.nodes);
+        assert_eq!(SkillLevel::from_elo(SkillLevel::MIN_ELO).score_noise, beginner.score_noise);
+        assert_eq!(SkillLevel::from_elo(SkillLevel::MAX_ELO).score_noise, 0);
+        assert_eq!(SkillLevel::from_elo(3000).nodes, None);
+        assert_eq!(SkillLevel::from_elo(1500).score_noise, SkillLevel::from_level(10).score_noise);
+
+        // the skill tightens the limits but never loosens them
+        let limited = beginner.limit(&SearchLimits::from_depth(10));
+        assert_eq!((limited.depth, limited.nodes), (Some(1), Some(200)));
+        let mut limits = SearchLimits::new(None, Some(50), None, None, None, None, None, None, true);
+        let limited = beginner.limit(&limits);
+        assert_eq!((limited.depth, limited.nodes, limited.infinite), (Some(1), Some(50), false));
+        limits.nodes = None;
+        let limited = strongest.limit(&limits);
+        assert_eq!((limited.depth, limited.nodes), (Some(6), None));
+    }
+
+    #[test]
+    fn low_skill_limits_depth_and_nodes() {
+        let mut computer = SkillComputer::new(SkillLevel::from_level(4));
+        computer.set_option("multipv", "2").unwrap();
+        let mut chessboard = chessboard(KIWIPETE_FEN);
+        let result = computer.search(&mut chessboard, &SearchLimits::from_depth(10), &SearchControl::without_stop());
+        assert!(result.best_move.is_some());
+        assert!(result.depth <= 2, "depth {}", result.depth);
+        assert!(result.nodes <= 800, "{} nodes", result.nodes);
+        // the candidates of the skill don't replace the multipv option
+        assert_eq!(computer.search.multipv, 2);
+    }
+
+    #[test]
+    fn same_skill_seed_plays_the_same_game() {
+        let games: Vec<Vec<Move>> = ["5", "5", "6"].iter().map(|seed| {
+            let mut computer = SkillComputer::new(SkillLevel::from_level(2));
+            computer.set_option("skill_seed", seed).unwrap();
+            random_game(&mut computer, 16)
+        }).collect();
+        assert_eq!(games[0], games[1]);
+        assert_ne!(games[0], games[2]);
+    }
+}
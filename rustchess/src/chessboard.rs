@@ -1,12 +1,20 @@
 use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use std::cmp;
 use crate::bitboard_helper::*;
 use crate::lookuptables::LoadMoves;
 use crate::chessboard_helper::*;
+use crate::zobrist::{hash_position, update_hash};
+use crate::mate_solver::{solve_mate, MateSolution};
 
 #[pyclass]
+#[derive(Clone)]
 pub struct Chessboard {
     pos: Position,
     history: Vec<Position>,
+    // zobrist hash of pos, kept up to date by every move and undo, and the hashes of the history
+    hash: u64,
+    hash_history: Vec<u64>,
     pseudo_moves: LoadMoves,
     // every time we make a move this will need to be cleared
     legal_moves_cache: [Option<u64>; 64],
@@ -17,7 +25,124 @@ pub struct Chessboard {
     pinned_masks_cache: [u64; 64]
 }
 
+#[pyclass]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveClass {
+    // what a legal move does, en passant also counts as a capture and mate also as a check
+    pub capture: bool,
+    pub promotion: bool,
+    pub castle: bool,
+    pub en_passant: bool,
+    pub check: bool,
+    pub mate: bool
+}
+
+#[pymethods]
+impl MoveClass {
+    pub fn is_capture(&self) -> bool {
+        self.capture
+    }
+    pub fn is_promotion(&self) -> bool {
+        self.promotion
+    }
+    pub fn is_castle(&self) -> bool {
+        self.castle
+    }
+    pub fn is_en_passant(&self) -> bool {
+        self.en_passant
+    }
+    pub fn is_check(&self) -> bool {
+        self.check
+    }
+    pub fn is_mate(&self) -> bool {
+        self.mate
+    }
+}
+
 impl Chessboard {
+    fn read_fen(&mut self, fen: String) {
+        // first clear the board
+        self.clear();
+        // get the parts of the FEN format
+        let parts: Vec<&str> = fen.split_whitespace().collect();
+        let board = match parts.get(0) {
+            Some(board) => board,
+            None => return
+        };
+        let filtered_board = board.replace("/", "");
+        // now load the board
+        let mut index: u32 = 0;
+        for ch in filtered_board.chars() {
+            if ch.is_digit(10) {
+                index += ch.to_digit(10).unwrap();
+            }
+            else if ch.is_ascii_uppercase() {
+                let piece_type = PieceType::from_char(ch.to_ascii_lowercase());
+                let new_bb = set_bit(self.pos.white_pieces.piece_type2bb(&piece_type), index as u8);
+                self.pos.white_pieces.set_bb_of_piece_type(new_bb, &piece_type);
+                index += 1;
+            }
+            else if ch.is_ascii_lowercase() {
+                let piece_type = PieceType::from_char(ch);
+                let new_bb = set_bit(self.pos.black_pieces.piece_type2bb(&piece_type), index as u8);
+                self.pos.black_pieces.set_bb_of_piece_type(new_bb, &piece_type);
+                index += 1;
+            }
+        }
+        // set the person to move
+        self.pos.to_move = match parts.get(1) {
+            None => return,
+            Some(c) => {
+                if c == &"w" {
+                    ToMove::White
+                }
+                else if c == &"b" {
+                    ToMove::Black
+                }
+                else {return}
+            }
+        };
+        // set castling ability
+        match parts.get(2) {
+            None => return,
+            Some(rights) => {
+                if rights.contains('K') {
+                    self.pos.white_kingside_castle = true;
+                } else {self.pos.white_kingside_castle = false;}
+                if rights.contains('Q') {
+                    self.pos.white_queenside_castle = true;
+                } else {self.pos.white_queenside_castle = false;}
+                if rights.contains('k') {
+                    self.pos.black_kingside_castle = true;
+                } else {self.pos.black_kingside_castle = false;}
+                if rights.contains('q') {
+                    self.pos.black_queenside_castle = true;
+                } else {self.pos.black_queenside_castle = false;}
+            }
+        }
+        // set en passant target square
+        self.pos.es_target = match parts.get(3) {
+            None => return,
+            Some(target) => board_notation2index(target)
+        };
+
+        // set half and full move clock
+        self.pos.halfmove_clock = match parts.get(4) {
+            None => 0,
+            Some(num) => match num.chars().nth(0) {
+                None => 0,
+                Some(i) => i.to_digit(10).unwrap() as u8
+            }
+        };
+        self.pos.fullmove_clock = match parts.get(5) {
+            None => 1,
+            Some(num) => match num.chars().nth(0) {
+                None => 1,
+                Some(i) => i.to_digit(10).unwrap() as u8
+            }
+        };
+        self.clear_cache();
+    }
     fn get_heatmap(&mut self, color: &PieceColor) -> u64 {
         // returns all squares that the color can move to in a bitboard,
         // check if we have already calculated the enemy heat before
@@ -651,6 +776,8 @@ impl Chessboard {
         }
         self.clear_cache();
         self.history.push(cloned);
+        self.hash_history.push(self.hash);
+        self.hash = update_hash(self.hash, &cloned, &self.pos);
         Ok(())
     }
 
@@ -692,6 +819,31 @@ impl Chessboard {
         all_moves
     }
 
+    pub fn is_in_check(&mut self) -> bool {
+        // returns whether the player that has to move is currently in check
+        let color = match self.pos.to_move {
+            ToMove::White => PieceColor::White,
+            ToMove::Black => PieceColor::Black
+        };
+        let king_index = get_lsb_index(self.pieces(&color).get_bb_king());
+        self.get_checking_pieces(king_index, &color) != 0
+    }
+
+    pub fn move_null(&mut self) {
+        // passes the turn to the other player without moving a piece, this is used by null move pruning.
+        // The old position is pushed on the history so that undo takes back a null move like any other move.
+        let cloned = self.pos;
+        self.pos.es_target = None;
+        self.pos.to_move = match self.pos.to_move {
+            ToMove::White => ToMove::Black,
+            ToMove::Black => ToMove::White
+        };
+        self.clear_cache();
+        self.history.push(cloned);
+        self.hash_history.push(self.hash);
+        self.hash = update_hash(self.hash, &cloned, &self.pos);
+    }
+
     // #[inline(always)]
     pub fn get_to_move(&self) -> &ToMove {
         &self.pos.to_move
@@ -700,14 +852,356 @@ impl Chessboard {
     pub fn get_position(&self) -> &Position {
         &self.pos
     }
+    pub fn get_pseudo_moves(&self) -> &LoadMoves {
+        // the lookup tables with the attacks of every piece, the evaluation uses them for mobility
+        &self.pseudo_moves
+    }
+    pub fn get_hash(&self) -> u64 {
+        // zobrist hash of the current position, used as key for the transposition table
+        self.hash
+    }
+    fn legal_move(&mut self, notation: &str) -> PyResult<Move> {
+        // a move like e2e4 or e7e8q that can be played in the current position
+        match Move::from_string(notation) {
+            Some(new_move) if self.all_moves().contains(&new_move) => Ok(new_move),
+            _ => Err(PyValueError::new_err(format!("{} is not a legal move", notation)))
+        }
+    }
+    fn attackers_with_occupancy(&self, square: usize, occupied: u64) -> u64 {
+        // all pieces of both colors that attack square, only the pieces on occupied block the sliders
+        let white = &self.pos.white_pieces;
+        let black = &self.pos.black_pieces;
+        let bishops = white.get_bb_bishops() | white.get_bb_queens() | black.get_bb_bishops() | black.get_bb_queens();
+        let rooks = white.get_bb_rooks() | white.get_bb_queens() | black.get_bb_rooks() | black.get_bb_queens();
+        // a white pawn attacks square from where a black pawn on square would capture, and the other way around
+        (subtract_bb(self.pseudo_moves.black_pawn(square), INDEX2FILE[square]) & white.get_bb_pawns())
+            | (subtract_bb(self.pseudo_moves.white_pawn(square), INDEX2FILE[square]) & black.get_bb_pawns())
+            | (self.pseudo_moves.knight(square) & (white.get_bb_knights() | black.get_bb_knights()))
+            | (self.pseudo_moves.king(square) & (white.get_bb_king() | black.get_bb_king()))
+            | (self.pseudo_moves.bishop(square, occupied).unwrap() & bishops)
+            | (self.pseudo_moves.rook(square, occupied).unwrap() & rooks)
+    }
+    fn color_bb(&self, color: &PieceColor) -> u64 {
+        // all pieces of color, without filling the cache of the pieces
+        let mut pieces = match color {
+            PieceColor::White => self.pos.white_pieces,
+            PieceColor::Black => self.pos.black_pieces,
+            PieceColor::None => return 0
+        };
+        pieces.get_all()
+    }
+    fn other_color(color: &PieceColor) -> PieceColor {
+        match color {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+            PieceColor::None => PieceColor::None
+        }
+    }
+    // The queries below have no side effects, unlike the cached helpers that the move generator uses.
+    pub fn attackers_to(&self, square: u8, color: &PieceColor) -> u64 {
+        // the pieces of color that attack square, whether square is empty or holds a piece of either color
+        let occupied = self.color_bb(&PieceColor::White) | self.color_bb(&PieceColor::Black);
+        self.attackers_with_occupancy(square as usize, occupied) & self.color_bb(color)
+    }
+    pub fn is_square_attacked(&self, square: u8, color: &PieceColor) -> bool {
+        self.attackers_to(square, color) != 0
+    }
+    pub fn checkers(&self) -> u64 {
+        // the pieces that give check to the player to move
+        let (color, king) = match self.pos.to_move {
+            ToMove::White => (PieceColor::White, self.pos.white_pieces.get_bb_king()),
+            ToMove::Black => (PieceColor::Black, self.pos.black_pieces.get_bb_king())
+        };
+        if king == 0 {
+            return 0
+        }
+        self.attackers_to(get_lsb_index(king) as u8, &Chessboard::other_color(&color))
+    }
+    pub fn pinned_pieces(&self, color: &PieceColor) -> u64 {
+        // the pieces of color that stand alone between their king and an enemy slider, they may only move along that line
+        let (friendly, enemy) = match color {
+            PieceColor::White => (&self.pos.white_pieces, &self.pos.black_pieces),
+            PieceColor::Black => (&self.pos.black_pieces, &self.pos.white_pieces),
+            PieceColor::None => return 0
+        };
+        if friendly.get_bb_king() == 0 {
+            return 0
+        }
+        let king = get_lsb_index(friendly.get_bb_king());
+        let occupied = self.color_bb(&PieceColor::White) | self.color_bb(&PieceColor::Black);
+        let rook_snipers = (enemy.get_bb_rooks() | enemy.get_bb_queens()) & self.pseudo_moves.rook(king, 0).unwrap();
+        let bishop_snipers = (enemy.get_bb_bishops() | enemy.get_bb_queens()) & self.pseudo_moves.bishop(king, 0).unwrap();
+        let mut pinned = 0;
+        for sniper in bb_to_vec(rook_snipers | bishop_snipers) {
+            // the squares between the king and the sniper are where both see each other on an empty board
+            let (king_bb, sniper_bb) = (set_bit(0, king as u8), set_bit(0, sniper));
+            let between = match sniper_bb & rook_snipers != 0 {
+                true => self.pseudo_moves.rook(king, sniper_bb).unwrap() & self.pseudo_moves.rook(sniper as usize, king_bb).unwrap(),
+                false => self.pseudo_moves.bishop(king, sniper_bb).unwrap() & self.pseudo_moves.bishop(sniper as usize, king_bb).unwrap()
+            };
+            let blockers = between & occupied;
+            if blockers.count_ones() == 1 && blockers & self.color_bb(color) != 0 {
+                pinned |= blockers;
+            }
+        }
+        pinned
+    }
+    pub fn attacked_squares(&self, color: &PieceColor) -> u64 {
+        // every square a piece of color attacks, pawns only attack diagonally and sliders stop at the first piece
+        let pieces = match color {
+            PieceColor::White => &self.pos.white_pieces,
+            PieceColor::Black => &self.pos.black_pieces,
+            PieceColor::None => return 0
+        };
+        let occupied = self.color_bb(&PieceColor::White) | self.color_bb(&PieceColor::Black);
+        let mut attacked = 0;
+        for index in bb_to_vec(pieces.get_bb_pawns()) {
+            let pawn_moves = match color {
+                PieceColor::White => self.pseudo_moves.white_pawn(index as usize),
+                _ => self.pseudo_moves.black_pawn(index as usize)
+            };
+            attacked |= subtract_bb(pawn_moves, INDEX2FILE[index as usize]);
+        }
+        for index in bb_to_vec(pieces.get_bb_knights()) {
+            attacked |= self.pseudo_moves.knight(index as usize);
+        }
+        for index in bb_to_vec(pieces.get_bb_bishops() | pieces.get_bb_queens()) {
+            attacked |= self.pseudo_moves.bishop(index as usize, occupied).unwrap();
+        }
+        for index in bb_to_vec(pieces.get_bb_rooks() | pieces.get_bb_queens()) {
+            attacked |= self.pseudo_moves.rook(index as usize, occupied).unwrap();
+        }
+        for index in bb_to_vec(pieces.get_bb_king()) {
+            attacked |= self.pseudo_moves.king(index as usize);
+        }
+        attacked
+    }
+    pub fn hanging_pieces(&self, color: &PieceColor) -> u64 {
+        // the pieces of color, the king aside, that are attacked and either not defended or attacked by a cheaper piece
+        let enemy_color = Chessboard::other_color(color);
+        let (friendly, enemy) = match color {
+            PieceColor::White => (&self.pos.white_pieces, &self.pos.black_pieces),
+            PieceColor::Black => (&self.pos.black_pieces, &self.pos.white_pieces),
+            PieceColor::None => return 0
+        };
+        let mut hanging = 0;
+        for index in bb_to_vec(subtract_bb(self.color_bb(color), friendly.get_bb_king())) {
+            let attackers = self.attackers_to(index, &enemy_color);
+            if attackers == 0 {
+                continue
+            }
+            let value = friendly.detect_piece_type(index).see_value();
+            let cheapest = bb_to_vec(attackers).into_iter().map(|attacker| enemy.detect_piece_type(attacker).see_value()).min().unwrap();
+            if cheapest < value || !self.is_square_attacked(index, color) {
+                hanging = set_bit(hanging, index);
+            }
+        }
+        hanging
+    }
+    pub fn is_castle(&self, new_move: &Move) -> bool {
+        // the king moves two files, which it only does when castling
+        let friendly = match self.pos.to_move {
+            ToMove::White => &self.pos.white_pieces,
+            ToMove::Black => &self.pos.black_pieces
+        };
+        friendly.get_bb_king() & set_bit(0, new_move.from) != 0 && (new_move.from as i32 - new_move.to as i32).abs() == 2
+    }
+    pub fn is_en_passant(&self, new_move: &Move) -> bool {
+        let friendly = match self.pos.to_move {
+            ToMove::White => &self.pos.white_pieces,
+            ToMove::Black => &self.pos.black_pieces
+        };
+        self.pos.es_target == Some(new_move.to) && friendly.get_bb_pawns() & set_bit(0, new_move.from) != 0
+    }
+    pub fn gives_check(&self, new_move: &Move) -> bool {
+        // Whether new_move checks the enemy king, without making it. The pieces of the player to move are
+        // moved on copies of their bitboards, then we look at what attacks the king with the new occupancy.
+        // That covers direct checks, discovered checks, the rook of a castle and a pawn taken en passant.
+        let (friendly, enemy) = match self.pos.to_move {
+            ToMove::White => (self.pos.white_pieces, self.pos.black_pieces),
+            ToMove::Black => (self.pos.black_pieces, self.pos.white_pieces)
+        };
+        if enemy.get_bb_king() == 0 {
+            return false
+        }
+        let king = get_lsb_index(enemy.get_bb_king());
+        let (from_bb, to_bb) = (set_bit(0, new_move.from), set_bit(0, new_move.to));
+        let mut occupied = subtract_bb(self.color_bb(&PieceColor::White) | self.color_bb(&PieceColor::Black), from_bb) | to_bb;
+        let mut pieces = friendly;
+        let piece_type = friendly.detect_piece_type(new_move.from);
+        let new_type = match new_move.on_promotion {
+            Some(promotion) => promotion.to_piece_type(),
+            None => piece_type
+        };
+        pieces.set_bb_of_piece_type(subtract_bb(pieces.piece_type2bb(&piece_type), from_bb), &piece_type);
+        pieces.set_bb_of_piece_type(pieces.piece_type2bb(&new_type) | to_bb, &new_type);
+        if self.is_en_passant(new_move) {
+            let captured_pawn = match self.pos.to_move {
+                ToMove::White => new_move.to + 8,
+                ToMove::Black => new_move.to - 8
+            };
+            occupied = subtract_bb(occupied, set_bit(0, captured_pawn));
+        }
+        if self.is_castle(new_move) {
+            // the rook jumps from the corner to the square the king crossed
+            let (rook_from, rook_to) = match new_move.to > new_move.from {
+                true => (new_move.from + 3, new_move.from + 1),
+                false => (new_move.from - 4, new_move.from - 1)
+            };
+            let rooks = subtract_bb(pieces.get_bb_rooks(), set_bit(0, rook_from)) | set_bit(0, rook_to);
+            pieces.set_bb_rooks(rooks);
+            occupied = subtract_bb(occupied, set_bit(0, rook_from)) | set_bit(0, rook_to);
+        }
+        // a pawn of ours checks from where an enemy pawn on the king square would capture
+        let pawn_checks = match self.pos.to_move {
+            ToMove::White => subtract_bb(self.pseudo_moves.black_pawn(king), INDEX2FILE[king]),
+            ToMove::Black => subtract_bb(self.pseudo_moves.white_pawn(king), INDEX2FILE[king])
+        };
+        (pawn_checks & pieces.get_bb_pawns())
+            | (self.pseudo_moves.knight(king) & pieces.get_bb_knights())
+            | (self.pseudo_moves.bishop(king, occupied).unwrap() & (pieces.get_bb_bishops() | pieces.get_bb_queens()))
+            | (self.pseudo_moves.rook(king, occupied).unwrap() & (pieces.get_bb_rooks() | pieces.get_bb_queens())) != 0
+    }
+    pub fn classify(&mut self, new_move: &Move) -> Result<MoveClass, NoLegalMoveInputError> {
+        // the flags of a legal move, the move is made and taken back to find out whether it mates
+        let en_passant = self.is_en_passant(new_move);
+        let enemy_bb = match self.pos.to_move {
+            ToMove::White => self.color_bb(&PieceColor::Black),
+            ToMove::Black => self.color_bb(&PieceColor::White)
+        };
+        let capture = en_passant || enemy_bb & set_bit(0, new_move.to) != 0;
+        let castle = self.is_castle(new_move);
+        let check = self.gives_check(new_move);
+        self.move_piece(new_move)?;
+        let mate = check && self.all_moves().is_empty();
+        self.undo();
+        Ok(MoveClass { capture, promotion: new_move.on_promotion.is_some(), castle, en_passant, check, mate })
+    }
+    pub fn see(&self, new_move: &Move) -> i32 {
+        // Static exchange evaluation: the material the player to move wins with new_move when both players keep
+        // capturing on the target square with their least valuable piece and stop as soon as that is better.
+        // A slider behind a piece that captures joins the exchange (x-ray). Pins and checks are not looked at.
+        let (friendly, enemy) = match self.pos.to_move {
+            ToMove::White => (&self.pos.white_pieces, &self.pos.black_pieces),
+            ToMove::Black => (&self.pos.black_pieces, &self.pos.white_pieces)
+        };
+        let mut pos = self.pos;
+        let (white_all, black_all) = (pos.white_pieces.get_all(), pos.black_pieces.get_all());
+        let mut occupied = white_all | black_all;
+        let to = new_move.to as usize;
+        let promotion_rank = !(8..56).contains(&to);
+
+        let mut piece = friendly.detect_piece_type(new_move.from);
+        let mut gain = [0; 32];
+        gain[0] = enemy.detect_piece_type(new_move.to).see_value();
+        if piece == PieceType::Pawn && self.pos.es_target == Some(new_move.to) {
+            let captured_pawn = match self.pos.to_move {
+                ToMove::White => new_move.to + 8,
+                ToMove::Black => new_move.to - 8
+            };
+            occupied = subtract_bb(occupied, set_bit(0, captured_pawn));
+            gain[0] = PieceType::Pawn.see_value();
+        }
+        if let Some(promotion) = new_move.on_promotion {
+            piece = promotion.to_piece_type();
+            gain[0] += piece.see_value() - PieceType::Pawn.see_value();
+        }
+        occupied = subtract_bb(occupied, set_bit(0, new_move.from));
+        let mut attackers = self.attackers_with_occupancy(to, occupied) & occupied;
+
+        // the gains if every capture is made, the exchange is then scored backwards
+        let mut white_captures = matches!(self.pos.to_move, ToMove::Black);
+        let mut depth = 0;
+        while depth + 1 < gain.len() {
+            let (side, side_bb, other_bb) = match white_captures {
+                true => (&self.pos.white_pieces, white_all, black_all),
+                false => (&self.pos.black_pieces, black_all, white_all)
+            };
+            let side_attackers = attackers & side_bb;
+            if side_attackers == 0 {
+                break
+            }
+            let (attacker_type, attacker_bb) = [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King]
+                .into_iter()
+                .map(|piece_type| (piece_type, side_attackers & side.piece_type2bb(&piece_type)))
+                .find(|(_, bb)| *bb != 0)
+                .unwrap();
+            // the king may not capture a defended piece
+            if attacker_type == PieceType::King && attackers & other_bb != 0 {
+                break
+            }
+            depth += 1;
+            gain[depth] = piece.see_value() - gain[depth - 1];
+            piece = attacker_type;
+            if piece == PieceType::Pawn && promotion_rank {
+                piece = PieceType::Queen;
+                gain[depth] += PieceType::Queen.see_value() - PieceType::Pawn.see_value();
+            }
+            occupied = subtract_bb(occupied, set_bit(0, get_lsb_index(attacker_bb) as u8));
+            attackers = (attackers | self.attackers_with_occupancy(to, occupied)) & occupied;
+            white_captures = !white_captures;
+        }
+        // each player only makes a capture if it is better than stopping
+        while depth > 0 {
+            gain[depth - 1] = -cmp::max(-gain[depth - 1], gain[depth]);
+            depth -= 1;
+        }
+        gain[0]
+    }
+    pub fn see_ge(&self, new_move: &Move, threshold: i32) -> bool {
+        // whether the static exchange evaluation of new_move is at least threshold, the exchange is only played
+        // out when losing the moving piece and keeping what it captures do not already decide it
+        let position = &self.pos;
+        let (friendly, enemy) = match position.to_move {
+            ToMove::White => (&position.white_pieces, &position.black_pieces),
+            ToMove::Black => (&position.black_pieces, &position.white_pieces)
+        };
+        let moving = match new_move.on_promotion {
+            Some(promotion) => promotion.to_piece_type().see_value(),
+            None => friendly.detect_piece_type(new_move.from).see_value()
+        };
+        let mut best_case = enemy.detect_piece_type(new_move.to).see_value();
+        if position.es_target == Some(new_move.to) && friendly.detect_piece_type(new_move.from) == PieceType::Pawn {
+            best_case = PieceType::Pawn.see_value();
+        }
+        if new_move.on_promotion.is_some() {
+            best_case += moving - PieceType::Pawn.see_value();
+        }
+        if best_case < threshold {
+            return false
+        }
+        if best_case - moving >= threshold {
+            return true
+        }
+        self.see(new_move) >= threshold
+    }
+}
+
+fn color_from_str(color: &str) -> PyResult<PieceColor> {
+    // python names the colors as white and black, the squares count from a8 (0) to h1 (63)
+    match color {
+        "white" => Ok(PieceColor::White),
+        "black" => Ok(PieceColor::Black),
+        _ => Err(PyValueError::new_err(format!("{} is not a color, use white or black", color)))
+    }
+}
+
+fn check_square(square: u8) -> PyResult<u8> {
+    match square {
+        0..=63 => Ok(square),
+        _ => Err(PyValueError::new_err(format!("{} is not a square", square)))
+    }
 }
 
 #[pymethods]
 impl Chessboard {
     #[staticmethod]
     pub fn new_start() -> Chessboard {
-        Chessboard { pos: Position::new_start(),
+        let pos = Position::new_start();
+        Chessboard { pos,
         history: Vec::new(),
+        hash: hash_position(&pos),
+        hash_history: Vec::new(),
         pseudo_moves: LoadMoves::new(),
         legal_moves_cache: [None; 64],
         enemy_heat_cache: None,
@@ -718,8 +1212,11 @@ impl Chessboard {
     }
     #[new]
     pub fn new() -> Chessboard {
-        Chessboard { pos: Position::new(),
+        let pos = Position::new();
+        Chessboard { pos,
             history: Vec::new(),
+            hash: hash_position(&pos),
+            hash_history: Vec::new(),
         pseudo_moves: LoadMoves::new(),
         legal_moves_cache: [None; 64],
         enemy_heat_cache: None,
@@ -731,9 +1228,13 @@ impl Chessboard {
     pub fn to_string(&self) -> String {
         self.pos.to_string()
     }
+    pub fn get_fen(&self) -> String {
+        self.pos.fen()
+    }
 
     pub fn clear(&mut self) {
         self.pos = Position::new();
+        self.hash = hash_position(&self.pos);
         self.clear_cache();
     }
 
@@ -748,6 +1249,7 @@ impl Chessboard {
     pub fn undo(&mut self) {
         if self.history.len() > 0 {
             self.pos = self.history.pop().expect("couldn't get previous position.");
+            self.hash = self.hash_history.pop().expect("couldn't get previous hash.");
             self.clear_cache();
         }
     }
@@ -872,87 +1374,9 @@ impl Chessboard {
 
 
     pub fn load_fen(&mut self, fen: String) {
-        // first clear the board
-        self.clear();
-        // get the parts of the FEN format
-        let parts: Vec<&str> = fen.split_whitespace().collect();
-        let board = match parts.get(0) {
-            Some(board) => board,
-            None => return
-        };
-        let filtered_board = board.replace("/", "");
-        // now load the board
-        let mut index: u32 = 0;
-        for ch in filtered_board.chars() {
-            if ch.is_digit(10) {
-                index += ch.to_digit(10).unwrap();
-            }
-            else if ch.is_ascii_uppercase() {
-                let piece_type = PieceType::from_char(ch.to_ascii_lowercase());
-                let new_bb = set_bit(self.pos.white_pieces.piece_type2bb(&piece_type), index as u8);
-                self.pos.white_pieces.set_bb_of_piece_type(new_bb, &piece_type);
-                index += 1;
-            }
-            else if ch.is_ascii_lowercase() {
-                let piece_type = PieceType::from_char(ch);
-                let new_bb = set_bit(self.pos.black_pieces.piece_type2bb(&piece_type), index as u8);
-                self.pos.black_pieces.set_bb_of_piece_type(new_bb, &piece_type);
-                index += 1;
-            }
-        }
-        // set the person to move
-        self.pos.to_move = match parts.get(1) {
-            None => return,
-            Some(c) => {
-                if c == &"w" {
-                    ToMove::White
-                }
-                else if c == &"b" {
-                    ToMove::Black
-                }
-                else {return}
-            }
-        };
-        // set castling ability
-        match parts.get(2) {
-            None => return,
-            Some(rights) => {
-                if rights.contains('K') {
-                    self.pos.white_kingside_castle = true;
-                } else {self.pos.white_kingside_castle = false;}
-                if rights.contains('Q') {
-                    self.pos.white_queenside_castle = true;
-                } else {self.pos.white_queenside_castle = false;}
-                if rights.contains('k') {
-                    self.pos.black_kingside_castle = true;
-                } else {self.pos.black_kingside_castle = false;}
-                if rights.contains('q') {
-                    self.pos.black_queenside_castle = true;
-                } else {self.pos.black_queenside_castle = false;}
-            }
-        }
-        // set en passant target square
-        self.pos.es_target = match parts.get(3) {
-            None => return,
-            Some(target) => board_notation2index(target)
-        };
-
-        // set half and full move clock
-        self.pos.halfmove_clock = match parts.get(4) {
-            None => 0,
-            Some(num) => match num.chars().nth(0) {
-                None => 0,
-                Some(i) => i.to_digit(10).unwrap() as u8
-            }
-        };
-        self.pos.fullmove_clock = match parts.get(5) {
-            None => 1,
-            Some(num) => match num.chars().nth(0) {
-                None => 1,
-                Some(i) => i.to_digit(10).unwrap() as u8
-            }
-        };
-        self.clear_cache();
+        // a fen that stops early leaves the rest of the position empty
+        self.read_fen(fen);
+        self.hash = hash_position(&self.pos);
     }
 
     pub fn get_legal_captures(&mut self, index: u8) -> Vec<u8> {
@@ -1008,4 +1432,197 @@ impl Chessboard {
     pub fn get_black_pieces(&mut self) -> u64 {
         self.pos.black_pieces.get_all()
     }
-}
\ No newline at end of file
+    #[pyo3(name = "attackers_to")]
+    pub fn py_attackers_to(&self, square: u8, color: &str) -> PyResult<Vec<u8>> {
+        Ok(bb_to_vec(self.attackers_to(check_square(square)?, &color_from_str(color)?)))
+    }
+    #[pyo3(name = "is_square_attacked")]
+    pub fn py_is_square_attacked(&self, square: u8, color: &str) -> PyResult<bool> {
+        Ok(self.is_square_attacked(check_square(square)?, &color_from_str(color)?))
+    }
+    #[pyo3(name = "checkers")]
+    pub fn py_checkers(&self) -> Vec<u8> {
+        bb_to_vec(self.checkers())
+    }
+    #[pyo3(name = "pinned_pieces")]
+    pub fn py_pinned_pieces(&self, color: &str) -> PyResult<Vec<u8>> {
+        Ok(bb_to_vec(self.pinned_pieces(&color_from_str(color)?)))
+    }
+    #[pyo3(name = "attacked_squares")]
+    pub fn py_attacked_squares(&self, color: &str) -> PyResult<Vec<u8>> {
+        Ok(bb_to_vec(self.attacked_squares(&color_from_str(color)?)))
+    }
+    #[pyo3(name = "hanging_pieces")]
+    pub fn py_hanging_pieces(&self, color: &str) -> PyResult<Vec<u8>> {
+        Ok(bb_to_vec(self.hanging_pieces(&color_from_str(color)?)))
+    }
+    #[pyo3(name = "gives_check")]
+    pub fn py_gives_check(&mut self, notation: &str) -> PyResult<bool> {
+        let new_move = self.legal_move(notation)?;
+        Ok(self.gives_check(&new_move))
+    }
+    #[pyo3(name = "classify")]
+    pub fn py_classify(&mut self, notation: &str) -> PyResult<MoveClass> {
+        let new_move = self.legal_move(notation)?;
+        self.classify(&new_move).map_err(|_| PyValueError::new_err(format!("{} is not a legal move", notation)))
+    }
+    #[pyo3(name = "see")]
+    pub fn py_see(&mut self, notation: &str) -> PyResult<i32> {
+        let new_move = self.legal_move(notation)?;
+        Ok(self.see(&new_move))
+    }
+    #[pyo3(name = "see_ge")]
+    pub fn py_see_ge(&mut self, notation: &str, threshold: i32) -> PyResult<bool> {
+        let new_move = self.legal_move(notation)?;
+        Ok(self.see_ge(&new_move, threshold))
+    }
+    pub fn solve_mate(&mut self, py: Python, moves: u8) -> MateSolution {
+        // proves or refutes a mate in moves for the player to move, see mate_solver.rs
+        py.allow_threads(|| solve_mate(self, moves))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::*;
+
+    fn board_and_move(fen: &str, notation: &str) -> (Chessboard, Move) {
+        let mut chessboard = chessboard(fen);
+        let new_move = Move::from_string(notation).unwrap();
+        assert!(chessboard.all_moves().contains(&new_move), "{} in {}", notation, fen);
+        (chessboard, new_move)
+    }
+
+    #[test]
+    fn static_exchange_evaluation() {
+        // the rook takes an undefended pawn
+        let (chessboard, rook_takes) = board_and_move("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5");
+        assert_eq!(chessboard.see(&rook_takes), 100);
+        assert!(chessboard.see_ge(&rook_takes, 100));
+        assert!(!chessboard.see_ge(&rook_takes, 101));
+        // the knight takes a pawn defended by a knight, a bishop and the queen behind it, the rook and queen
+        // of white join from behind
+        let (chessboard, knight_takes) = board_and_move("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5");
+        assert_eq!(chessboard.see(&knight_takes), -220);
+        assert!(chessboard.see_ge(&knight_takes, -220));
+        assert!(!chessboard.see_ge(&knight_takes, -219));
+        // a quiet move to a square a pawn attacks loses the piece
+        let (chessboard, hanging) = board_and_move("4k3/8/8/8/3p4/8/8/2B1K3 w - - 0 1", "c1e3");
+        assert_eq!(chessboard.see(&hanging), -330);
+        assert!(!chessboard.see_ge(&hanging, 0));
+        // en passant wins the pawn that is not on the target square, a promotion wins the new piece
+        let (chessboard, en_passant) = board_and_move("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6");
+        assert_eq!(chessboard.see(&en_passant), 100);
+        let (chessboard, promotion) = board_and_move("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q");
+        assert_eq!(chessboard.see(&promotion), 800);
+    }
+
+    fn class(fen: &str, notation: &str) -> MoveClass {
+        let (mut chessboard, new_move) = board_and_move(fen, notation);
+        let class = chessboard.classify(&new_move).unwrap();
+        assert_eq!(chessboard.gives_check(&new_move), class.check, "{}", notation);
+        // the check is also found by playing the move
+        chessboard.move_piece(&new_move).unwrap();
+        assert_eq!(class.check, chessboard.is_in_check(), "{}", notation);
+        class
+    }
+
+    #[test]
+    fn checks_and_move_classes() {
+        let quiet = class(START_FEN, "e2e4");
+        assert_eq!(quiet, MoveClass { capture: false, promotion: false, castle: false, en_passant: false, check: false, mate: false });
+        // the rook checks from the square the king crossed
+        let castle = class("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1");
+        assert!(castle.castle && castle.check && !castle.mate);
+        // the knight leaves the file of the rook
+        assert!(class("4k3/8/8/8/8/8/4N3/4R1K1 w - - 0 1", "e2c3").check);
+        assert!(!class("4k3/8/8/8/8/8/4N3/4R1K1 w - - 0 1", "g1h1").check);
+        // en passant takes both pawns off the rank of the rook
+        let en_passant = class("8/8/8/k2pP2R/8/8/8/4K3 w - d6 0 1", "e5d6");
+        assert!(en_passant.en_passant && en_passant.capture && en_passant.check && !en_passant.mate);
+        let mate = class("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8");
+        assert!(mate.check && mate.mate && !mate.capture);
+        let queen = class("3k4/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q");
+        assert!(queen.promotion && queen.check && !queen.mate);
+        assert!(!class("3k4/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8n").check);
+        assert!(class(KIWIPETE_FEN, "e2a6").capture);
+    }
+
+    fn squares(names: &[&str]) -> u64 {
+        // a bitboard from square names like e4
+        names.iter().fold(0, |bitboard, name| {
+            let name = name.as_bytes();
+            set_bit(bitboard, (name[0] - b'a') + (b'8' - name[1]) * 8)
+        })
+    }
+
+    fn square(name: &str) -> u8 {
+        get_lsb_index(squares(&[name])) as u8
+    }
+
+    #[test]
+    fn attackers_and_checkers() {
+        let double_check = chessboard("4k3/8/8/8/1b6/8/4r3/4K3 w - - 0 1");
+        assert_eq!(double_check.checkers(), squares(&["b4", "e2"]));
+        let chessboard = chessboard("4k3/8/8/3p4/3r4/2N1P3/8/4K2B w - - 0 1");
+        // the pawn on e3 pushes to e4 but does not attack it
+        assert_eq!(chessboard.attackers_to(square("e4"), &PieceColor::White), squares(&["c3", "h1"]));
+        assert_eq!(chessboard.attackers_to(square("e4"), &PieceColor::Black), squares(&["d4", "d5"]));
+        // the rook on d4 is attacked by the pawn and not defended
+        assert_eq!(chessboard.attackers_to(square("d4"), &PieceColor::White), squares(&["e3"]));
+        assert_eq!(chessboard.attackers_to(square("d4"), &PieceColor::Black), 0);
+        // the bishop stops at the pawn on d5
+        assert!(chessboard.is_square_attacked(square("d5"), &PieceColor::White));
+        assert!(!chessboard.is_square_attacked(square("c6"), &PieceColor::White));
+        assert_eq!(chessboard.checkers(), 0);
+    }
+
+    #[test]
+    fn pinned_pieces() {
+        // the knight is pinned by the bishop and the bishop on f1 by the rook, the pawns on the e-file block the
+        // rook together and the black pawn on g3 stands between its own bishop and the white king
+        let chessboard = chessboard("k7/4r3/8/b7/7b/2N1P1p1/4P3/4KB1r w - - 0 1");
+        assert_eq!(chessboard.pinned_pieces(&PieceColor::White), squares(&["c3", "f1"]));
+        assert_eq!(chessboard.pinned_pieces(&PieceColor::Black), 0);
+    }
+
+    #[test]
+    fn attacked_squares() {
+        // the pawns attack diagonally but not the square in front of them, the rook stops at the pawn on b4
+        let chessboard = chessboard("7k/6p1/8/8/1P2P3/8/1R6/7K w - - 0 1");
+        let white = squares(&["g1", "g2", "h2", "d5", "f5", "a5", "c5", "a2", "c2", "d2", "e2", "f2", "b1", "b3", "b4"]);
+        assert_eq!(chessboard.attacked_squares(&PieceColor::White), white);
+        assert_eq!(chessboard.attacked_squares(&PieceColor::Black), squares(&["g8", "g7", "h7", "f6", "h6"]));
+    }
+
+    #[test]
+    fn hanging_pieces() {
+        // the knight on b5 is not defended, the rook on d4 is defended but attacked by a knight and the pawn on f4
+        // is defended and only attacked by a rook
+        let chessboard = chessboard("5rk1/8/p1n5/1N6/3R1P2/4P1PB/8/6K1 w - - 0 1");
+        assert_eq!(chessboard.hanging_pieces(&PieceColor::White), squares(&["b5", "d4"]));
+        assert_eq!(chessboard.hanging_pieces(&PieceColor::Black), 0);
+    }
+
+    #[test]
+    fn python_attack_queries() {
+        // the python methods return the squares of the same bitboards
+        let in_check = chessboard("4k3/8/8/8/1b6/8/4r3/4K3 w - - 0 1");
+        assert_eq!(in_check.py_checkers(), bb_to_vec(in_check.checkers()));
+        let chessboard = chessboard("5rk1/8/p1n5/1N6/3R1P2/4P1PB/8/6K1 w - - 0 1");
+        for (name, color) in [("white", PieceColor::White), ("black", PieceColor::Black)] {
+            for index in [square("d4"), square("e5"), square("f8")] {
+                assert_eq!(chessboard.py_attackers_to(index, name).unwrap(), bb_to_vec(chessboard.attackers_to(index, &color)));
+                assert_eq!(chessboard.py_is_square_attacked(index, name).unwrap(), chessboard.is_square_attacked(index, &color));
+            }
+            assert_eq!(chessboard.py_pinned_pieces(name).unwrap(), bb_to_vec(chessboard.pinned_pieces(&color)));
+            assert_eq!(chessboard.py_attacked_squares(name).unwrap(), bb_to_vec(chessboard.attacked_squares(&color)));
+            assert_eq!(chessboard.py_hanging_pieces(name).unwrap(), bb_to_vec(chessboard.hanging_pieces(&color)));
+        }
+        assert_eq!(chessboard.py_hanging_pieces("white").unwrap(), vec![square("b5"), square("d4")]);
+        // colors and squares that don't exist are errors
+        assert!(chessboard.py_attackers_to(64, "white").is_err());
+        assert!(chessboard.py_is_square_attacked(0, "red").is_err());
+        assert!(chessboard.py_attacked_squares("none").is_err());
+    }
+}
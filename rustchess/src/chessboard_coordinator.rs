@@ -1,18 +1,61 @@
 use pyo3::prelude::*;
+use pyo3::exceptions::{PyValueError, PyRuntimeError};
 use crate::bitboard_helper::to_stringboard;
 use crate::chessboard::*;
 use crate::chessboard_helper::*;
 use crate::chess_computer::*;
+use crate::computer_registry::*;
+use crate::search_helper::*;
+use crate::eval_params::EvalParams;
+use crate::evaluation::{evaluate_trace, EvalTrace};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
 
-// converts a string into an option<computer>
-pub fn computer_from_string(name: &str) -> Option<Box<dyn RecieveAndReturnMove + Send>> {
-    // keep adding newer and different versions of computers
-    match name {
-        "human" => None,
-        "random" => Some(Box::new(RandomComputer::new())),
-        "basic" => Some(Box::new(BasicTreeSearchComputer::new())),
-        _ => None
+fn player_from_name(name: &str, options: Option<HashMap<String, String>>) -> PyResult<Option<Box<dyn RecieveAndReturnMove + Send>>> {
+    // None is a human, every other name has to be one of the registered computers
+    let options = options.unwrap_or_default();
+    if name == "human" {
+        if !options.is_empty() {
+            return Err(PyValueError::new_err("a human has no options"))
+        }
+        return Ok(None)
     }
+    create_computer(name, &options).map(Some).map_err(|err| err.into_py_err(name))
+}
+
+struct BackgroundSearch {
+    // A search that runs on a worker thread. The computer is moved into the thread together with a copy
+    // of the chessboard and handed back through the join handle once the search is done.
+    player: ToMove,
+    handle: JoinHandle<(Box<dyn RecieveAndReturnMove + Send>, SearchResult)>,
+    info: Receiver<SearchResult>,
+    // Some while the computer searches on the time of the human, see start_pondering
+    ponder: Option<Ponder>,
+    // the control of the running search, a search that started as pondering gets its time limit through it
+    control: SearchControl
+}
+
+struct BackgroundAnalysis {
+    // the analyser searches on a worker thread and comes back through the join handle when it is done
+    handle: JoinHandle<(BasicTreeSearchComputer, SearchResult)>,
+    info: Receiver<SearchResult>,
+    // every analysis has its own stop flag, so that stopping it never ends a search of the players
+    stop: Arc<AtomicBool>
+}
+
+struct Ponder {
+    // the hash of the position after the reply the computer expects
+    expected_hash: u64,
+    // the human played the expected reply, the search goes on once start_search gives it a time limit
+    hit: bool
+}
+
+fn same_player(player: ToMove, other: ToMove) -> bool {
+    matches!((player, other), (ToMove::White, ToMove::White) | (ToMove::Black, ToMove::Black))
 }
 
 #[pyclass]
@@ -26,6 +69,21 @@ pub struct Coordinator {
     chessboard: Chessboard,
     // allow to select a square for user input
     selected: Selected,
+    // the result of the most recent search of one of the computers
+    last_search_result: Option<SearchResult>,
+    // setting this flag makes the running search return its best move as soon as possible
+    stop_flag: Arc<AtomicBool>,
+    // the search running in the background, while it runs the computer that searches is not in computer1/2
+    background: Option<BackgroundSearch>,
+    // python function that is called with the intermediate results of the background search
+    info_callback: Option<PyObject>,
+    // the computer that analyses positions for the analysis board, it never plays a move itself.
+    // None while it analyses in the background
+    analyser: Option<BasicTreeSearchComputer>,
+    analysis: Option<BackgroundAnalysis>,
+    // whether computer1 and computer2 keep searching while the human thinks about the reply
+    ponder1: bool,
+    ponder2: bool,
 
 }
 
@@ -35,6 +93,15 @@ impl Coordinator {
         // Note that Some(new_move) will only be used whenever the player that has to move, is a human, i.e. computer1/2 is a None.
         // If computer1 has to move and computer1 is Some(T) then any value Some value passed into new_move will be ignored, since
         // the computer1 will make a move on his own.
+        // No moves can be made while a computer is thinking in the background, a computer that ponders
+        // stops when it has to move itself.
+        if self.is_searching() {
+            return Err(NoLegalMoveInputError)
+        }
+        if self.background.as_ref().is_some_and(|search| same_player(search.player, *self.chessboard.get_to_move())) {
+            self.abort_background_search();
+        }
+        self.abort_analysis();
         let to_move = self.chessboard.get_to_move();
         
         match to_move {
@@ -44,6 +111,7 @@ impl Coordinator {
                     // Black to move and a computer is playing black
                     Some(computer) => {
                         let computer_move = computer.return_move(&mut self.chessboard);
+                        self.last_search_result = computer.last_search_result();
                         self.chessboard.move_piece(&computer_move)?;
                     },
                     // Black to move but a human is playing black
@@ -63,6 +131,7 @@ impl Coordinator {
                     // Black to move and a computer is playing black
                     Some(computer) => {
                         let computer_move = computer.return_move(&mut self.chessboard);
+                        self.last_search_result = computer.last_search_result();
                         self.chessboard.move_piece(&computer_move)?;
                     },
                     // Black to move but a human is playing black
@@ -77,8 +146,155 @@ impl Coordinator {
                 }
             }
         }
+        self.check_ponder_move();
+        Ok(())
+    }
+    fn search_computer_move(&mut self, limits: &SearchLimits) -> Option<SearchResult> {
+        // lets the computer that has to move search with the given limits and plays the best move it found,
+        // returns None if a human has to move
+        let control = SearchControl::new(Arc::clone(&self.stop_flag));
+        let computer = match self.chessboard.get_to_move() {
+            ToMove::White => self.computer1.as_mut()?,
+            ToMove::Black => self.computer2.as_mut()?
+        };
+        let result = computer.search(&mut self.chessboard, limits, &control);
+        self.chessboard.move_piece(&result.best_move?).ok()?;
+        self.last_search_result = Some(result.clone());
+        Some(result)
+    }
+    fn finish_background_search(&mut self, player: ToMove, joined: thread::Result<(Box<dyn RecieveAndReturnMove + Send>, SearchResult)>,
+        play_move: bool) -> PyResult<Option<SearchResult>> {
+        // gives the computer of the joined worker thread back its seat and plays the move it found
+        let (computer, result) = match joined {
+            Ok(res) => res,
+            Err(_) => return Err(PyRuntimeError::new_err("the background search panicked"))
+        };
+        match player {
+            ToMove::White => self.computer1 = Some(computer),
+            ToMove::Black => self.computer2 = Some(computer)
+        }
+        if !play_move {
+            return Ok(None)
+        }
+        let best_move = match result.best_move {
+            Some(best_move) => best_move,
+            None => return Ok(None)
+        };
+        if self.chessboard.move_piece(&best_move).is_err() {
+            return Ok(None)
+        }
+        self.last_search_result = Some(result.clone());
+        self.start_pondering(player, &result);
+        Ok(Some(result))
+    }
+    fn spawn_search(&mut self, player: ToMove, mut computer: Box<dyn RecieveAndReturnMove + Send>, mut chessboard: Chessboard,
+        limits: SearchLimits, ponder: Option<Ponder>) {
+        self.stop_flag.store(false, Ordering::Relaxed);
+        let (sender, receiver) = channel();
+        let control = SearchControl::with_info(Arc::clone(&self.stop_flag), sender);
+        let search_control = control.clone();
+        let handle = thread::spawn(move || {
+            let result = computer.search(&mut chessboard, &limits, &search_control);
+            (computer, result)
+        });
+        self.background = Some(BackgroundSearch { player, handle, info: receiver, ponder, control });
+    }
+    fn start_pondering(&mut self, player: ToMove, result: &SearchResult) {
+        // Searches the position after the reply the computer expects while a human thinks. The search has no
+        // limit, it ends when the human plays something else or becomes the normal search after a ponderhit.
+        let (ponder, opponent) = match player {
+            ToMove::White => (self.ponder1, &self.computer2),
+            ToMove::Black => (self.ponder2, &self.computer1)
+        };
+        if !ponder || opponent.is_some() || self.background.is_some() {
+            return
+        }
+        let expected_reply = match result.pv.get(1) {
+            Some(expected_reply) => *expected_reply,
+            None => return
+        };
+        let mut chessboard = self.chessboard.clone();
+        if chessboard.move_piece(&expected_reply).is_err() {
+            return
+        }
+        let computer = match player {
+            ToMove::White => self.computer1.take(),
+            ToMove::Black => self.computer2.take()
+        };
+        if let Some(computer) = computer {
+            let expected_hash = chessboard.get_hash();
+            let limits = SearchLimits::new(None, None, None, None, None, None, None, None, true);
+            self.spawn_search(player, computer, chessboard, limits, Some(Ponder { expected_hash, hit: false }));
+        }
+    }
+    fn check_ponder_move(&mut self) {
+        // called after the human moved, a pondering computer keeps its search only if it expected the move
+        let expected = match &self.background {
+            Some(BackgroundSearch { ponder: Some(ponder), .. }) => ponder.expected_hash == self.chessboard.get_hash(),
+            _ => return
+        };
+        if expected {
+            if let Some(ponder) = self.background.as_mut().and_then(|search| search.ponder.as_mut()) {
+                ponder.hit = true;
+            }
+        }
+        else {
+            self.abort_background_search();
+        }
+    }
+    fn abort_background_search(&mut self) {
+        // stops the background search without playing its move, which is needed before we change the board,
+        // the analysis of the old board is stopped as well
+        if let Some(search) = self.background.take() {
+            self.stop_flag.store(true, Ordering::Relaxed);
+            let _ = self.finish_background_search(search.player, search.handle.join(), false);
+        }
+        self.abort_analysis();
+    }
+    fn abort_analysis(&mut self) {
+        // stops the background analysis and throws its result away
+        if let Some(analysis) = self.analysis.take() {
+            analysis.stop.store(true, Ordering::Relaxed);
+            let _ = self.finish_analysis(analysis.handle.join());
+        }
+    }
+    fn finish_analysis(&mut self, joined: thread::Result<(BasicTreeSearchComputer, SearchResult)>) -> PyResult<SearchResult> {
+        // puts the analyser back, a new one takes its place if the analysis panicked
+        match joined {
+            Ok((analyser, result)) => {
+                self.analyser = Some(analyser);
+                Ok(result)
+            }
+            Err(_) => {
+                self.analyser = Some(BasicTreeSearchComputer::new());
+                Err(PyRuntimeError::new_err("the analysis panicked"))
+            }
+        }
+    }
+    fn abort_pondering(&mut self) {
+        // the human keeps thinking, the computer simply ponders again after its next move
+        if self.is_pondering() {
+            self.abort_background_search();
+        }
+    }
+    fn report_info(&self, py: Python, info: &Receiver<SearchResult>) -> PyResult<()> {
+        // hands all intermediate results that came in since the last call to the info callback
+        for result in info.try_iter() {
+            if let Some(callback) = &self.info_callback {
+                callback.call1(py, (result,))?;
+            }
+        }
         Ok(())
     }
+    fn computer_of(&mut self, player: &str) -> PyResult<&mut Box<dyn RecieveAndReturnMove + Send>> {
+        // the computer playing as player1 or player2, which is not available while it searches in the background
+        let computer = match player {
+            "player1" => &mut self.computer1,
+            "player2" => &mut self.computer2,
+            _ => return Err(PyValueError::new_err(format!("unknown player {}", player)))
+        };
+        computer.as_mut().ok_or_else(|| PyValueError::new_err(format!("{} is a human or is searching", player)))
+    }
     fn select_new(&mut self, index: u8) {
         let w_pieces = self.chessboard.get_white_pieces();
         let b_pieces = self.chessboard.get_black_pieces();
@@ -104,31 +320,69 @@ impl Coordinator {
 impl Coordinator {
     #[new]
     pub fn new() -> Coordinator {
-        Coordinator { computer1: None, computer2: None, chessboard: Chessboard::new_start(), selected: Selected::None}
+        Coordinator { computer1: None, computer2: None, chessboard: Chessboard::new_start(), selected: Selected::None, last_search_result: None, stop_flag: Arc::new(AtomicBool::new(false)), background: None, info_callback: None, analyser: Some(BasicTreeSearchComputer::new()), analysis: None, ponder1: false, ponder2: false}
     }
     #[staticmethod]
     pub fn new_human_vs_human() -> Coordinator {
-        Coordinator { computer1: None, computer2: None, chessboard: Chessboard::new_start(), selected: Selected::None}
+        Coordinator { computer1: None, computer2: None, chessboard: Chessboard::new_start(), selected: Selected::None, last_search_result: None, stop_flag: Arc::new(AtomicBool::new(false)), background: None, info_callback: None, analyser: Some(BasicTreeSearchComputer::new()), analysis: None, ponder1: false, ponder2: false}
     }
     #[staticmethod]
-    pub fn new_computer_vs_computer(comp1: &str, comp2: &str) -> Coordinator {
-        Coordinator { computer1: computer_from_string(comp1), computer2: computer_from_string(comp2), chessboard: Chessboard::new_start(), selected: Selected::None}
+    pub fn new_computer_vs_computer(comp1: &str, comp2: &str) -> PyResult<Coordinator> {
+        Ok(Coordinator { computer1: player_from_name(comp1, None)?, computer2: player_from_name(comp2, None)?, chessboard: Chessboard::new_start(), selected: Selected::None, last_search_result: None, stop_flag: Arc::new(AtomicBool::new(false)), background: None, info_callback: None, analyser: Some(BasicTreeSearchComputer::new()), analysis: None, ponder1: false, ponder2: false})
     }
     #[staticmethod]
-    pub fn new_human_vs_computer(comp2: &str) -> Coordinator {
-        Coordinator { computer1: None, computer2: computer_from_string(comp2), chessboard: Chessboard::new_start(), selected: Selected::None}
+    pub fn new_human_vs_computer(comp2: &str) -> PyResult<Coordinator> {
+        Ok(Coordinator { computer1: None, computer2: player_from_name(comp2, None)?, chessboard: Chessboard::new_start(), selected: Selected::None, last_search_result: None, stop_flag: Arc::new(AtomicBool::new(false)), background: None, info_callback: None, analyser: Some(BasicTreeSearchComputer::new()), analysis: None, ponder1: false, ponder2: false})
     }
     #[staticmethod]
-    pub fn new_computer_vs_human(comp1: &str) -> Coordinator {
-        Coordinator { computer1: computer_from_string(comp1), computer2: None, chessboard: Chessboard::new_start(), selected: Selected::None}
+    pub fn new_computer_vs_human(comp1: &str) -> PyResult<Coordinator> {
+        Ok(Coordinator { computer1: player_from_name(comp1, None)?, computer2: None, chessboard: Chessboard::new_start(), selected: Selected::None, last_search_result: None, stop_flag: Arc::new(AtomicBool::new(false)), background: None, info_callback: None, analyser: Some(BasicTreeSearchComputer::new()), analysis: None, ponder1: false, ponder2: false})
     }
-    pub fn set_player1(&mut self, name: &str) {
-        self.computer1 = computer_from_string(name);
+    #[pyo3(signature = (name, options=None))]
+    pub fn set_player1(&mut self, name: &str, options: Option<HashMap<String, String>>) -> PyResult<()> {
+        // "human" or one of the computers of list_computers, with a map from option names to values
+        let computer = player_from_name(name, options)?;
+        self.abort_background_search();
+        self.computer1 = computer;
+        Ok(())
     }
-    pub fn set_player2(&mut self, name: &str) {
-        self.computer2 = computer_from_string(name);
+    #[pyo3(signature = (name, options=None))]
+    pub fn set_player2(&mut self, name: &str, options: Option<HashMap<String, String>>) -> PyResult<()> {
+        let computer = player_from_name(name, options)?;
+        self.abort_background_search();
+        self.computer2 = computer;
+        Ok(())
+    }
+    pub fn set_option(&mut self, player: &str, name: &str, value: &str) -> PyResult<()> {
+        // sets an option of the computer playing as player1 or player2, e.g. set_option("player1", "lmr", "false"),
+        // a computer that ponders stops so that it is back in its seat
+        self.abort_pondering();
+        self.computer_of(player)?.set_option(name, value)
+            .map_err(|_| PyValueError::new_err(format!("invalid option {} with value {}", name, value)))
+    }
+    pub fn set_eval_params(&mut self, player: &str, params: EvalParams) -> PyResult<()> {
+        // gives the computer playing as player1 or player2 its own evaluation weights
+        self.abort_pondering();
+        self.computer_of(player)?.set_eval_params(params)
+            .map_err(|_| PyValueError::new_err(format!("the computer of {} has no evaluation", player)))
+    }
+    pub fn get_eval_params(&mut self, player: &str) -> PyResult<EvalParams> {
+        self.computer_of(player)?.eval_params()
+            .ok_or_else(|| PyValueError::new_err(format!("the computer of {} has no evaluation", player)))
+    }
+    #[pyo3(signature = (player=None))]
+    pub fn evaluate_trace(&mut self, player: Option<&str>) -> PyResult<EvalTrace> {
+        // the static evaluation of the current position split into its terms, with the weights of the
+        // computer playing as player or the default weights of the analysis when no player is given. Endgames
+        // of the bitbases that are loaded get the score of the table, like in the search
+        let params = match player {
+            Some(player) => self.get_eval_params(player)?,
+            None => self.analyser.as_ref().and_then(|analyser| analyser.eval_params()).unwrap_or_default()
+        };
+        Ok(evaluate_trace(self.chessboard.get_position(), self.chessboard.get_pseudo_moves(), &params, true))
     }
     pub fn load_fen(&mut self, fen: String) {
+        self.abort_background_search();
         self.chessboard.load_fen(fen);
     }
 
@@ -137,6 +391,7 @@ impl Coordinator {
     }
 
     pub fn undo(&mut self) {
+        self.abort_background_search();
         self.chessboard.undo();
     }
 
@@ -150,6 +405,192 @@ impl Coordinator {
             Selected::White(i) => i as i32
         }
     }
+    pub fn get_stop_handle(&self) -> StopHandle {
+        // Python keeps this handle to stop a search from another thread, while the search runs the
+        // coordinator itself is borrowed and cannot be called.
+        StopHandle::new(Arc::clone(&self.stop_flag))
+    }
+    pub fn next_computer_move_with_limits(&mut self, py: Python, limits: SearchLimits) -> Option<SearchResult> {
+        // like next_computer_move but the computer searches until one of the limits is reached or until
+        // the stop handle is used, the GIL is released while searching so that other python threads can run
+        if self.is_searching() {
+            return None
+        }
+        self.abort_background_search();
+        self.stop_flag.store(false, Ordering::Relaxed);
+        py.allow_threads(|| self.search_computer_move(&limits))
+    }
+    pub fn start_search(&mut self, limits: SearchLimits) -> bool {
+        // Starts a search of the computer that has to move on a worker thread and returns immediately.
+        // Returns false when a human has to move or when a search is already running. Use poll_search
+        // or wait_search to get the result, the best move is played on the board when the result is collected.
+        // After a ponderhit the search that is already running becomes the search of this move, the time
+        // limit starts now. Without a time limit it starts over, but it still has its transposition table.
+        let player = *self.chessboard.get_to_move();
+        if let Some(search) = self.background.as_mut() {
+            let ponderhit = search.ponder.as_ref().is_some_and(|ponder| ponder.hit) && same_player(search.player, player);
+            let budget = limits.time_budget(&player);
+            match (ponderhit, budget) {
+                (true, Some(budget)) => {
+                    search.ponder = None;
+                    search.control.set_deadline(Instant::now() + budget);
+                    return true
+                }
+                (true, None) => self.abort_background_search(),
+                (false, _) => return false
+            }
+        }
+        let computer = match player {
+            ToMove::White => self.computer1.take(),
+            ToMove::Black => self.computer2.take()
+        };
+        let computer = match computer {
+            Some(computer) => computer,
+            None => return false
+        };
+        let chessboard = self.chessboard.clone();
+        self.spawn_search(player, computer, chessboard, limits, None);
+        true
+    }
+    pub fn is_searching(&self) -> bool {
+        // a computer that ponders does not count, the human can still move
+        self.background.as_ref().is_some_and(|search| search.ponder.is_none())
+    }
+    pub fn is_pondering(&self) -> bool {
+        self.background.as_ref().is_some_and(|search| search.ponder.is_some())
+    }
+    pub fn set_ponder(&mut self, player: &str, ponder: bool) -> PyResult<()> {
+        // lets the computer playing as player1 or player2 search on the time of a human opponent, the
+        // pondering starts after the next move the computer plays from start_search
+        match player {
+            "player1" => self.ponder1 = ponder,
+            "player2" => self.ponder2 = ponder,
+            _ => return Err(PyValueError::new_err(format!("unknown player {}", player)))
+        }
+        if !ponder {
+            self.abort_pondering();
+        }
+        Ok(())
+    }
+    pub fn poll_search(&mut self, py: Python) -> PyResult<Option<SearchResult>> {
+        // Never blocks: passes new intermediate results to the info callback and returns the final result
+        // once the search is done, None while it is still running.
+        if !self.is_searching() {
+            return Ok(None)
+        }
+        let finished = match &self.background {
+            Some(search) => {
+                self.report_info(py, &search.info)?;
+                search.handle.is_finished()
+            }
+            None => return Ok(None)
+        };
+        if !finished {
+            return Ok(None)
+        }
+        let search = self.background.take().expect("background search disappeared");
+        self.report_info(py, &search.info)?;
+        self.finish_background_search(search.player, search.handle.join(), true)
+    }
+    pub fn wait_search(&mut self, py: Python) -> PyResult<Option<SearchResult>> {
+        // blocks until the background search is done, without holding the GIL, and returns its result
+        if !self.is_searching() {
+            return Ok(None)
+        }
+        let search = match self.background.take() {
+            Some(search) => search,
+            None => return Ok(None)
+        };
+        let BackgroundSearch { player, handle, info, .. } = search;
+        let joined = py.allow_threads(move || handle.join());
+        self.report_info(py, &info)?;
+        self.finish_background_search(player, joined, true)
+    }
+    pub fn move_now(&self) {
+        // the background search stops as soon as possible, its best move so far is played when it is collected
+        if self.is_searching() {
+            self.stop_flag.store(true, Ordering::Relaxed);
+        }
+    }
+    pub fn cancel_search(&mut self, py: Python) -> PyResult<()> {
+        // stops the background search and throws its result away, nothing is played on the board
+        let search = match self.background.take() {
+            Some(search) => search,
+            None => return Ok(())
+        };
+        self.stop_flag.store(true, Ordering::Relaxed);
+        let BackgroundSearch { player, handle, .. } = search;
+        let joined = py.allow_threads(move || handle.join());
+        self.finish_background_search(player, joined, false)?;
+        Ok(())
+    }
+    pub fn analyse(&mut self, py: Python, limits: SearchLimits, multipv: usize) -> PyResult<SearchResult> {
+        // Searches the current position for the best multipv lines without playing a move, the lines of the
+        // result are sorted from best to worst. The GIL is released and the stop handle ends the analysis.
+        if self.is_searching() {
+            return Err(PyRuntimeError::new_err("cannot analyse while a computer is searching"))
+        }
+        self.abort_background_search();
+        let analyser = self.analyser.get_or_insert_with(BasicTreeSearchComputer::new);
+        analyser.set_option("multipv", &multipv.to_string())
+            .map_err(|_| PyValueError::new_err("multipv must be at least 1"))?;
+        self.stop_flag.store(false, Ordering::Relaxed);
+        let control = SearchControl::new(Arc::clone(&self.stop_flag));
+        let chessboard = &mut self.chessboard;
+        Ok(py.allow_threads(|| analyser.search(chessboard, &limits, &control)))
+    }
+    pub fn start_analysis(&mut self, limits: SearchLimits, multipv: usize) -> PyResult<()> {
+        // Like analyse, but on a worker thread so that it returns immediately. poll_analysis passes the lines
+        // of every finished iteration to the info callback. The analysis stops when the board changes.
+        if self.is_searching() {
+            return Err(PyRuntimeError::new_err("cannot analyse while a computer is searching"))
+        }
+        self.abort_background_search();
+        let mut analyser = self.analyser.take().unwrap_or_else(BasicTreeSearchComputer::new);
+        if analyser.set_option("multipv", &multipv.to_string()).is_err() {
+            self.analyser = Some(analyser);
+            return Err(PyValueError::new_err("multipv must be at least 1"))
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = channel();
+        let control = SearchControl::with_info(Arc::clone(&stop), sender);
+        let mut chessboard = self.chessboard.clone();
+        let handle = thread::spawn(move || {
+            let result = analyser.search(&mut chessboard, &limits, &control);
+            (analyser, result)
+        });
+        self.analysis = Some(BackgroundAnalysis { handle, info: receiver, stop });
+        Ok(())
+    }
+    pub fn poll_analysis(&mut self, py: Python) -> PyResult<Option<SearchResult>> {
+        // Never blocks: passes new lines to the info callback and returns the final result once the analysis
+        // is done, None while it is still running or when there is no analysis.
+        let finished = match &self.analysis {
+            Some(analysis) => {
+                self.report_info(py, &analysis.info)?;
+                analysis.handle.is_finished()
+            }
+            None => return Ok(None)
+        };
+        if !finished {
+            return Ok(None)
+        }
+        let analysis = self.analysis.take().expect("background analysis disappeared");
+        self.report_info(py, &analysis.info)?;
+        self.finish_analysis(analysis.handle.join()).map(Some)
+    }
+    pub fn is_analysing(&self) -> bool {
+        self.analysis.is_some()
+    }
+    pub fn stop_analysis(&mut self) {
+        // ends the background analysis, its result is thrown away
+        self.abort_analysis();
+    }
+    pub fn set_info_callback(&mut self, callback: Option<PyObject>) {
+        // the callback is called with a SearchResult for every finished iteration of a background search,
+        // always from the thread that calls poll_search or wait_search
+        self.info_callback = callback;
+    }
     pub fn next_computer_move(&mut self) {
         // tries to make a new computer move
         match self.next_move(None) {
@@ -160,6 +601,9 @@ impl Coordinator {
 
     pub fn input_select(&mut self, index: u8) {
         // if we try to select while a computer has to move, we return early
+        if self.is_searching() {
+            return
+        }
         match self.chessboard.get_to_move() {
             ToMove::White => {
                 match self.computer1 {
@@ -211,9 +655,11 @@ impl Coordinator {
         }
     }
     pub fn reset_position(&mut self) {
+        self.abort_background_search();
         self.chessboard = Chessboard::new_start();
     }
     pub fn empty_position(&mut self) {
+        self.abort_background_search();
         self.chessboard = Chessboard::new();
     }
 
@@ -233,4 +679,8 @@ impl Coordinator {
     pub fn get_legal_non_captures(&mut self, index: u8) -> Vec<u8> {
         self.chessboard.get_legal_non_captures(index)
     }
+    pub fn get_last_search_result(&self) -> Option<SearchResult> {
+        // returns None when no computer has searched yet or when the last computer does not search
+        self.last_search_result.clone()
+    }
 }
\ No newline at end of file
@@ -205,7 +205,7 @@ pub enum PieceColor {
 #[derive(Debug, Clone, Copy)]
 pub struct NoLegalMoveInputError;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PiecePromotes {
     Rook,
     Knight,
@@ -290,7 +290,7 @@ pub fn index2board_notation(index: u8) -> String {
     notation
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Move {
     pub from: u8,
     pub to: u8,
@@ -312,6 +312,27 @@ impl Move {
         }
         res
     }
+    pub fn from_string(notation: &str) -> Option<Move> {
+        // reads a move written like to_string does, e2e4 or e7e8q, it is not checked for legality
+        let square = |notation: &str| -> Option<u8> {
+            let mut chars = notation.chars();
+            let file = chars.next().filter(|file| ('a'..='h').contains(file))?;
+            let rank = chars.next().filter(|rank| ('1'..='8').contains(rank))?;
+            board_notation2index(&format!("{}{}", file, rank))
+        };
+        if !notation.is_ascii() || notation.len() < 4 || notation.len() > 5 {
+            return None
+        }
+        let on_promotion = match notation.get(4..) {
+            Some("q") => Some(PiecePromotes::Queen),
+            Some("r") => Some(PiecePromotes::Rook),
+            Some("b") => Some(PiecePromotes::Bishop),
+            Some("n") => Some(PiecePromotes::Knight),
+            Some("") => None,
+            _ => return None
+        };
+        Some(Move { from: square(&notation[0..2])?, to: square(&notation[2..4])?, on_promotion })
+    }
 }
 
 
@@ -322,7 +343,7 @@ pub enum Selected {
     Black(u8)
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PieceType {
     Rook,
     Knight,
@@ -366,6 +387,18 @@ impl PieceType {
         }
         else {PieceType::EmptySquare}
     }
+    pub fn see_value(&self) -> i32 {
+        // the material value in the static exchange evaluation, the king is never really captured
+        match *self {
+            PieceType::Pawn => 100,
+            PieceType::Knight => 320,
+            PieceType::Bishop => 330,
+            PieceType::Rook => 500,
+            PieceType::Queen => 900,
+            PieceType::King => 20000,
+            PieceType::EmptySquare => 0
+        }
+    }
 }
 #[derive(Clone, Debug, Copy)]
 pub struct Pieces {
@@ -625,6 +658,46 @@ impl Position {
     pub fn to_string(&self) -> String {
         (0..64).map(|i| self.index2char(i).to_string()).collect::<Vec<String>>().join("")
     }
+    pub fn fen(&self) -> String {
+        // the position in Forsyth-Edwards notation, the board starts at a8 just like our indices
+        let mut board = String::new();
+        for row in 0..8 {
+            let mut empty = 0;
+            for index in row * 8..row * 8 + 8 {
+                match self.index2char(index) {
+                    ' ' => empty += 1,
+                    piece => {
+                        if empty > 0 {
+                            board.push(char::from_digit(empty, 10).unwrap());
+                            empty = 0;
+                        }
+                        board.push(piece);
+                    }
+                }
+            }
+            if empty > 0 {
+                board.push(char::from_digit(empty, 10).unwrap());
+            }
+            if row < 7 {
+                board.push('/');
+            }
+        }
+        let to_move = match self.to_move {
+            ToMove::White => "w",
+            ToMove::Black => "b"
+        };
+        let mut castling: String = [(self.white_kingside_castle, 'K'), (self.white_queenside_castle, 'Q'),
+            (self.black_kingside_castle, 'k'), (self.black_queenside_castle, 'q')]
+            .iter().filter(|(right, _)| *right).map(|(_, c)| *c).collect();
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        let en_passant = match self.es_target {
+            Some(target) => index2board_notation(target),
+            None => "-".to_string()
+        };
+        format!("{} {} {} {} {} {}", board, to_move, castling, en_passant, self.halfmove_clock, self.fullmove_clock)
+    }
     pub fn detect_piece_color(&mut self, piece_index: u8) -> PieceColor {
         if (self.white_pieces.get_all() >> piece_index) & 1 == 1 {
             PieceColor::White
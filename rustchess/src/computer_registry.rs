@@ -0,0 +1,343 @@
+use pyo3::prelude::*;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use std::collections::HashMap;
+use crate::chess_computer::*;
+use crate::mcts::MctsComputer;
+use crate::nnue::DEFAULT_NETWORK;
+use crate::polyglot::DEFAULT_BOOK;
+use crate::search_helper::*;
+
+// Every computer the players can choose from, together with the options it understands. A computer is made from
+// its name and a map of option values, the values are checked against the type and range of the option before
+// they are passed on to set_option of the computer. Options that are not in the map keep their default.
+
+#[derive(Debug)]
+pub enum ComputerError {
+    UnknownComputer,
+    // the name of the option
+    UnknownOption(String),
+    InvalidValue(String),
+    // a file the computer cannot play without could not be loaded, with the reason
+    Unavailable(String)
+}
+
+impl ComputerError {
+    pub fn into_py_err(self, name: &str) -> PyErr {
+        // name is the computer that could not be created
+        match self {
+            ComputerError::UnknownComputer => PyValueError::new_err(format!("unknown computer {}", name)),
+            ComputerError::UnknownOption(option) => PyValueError::new_err(format!("{} has no option {}", name, option)),
+            ComputerError::InvalidValue(option) => PyValueError::new_err(format!("invalid value for the option {} of {}", option, name)),
+            ComputerError::Unavailable(reason) => PyIOError::new_err(format!("{} cannot play, {}", name, reason))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    Bool,
+    Int,
+    Float,
+    // one of a fixed list of words
+    Choice,
+    // a file name or anything else the computer checks itself
+    Text
+}
+
+impl OptionKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OptionKind::Bool => "bool",
+            OptionKind::Int => "int",
+            OptionKind::Float => "float",
+            OptionKind::Choice => "choice",
+            OptionKind::Text => "text"
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct ComputerOption {
+    name: &'static str,
+    kind: OptionKind,
+    // None when the computer has no fixed default, like a random seed or a file that is not loaded
+    default: Option<&'static str>,
+    min: Option<f64>,
+    max: Option<f64>,
+    choices: &'static [&'static str],
+    description: &'static str
+}
+
+const fn boolean(name: &'static str, default: &'static str, description: &'static str) -> ComputerOption {
+    ComputerOption { name, kind: OptionKind::Bool, default: Some(default), min: None, max: None, choices: &[], description }
+}
+
+const fn int(name: &'static str, default: Option<&'static str>, min: f64, max: f64, description: &'static str) -> ComputerOption {
+    ComputerOption { name, kind: OptionKind::Int, default, min: Some(min), max: Some(max), choices: &[], description }
+}
+
+const fn float(name: &'static str, default: &'static str, min: f64, max: f64, description: &'static str) -> ComputerOption {
+    ComputerOption { name, kind: OptionKind::Float, default: Some(default), min: Some(min), max: Some(max), choices: &[], description }
+}
+
+const fn choice(name: &'static str, default: &'static str, choices: &'static [&'static str], description: &'static str) -> ComputerOption {
+    ComputerOption { name, kind: OptionKind::Choice, default: Some(default), min: None, max: None, choices, description }
+}
+
+const fn text(name: &'static str, description: &'static str) -> ComputerOption {
+    ComputerOption { name, kind: OptionKind::Text, default: None, min: None, max: None, choices: &[], description }
+}
+
+const SEED: f64 = u64::MAX as f64;
+
+const RANDOM_OPTIONS: &[ComputerOption] = &[
+    int("seed", None, 0.0, SEED, "the seed of the random moves")
+];
+
+const TACTICAL_OPTIONS: &[ComputerOption] = &[
+    float("capture_weight", "4", 0.0, 1000.0, "how much more likely a capture is than a quiet move"),
+    float("check_weight", "2", 0.0, 1000.0, "how much more likely a check is than a quiet move")
+];
+
+const SOFTMAX_OPTIONS: &[ComputerOption] = &[
+    float("temperature", "50", 0.001, 10000.0, "in centipawns, higher temperatures play worse moves more often")
+];
+
+const SEARCH_OPTIONS: &[ComputerOption] = &[
+    int("depth", Some("5"), 1.0, (MAX_PLY - 1) as f64, "the depth of the search when no other limit is given"),
+    int("threads", Some("1"), 1.0, 256.0, "the number of threads that search together"),
+    int("multipv", Some("1"), 1.0, 256.0, "the number of best lines that are searched"),
+    int("hash", Some("16"), 1.0, 4096.0, "the size of the transposition table in megabytes"),
+    boolean("pvs", "true", "principal variation search"),
+    boolean("aspiration", "true", "aspiration windows"),
+    boolean("null_move", "true", "null move pruning"),
+    boolean("lmr", "true", "late move reductions"),
+    boolean("check_extensions", "true", "search one ply deeper when in check"),
+    boolean("quiescence", "true", "search the captures at the end of the search"),
+    boolean("see_pruning", "true", "skip captures that lose material in the quiescence search"),
+    boolean("bitbases", "true", "score the endgames of the bitbases exactly"),
+    text("eval_file", "a json file with the weights of the evaluation"),
+    text("nnue_file", "a network that replaces the evaluation, empty for none"),
+    text("syzygy_path", "directories with syzygy tablebases, empty for none")
+];
+
+const BOOK_OPTIONS: &[ComputerOption] = &[
+    text("book_file", "a polyglot opening book"),
+    choice("book_selection", "random", &["best", "random"], "play the most common book move or a weighted random one"),
+    int("book_seed", None, 0.0, SEED, "the seed of the random book moves")
+];
+
+const MCTS_OPTIONS: &[ComputerOption] = &[
+    int("iterations", Some("20000"), 1.0, 1e9, "the number of iterations when no other limit is given"),
+    float("exploration", "1.4", 0.0, 100.0, "how much the search prefers moves that were tried less often"),
+    choice("selection", "puct", &["uct", "puct"], "the formula that picks the next child"),
+    choice("rollout", "evaluation", &["random", "evaluation"], "random playouts or the evaluation of the new position"),
+    int("rollout_depth", Some("40"), 0.0, 1000.0, "random playouts stop after this many plies"),
+    boolean("tree_reuse", "true", "keep the tree of the previous move"),
+    int("seed", None, 0.0, SEED, "the seed of the random playouts")
+];
+
+const SKILL_OPTIONS: &[ComputerOption] = &[
+    int("skill_level", Some("10"), 0.0, SkillLevel::MAX_LEVEL as f64, "sets all values below at once, 0 is a beginner"),
+    int("elo", None, SkillLevel::MIN_ELO as f64, SkillLevel::MAX_ELO as f64, "sets all values below from a rough rating"),
+    int("skill_depth", None, 1.0, (MAX_PLY - 1) as f64, "the deepest search"),
+    int("skill_nodes", None, 0.0, 1e12, "the most nodes per search, 0 for no limit"),
+    int("skill_candidates", None, 1.0, 256.0, "the number of best lines the move is picked from"),
+    int("score_noise", None, 0.0, 10000.0, "the largest number of centipawns added to or subtracted from a line"),
+    int("blunder_chance", None, 0.0, 100.0, "the chance in percent to play any of the lines"),
+    int("skill_seed", None, 0.0, SEED, "the seed of the random choices")
+];
+
+impl ComputerOption {
+    pub fn check(&self, value: &str) -> bool {
+        // whether value has the type of the option and lies in its range
+        let number = match self.kind {
+            OptionKind::Bool => return value == "true" || value == "false",
+            OptionKind::Choice => return self.choices.contains(&value),
+            OptionKind::Text => return true,
+            OptionKind::Int => value.parse::<i128>().ok().map(|number| number as f64),
+            OptionKind::Float => value.parse::<f64>().ok().filter(|number| number.is_finite())
+        };
+        match number {
+            Some(number) => !self.min.is_some_and(|min| number < min) && !self.max.is_some_and(|max| number > max),
+            None => false
+        }
+    }
+}
+
+#[pymethods]
+impl ComputerOption {
+    pub fn get_name(&self) -> String {
+        self.name.to_string()
+    }
+    pub fn get_type(&self) -> String {
+        self.kind.name().to_string()
+    }
+    pub fn get_default(&self) -> Option<String> {
+        self.default.map(|default| default.to_string())
+    }
+    pub fn get_min(&self) -> Option<f64> {
+        self.min
+    }
+    pub fn get_max(&self) -> Option<f64> {
+        self.max
+    }
+    pub fn get_choices(&self) -> Vec<String> {
+        self.choices.iter().map(|choice| choice.to_string()).collect()
+    }
+    pub fn get_description(&self) -> String {
+        self.description.to_string()
+    }
+}
+
+#[pyclass]
+#[derive(Clone)]
+pub struct ComputerInfo {
+    name: &'static str,
+    description: &'static str,
+    options: Vec<ComputerOption>,
+    create: CreateComputer
+}
+
+// makes a computer before the options are set, it sees the options for the files it cannot do without
+type CreateComputer = fn(&HashMap<String, String>) -> Result<Box<dyn RecieveAndReturnMove + Send>, ComputerError>;
+
+impl ComputerInfo {
+    fn new(name: &'static str, description: &'static str, options: &[&[ComputerOption]], create: CreateComputer) -> ComputerInfo {
+        ComputerInfo { name, description, options: options.concat(), create }
+    }
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+    pub fn create(&self, options: &HashMap<String, String>) -> Result<Box<dyn RecieveAndReturnMove + Send>, ComputerError> {
+        if let Some(name) = options.keys().find(|name| !self.options.iter().any(|option| option.name == *name)) {
+            return Err(ComputerError::UnknownOption(name.clone()))
+        }
+        let mut computer = (self.create)(options)?;
+        // the options are set in the order of the list, so that skill_level comes before the values it sets
+        for option in self.options.iter() {
+            if let Some(value) = options.get(option.name) {
+                if !option.check(value) {
+                    return Err(ComputerError::InvalidValue(option.name.to_string()))
+                }
+                computer.set_option(option.name, value).map_err(|_| ComputerError::InvalidValue(option.name.to_string()))?;
+            }
+        }
+        Ok(computer)
+    }
+}
+
+#[pymethods]
+impl ComputerInfo {
+    pub fn get_name(&self) -> String {
+        self.name.to_string()
+    }
+    pub fn get_description(&self) -> String {
+        self.description.to_string()
+    }
+    pub fn get_options(&self) -> Vec<ComputerOption> {
+        self.options.clone()
+    }
+}
+
+pub fn registered_computers() -> Vec<ComputerInfo> {
+    // keep adding newer and different versions of computers
+    let skill_search_options: Vec<ComputerOption> = SEARCH_OPTIONS.iter()
+        .filter(|option| option.name != "depth" && option.name != "multipv")
+        .cloned()
+        .collect();
+    vec![
+        ComputerInfo::new("random", "plays a random legal move", &[RANDOM_OPTIONS], |_| Ok(Box::new(RandomComputer::new()))),
+        ComputerInfo::new("random_tactical", "plays random moves, mostly captures and checks", &[RANDOM_OPTIONS, TACTICAL_OPTIONS],
+            |_| Ok(Box::new(RandomComputer::with_policy(RandomPolicy::Tactical)))),
+        ComputerInfo::new("random_softmax", "plays random moves, mostly the ones that evaluate best", &[RANDOM_OPTIONS, SOFTMAX_OPTIONS],
+            |_| Ok(Box::new(RandomComputer::with_policy(RandomPolicy::Softmax)))),
+        ComputerInfo::new("basic", "the alpha-beta search with the hand written evaluation", &[SEARCH_OPTIONS],
+            |_| Ok(Box::new(BasicTreeSearchComputer::new()))),
+        ComputerInfo::new("nnue", "the alpha-beta search with a network, the default one unless nnue_file is given", &[SEARCH_OPTIONS],
+            |options| {
+                let computer = match options.get("nnue_file") {
+                    Some(filepath) => NnueComputer::from_file(filepath),
+                    None => NnueComputer::new()
+                };
+                let filepath = options.get("nnue_file").map_or(DEFAULT_NETWORK, |filepath| filepath.as_str());
+                match computer {
+                    Ok(computer) => Ok(Box::new(computer)),
+                    Err(err) => Err(ComputerError::Unavailable(format!("no network in {}: {}", filepath, err)))
+                }
+            }),
+        ComputerInfo::new("book", "plays from the opening book, then searches like basic", &[BOOK_OPTIONS, SEARCH_OPTIONS],
+            |options| {
+                let fallback = Box::new(BasicTreeSearchComputer::new());
+                let computer = match options.get("book_file") {
+                    Some(filepath) => BookComputer::from_file(filepath, fallback),
+                    None => BookComputer::new(fallback)
+                };
+                let filepath = options.get("book_file").map_or(DEFAULT_BOOK, |filepath| filepath.as_str());
+                match computer {
+                    Ok(computer) => Ok(Box::new(computer)),
+                    Err(err) => Err(ComputerError::Unavailable(format!("no opening book in {}: {}", filepath, err)))
+                }
+            }),
+        ComputerInfo::new("mcts", "Monte Carlo tree search", &[MCTS_OPTIONS], |_| Ok(Box::new(MctsComputer::new()))),
+        ComputerInfo::new("skill", "the alpha-beta search at a chosen strength", &[SKILL_OPTIONS, &skill_search_options],
+            |_| Ok(Box::new(SkillComputer::new(SkillLevel::from_level(10)))))
+    ]
+}
+
+pub fn find_computer(name: &str) -> Option<ComputerInfo> {
+    registered_computers().into_iter().find(|info| info.name == name)
+}
+
+pub fn create_computer(name: &str, options: &HashMap<String, String>) -> Result<Box<dyn RecieveAndReturnMove + Send>, ComputerError> {
+    find_computer(name).ok_or(ComputerError::UnknownComputer)?.create(options)
+}
+
+#[pyfunction]
+pub fn list_computers() -> Vec<ComputerInfo> {
+    // the computers in the order they are shown in the menus, "human" is not one of them
+    registered_computers()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nnue::{FeatureSet, NnueNetwork};
+    use crate::test_helper::*;
+
+    #[test]
+    fn nnue_needs_a_network() {
+        let options = HashMap::from([("nnue_file".to_string(), "no/such/network.nnue".to_string())]);
+        assert!(matches!(create_computer("nnue", &options), Err(ComputerError::Unavailable(_))));
+
+        let filepath = std::env::temp_dir().join("rustchess_registry_network.nnue");
+        let filepath = filepath.to_str().unwrap().to_string();
+        NnueNetwork::random_network(FeatureSet::HalfKP, 16, 4).save_file(&filepath).unwrap();
+        let options = HashMap::from([("nnue_file".to_string(), filepath.clone())]);
+        let computer = create_computer("nnue", &options);
+        std::fs::remove_file(&filepath).unwrap();
+        let mut computer = computer.unwrap();
+        // the network can be swapped but not taken away
+        assert!(computer.set_option("nnue_file", "").is_err());
+        let mut board = chessboard(KIWIPETE_FEN);
+        let result = computer.search(&mut board, &SearchLimits::from_depth(2), &SearchControl::without_stop());
+        assert!(result.best_move.is_some());
+        assert_eq!(board.get_fen(), chessboard(KIWIPETE_FEN).get_fen());
+    }
+
+    #[test]
+    fn book_needs_a_book() {
+        let options = HashMap::from([("book_file".to_string(), "no/such/book.bin".to_string())]);
+        assert!(matches!(create_computer("book", &options), Err(ComputerError::Unavailable(_))));
+    }
+
+    #[test]
+    fn options_are_checked() {
+        let options = HashMap::from([("depth".to_string(), "0".to_string())]);
+        assert!(matches!(create_computer("basic", &options), Err(ComputerError::InvalidValue(_))));
+        let options = HashMap::from([("iterations".to_string(), "10".to_string())]);
+        assert!(matches!(create_computer("basic", &options), Err(ComputerError::UnknownOption(_))));
+        assert!(matches!(create_computer("nobody", &HashMap::new()), Err(ComputerError::UnknownComputer)));
+    }
+}
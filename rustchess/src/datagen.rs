@@ -0,0 +1,371 @@
+use pyo3::prelude::*;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+use std::thread;
+use crate::chessboard::*;
+use crate::chessboard_helper::*;
+use crate::chess_computer::RecieveAndReturnMove;
+use crate::computer_registry::create_computer;
+use crate::search_helper::*;
+
+// Generates training data by letting a computer play against itself. Every game starts with a few random
+// moves so that the games differ, after that every position is searched and stored together with the score
+// and best move of the search. Once the game is over the result is added to all positions of the game.
+//
+// Positions are written either as one json object per line, {"fen": .., "score": .., "move": .., "result": ..},
+// or in a binary format with 32 bytes per position:
+//   occupancy u64, the pieces of the occupied squares from a8 to h1 as 4 bits each (color << 3 | piece with
+//   pawn, knight, bishop, rook, queen, king as 0 to 5, the first square in the low bits) in 16 bytes,
+//   flags u8 (bit 0 black to move, bits 1 to 4 the castling rights KQkq), en passant square u8 (64 for none),
+//   halfmove clock u8, score i16, move u16 (from | to << 6 | promotion << 12 with none, knight, bishop,
+//   rook, queen as 0 to 4), result u8 (0 black won, 1 draw, 2 white won)
+// All numbers are little endian. Scores and results are always seen from white.
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct DatagenOptions {
+    #[pyo3(get, set)]
+    pub games: usize,
+    #[pyo3(get, set)]
+    pub threads: usize,
+    // the name of the computer that plays both sides, one of list_computers
+    #[pyo3(get, set)]
+    pub computer: String,
+    // the limits of every search, at least one of them has to be set
+    #[pyo3(get, set)]
+    pub depth: Option<u8>,
+    #[pyo3(get, set)]
+    pub nodes: Option<u64>,
+    // the number of random moves at the start of every game
+    #[pyo3(get, set)]
+    pub random_plies: usize,
+    // "jsonl" or "binary"
+    #[pyo3(get, set)]
+    pub format: String,
+    #[pyo3(get, set)]
+    pub seed: u64,
+    // a game is adjudicated as won once the score stays above win_score for win_plies plies in a row
+    #[pyo3(get, set)]
+    pub win_score: i32,
+    #[pyo3(get, set)]
+    pub win_plies: usize,
+    // and as a draw once the score stays within draw_score for draw_plies plies after ply draw_after
+    #[pyo3(get, set)]
+    pub draw_score: i32,
+    #[pyo3(get, set)]
+    pub draw_plies: usize,
+    #[pyo3(get, set)]
+    pub draw_after: usize,
+    // games that reach this many plies are counted as a draw
+    #[pyo3(get, set)]
+    pub max_plies: usize
+}
+
+#[pymethods]
+impl DatagenOptions {
+    #[new]
+    #[pyo3(signature = (games=100, threads=1, computer="basic".to_string(), depth=None, nodes=5000, random_plies=8,
+        format="jsonl".to_string(), seed=0, win_score=1000, win_plies=4, draw_score=10, draw_plies=10, draw_after=80, max_plies=400))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(games: usize, threads: usize, computer: String, depth: Option<u8>, nodes: Option<u64>, random_plies: usize,
+        format: String, seed: u64, win_score: i32, win_plies: usize, draw_score: i32, draw_plies: usize, draw_after: usize,
+        max_plies: usize) -> DatagenOptions {
+        DatagenOptions { games, threads, computer, depth, nodes, random_plies, format, seed, win_score, win_plies,
+            draw_score, draw_plies, draw_after, max_plies }
+    }
+}
+
+impl Default for DatagenOptions {
+    fn default() -> DatagenOptions {
+        DatagenOptions::new(100, 1, "basic".to_string(), None, Some(5000), 8, "jsonl".to_string(), 0, 1000, 4, 10, 10, 80, 400)
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone, Default)]
+pub struct DatagenReport {
+    #[pyo3(get)]
+    pub games: usize,
+    #[pyo3(get)]
+    pub positions: usize,
+    #[pyo3(get)]
+    pub white_wins: usize,
+    #[pyo3(get)]
+    pub black_wins: usize,
+    #[pyo3(get)]
+    pub draws: usize
+}
+
+struct Record {
+    position: Position,
+    // from the perspective of white
+    score: i32,
+    best_move: Move
+}
+
+struct GameRecords {
+    records: Vec<Record>,
+    // 1.0 white won, 0.5 draw, 0.0 black won
+    result: f64
+}
+
+pub fn insufficient_material(position: &Position) -> bool {
+    // no side can ever mate when there are no pawns, rooks or queens and each side has at most one minor piece
+    let minor = |pieces: &Pieces| (pieces.get_bb_knights() | pieces.get_bb_bishops()).count_ones();
+    let heavy = |pieces: &Pieces| pieces.get_bb_pawns() | pieces.get_bb_rooks() | pieces.get_bb_queens();
+    heavy(&position.white_pieces) == 0 && heavy(&position.black_pieces) == 0
+        && minor(&position.white_pieces) <= 1 && minor(&position.black_pieces) <= 1
+}
+
+fn play_random_opening(chessboard: &mut Chessboard, plies: usize, rng: &mut StdRng) -> bool {
+    // returns false when the random moves already ended the game, the opening is then played again
+    for _ in 0..plies {
+        let moves = chessboard.all_moves();
+        match moves.choose(rng) {
+            Some(new_move) => chessboard.move_piece(new_move).unwrap(),
+            None => return false
+        }
+    }
+    !chessboard.all_moves().is_empty()
+}
+
+fn play_game(options: &DatagenOptions, game: usize, limits: &SearchLimits, start: &Chessboard,
+    computer: &mut dyn RecieveAndReturnMove) -> GameRecords {
+    // Plays one game, the same seed and game number always give the same game since the computer forgets the
+    // previous game first. The board is a copy of start, loading the lookup tables changes the working
+    // directory which threads must not do.
+    computer.new_game();
+    let mut rng = StdRng::seed_from_u64(options.seed ^ (game as u64).wrapping_mul(0x9E3779B97F4A7C15));
+    let mut chessboard = start.clone();
+    while !play_random_opening(&mut chessboard, options.random_plies, &mut rng) {
+        chessboard = start.clone();
+    }
+    let control = SearchControl::without_stop();
+    let mut records = Vec::new();
+    let mut repetitions: HashMap<u64, usize> = HashMap::new();
+    let (mut white_streak, mut black_streak, mut draw_streak) = (0, 0, 0);
+    let mut ply = 0;
+    let result = loop {
+        let position = *chessboard.get_position();
+        if chessboard.all_moves().is_empty() {
+            // checkmate or stalemate
+            break match (chessboard.is_in_check(), position.to_move) {
+                (false, _) => 0.5,
+                (true, ToMove::White) => 0.0,
+                (true, ToMove::Black) => 1.0
+            }
+        }
+        let seen = repetitions.entry(chessboard.get_hash()).or_insert(0);
+        *seen += 1;
+        if *seen >= 3 || position.halfmove_clock >= 100 || insufficient_material(&position) || ply >= options.max_plies {
+            break 0.5
+        }
+        let search = computer.search(&mut chessboard, limits, &control);
+        let best_move = search.best_move.expect("there is a legal move");
+        let score = match position.to_move {
+            ToMove::White => search.score,
+            ToMove::Black => -search.score
+        };
+        // positions in check and mate scores teach an evaluation nothing, but they still count for adjudication
+        if !chessboard.is_in_check() && !is_mate_score(score) {
+            records.push(Record { position, score, best_move });
+        }
+        white_streak = if score >= options.win_score {white_streak + 1} else {0};
+        black_streak = if score <= -options.win_score {black_streak + 1} else {0};
+        draw_streak = if ply >= options.draw_after && score.abs() <= options.draw_score {draw_streak + 1} else {0};
+        if white_streak >= options.win_plies {
+            break 1.0
+        }
+        if black_streak >= options.win_plies {
+            break 0.0
+        }
+        if draw_streak >= options.draw_plies {
+            break 0.5
+        }
+        chessboard.move_piece(&best_move).unwrap();
+        ply += 1;
+    };
+    GameRecords { records, result }
+}
+
+fn encode_record(record: &Record, result: f64) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    let position = &record.position;
+    let (mut white, mut black) = (position.white_pieces, position.black_pieces);
+    let white_occupancy = white.get_all();
+    let occupancy = white_occupancy | black.get_all();
+    bytes[..8].copy_from_slice(&occupancy.to_le_bytes());
+    let mut nibble = 0;
+    let mut bb = occupancy;
+    while bb != 0 {
+        let square = bb.trailing_zeros() as u8;
+        bb &= bb - 1;
+        let (color, pieces) = if white_occupancy >> square & 1 == 1 {(0, &white)} else {(1, &black)};
+        let piece = match pieces.detect_piece_type(square) {
+            PieceType::Pawn => 0,
+            PieceType::Knight => 1,
+            PieceType::Bishop => 2,
+            PieceType::Rook => 3,
+            PieceType::Queen => 4,
+            _ => 5
+        };
+        bytes[8 + nibble / 2] |= (color << 3 | piece) << (4 * (nibble % 2));
+        nibble += 1;
+    }
+    let mut flags = match position.to_move {
+        ToMove::White => 0,
+        ToMove::Black => 1
+    };
+    for (i, right) in [position.white_kingside_castle, position.white_queenside_castle,
+        position.black_kingside_castle, position.black_queenside_castle].iter().enumerate() {
+        if *right {
+            flags |= 2 << i;
+        }
+    }
+    bytes[24] = flags;
+    bytes[25] = position.es_target.unwrap_or(64);
+    bytes[26] = position.halfmove_clock;
+    bytes[27..29].copy_from_slice(&(record.score.clamp(i16::MIN as i32, i16::MAX as i32) as i16).to_le_bytes());
+    let promotion = match record.best_move.on_promotion {
+        None => 0,
+        Some(PiecePromotes::Knight) => 1,
+        Some(PiecePromotes::Bishop) => 2,
+        Some(PiecePromotes::Rook) => 3,
+        Some(PiecePromotes::Queen) => 4
+    };
+    let encoded_move = record.best_move.from as u16 | (record.best_move.to as u16) << 6 | promotion << 12;
+    bytes[29..31].copy_from_slice(&encoded_move.to_le_bytes());
+    bytes[31] = (result * 2.0) as u8;
+    bytes
+}
+
+fn write_game(writer: &mut impl Write, game: &GameRecords, binary: bool) -> std::io::Result<()> {
+    for record in game.records.iter() {
+        if binary {
+            writer.write_all(&encode_record(record, game.result))?;
+        }
+        else {
+            let line = json!({"fen": record.position.fen(), "score": record.score,
+                "move": record.best_move.to_string(), "result": game.result});
+            writeln!(writer, "{}", line)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn generate(output: &str, options: &DatagenOptions, computers: Vec<Box<dyn RecieveAndReturnMove + Send>>,
+    progress: &mut dyn FnMut(&DatagenReport)) -> std::io::Result<DatagenReport> {
+    // Every computer plays on its own thread. The games are divided over the threads through a shared counter,
+    // finished games are sent to this thread which writes them, so the positions of a game always stay together
+    // in the file. progress is called with the report so far after every game.
+    let binary = options.format == "binary";
+    let mut writer = BufWriter::new(fs::File::create(output)?);
+    let limits = SearchLimits::new(options.depth, options.nodes, None, None, None, None, None, None, false);
+    let start = Chessboard::new_start();
+    let next_game = AtomicUsize::new(0);
+    let (sender, receiver) = channel();
+    let mut report = DatagenReport::default();
+    thread::scope(|scope| -> std::io::Result<()> {
+        for mut computer in computers {
+            let sender = sender.clone();
+            let next_game = &next_game;
+            let limits = &limits;
+            let start = &start;
+            scope.spawn(move || {
+                loop {
+                    let game = next_game.fetch_add(1, Ordering::Relaxed);
+                    if game >= options.games || sender.send(play_game(options, game, limits, start, computer.as_mut())).is_err() {
+                        break
+                    }
+                }
+            });
+        }
+        drop(sender);
+        for game in receiver {
+            write_game(&mut writer, &game, binary)?;
+            report.games += 1;
+            report.positions += game.records.len();
+            match game.result {
+                result if result > 0.75 => report.white_wins += 1,
+                result if result < 0.25 => report.black_wins += 1,
+                _ => report.draws += 1
+            }
+            progress(&report);
+        }
+        Ok(())
+    })?;
+    writer.flush()?;
+    Ok(report)
+}
+
+#[pyfunction]
+#[pyo3(signature = (output, options=None, progress=None))]
+pub fn generate_data(py: Python, output: &str, options: Option<DatagenOptions>, progress: Option<PyObject>) -> PyResult<DatagenReport> {
+    // Plays self-play games and writes their positions to output, see DatagenOptions for the settings. progress
+    // is called with a DatagenReport of the games so far after every game.
+    let options = options.unwrap_or_default();
+    if options.format != "jsonl" && options.format != "binary" {
+        return Err(PyValueError::new_err("the format must be jsonl or binary"))
+    }
+    if options.depth.is_none() && options.nodes.is_none() {
+        return Err(PyValueError::new_err("set a depth or a node limit for the searches"))
+    }
+    if options.threads == 0 {
+        return Err(PyValueError::new_err("threads must be at least 1"))
+    }
+    let computers = (0..options.threads).map(|_| create_computer(&options.computer, &HashMap::new()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| err.into_py_err(&options.computer))?;
+    // an error raised by the callback is kept and returned once all games are written
+    let mut callback_error = None;
+    let mut report_progress = |report: &DatagenReport| {
+        if let (Some(callback), None) = (&progress, &callback_error) {
+            callback_error = Python::with_gil(|py| callback.call1(py, (report.clone(),)).err());
+        }
+    };
+    let report = py.allow_threads(|| generate(output, &options, computers, &mut report_progress))
+        .map_err(|err| PyIOError::new_err(err.to_string()))?;
+    match callback_error {
+        Some(err) => Err(err),
+        None => Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::*;
+
+    fn generate_lines(threads: usize) -> Vec<String> {
+        // the positions of a few short games, sorted since the threads finish their games in any order
+        let options = DatagenOptions::new(4, threads, "basic".to_string(), Some(1), None, 6, "jsonl".to_string(), 7,
+            1000, 4, 10, 10, 80, 40);
+        let computers = (0..threads).map(|_| create_computer("basic", &HashMap::new()).unwrap()).collect();
+        let output = std::env::temp_dir().join(format!("rustchess_datagen_{}.jsonl", threads));
+        let output = output.to_str().unwrap();
+        let mut reported = Vec::new();
+        let report = generate(output, &options, computers, &mut |report| reported.push(report.games)).unwrap();
+        assert_eq!(report.games, 4);
+        assert_eq!(reported, vec![1, 2, 3, 4]);
+        let mut lines: Vec<String> = fs::read_to_string(output).unwrap().lines().map(|line| line.to_string()).collect();
+        fs::remove_file(output).unwrap();
+        assert_eq!(lines.len(), report.positions);
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn games_do_not_depend_on_the_threads() {
+        // the working directory has to point at the lookup tables before generate makes its start board
+        chessboard(START_FEN);
+        assert_eq!(generate_lines(1), generate_lines(2));
+    }
+}
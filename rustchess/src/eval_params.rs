@@ -0,0 +1,377 @@
+use pyo3::prelude::*;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use serde::{Serialize, Deserialize};
+use serde_big_array::BigArray;
+use serde_json::Value;
+use std::fs;
+use std::io::{BufReader, BufWriter, Write};
+use crate::evaluation::Score;
+use crate::search_helper::InvalidOptionError;
+
+// All weights of the evaluation in one place. The parameters can be written to and read from a json file, so
+// that we can try different evaluations against each other without recompiling. Every single number can also
+// be read and changed by its name, which is the path to it in the json file with dots in between, for example
+// "knight_value.mg" or "passed_pawn_end_game.6". Values missing from a file keep their default.
+
+#[pyclass]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct EvalParams {
+    pub pawn_value: Score,
+    pub knight_value: Score,
+    pub bishop_value: Score,
+    pub rook_value: Score,
+    pub queen_value: Score,
+    // the weight of every piece type in the game phase, the start position has the highest phase
+    pub knight_phase: i32,
+    pub bishop_phase: i32,
+    pub rook_phase: i32,
+    pub queen_phase: i32,
+    // piece square tables, written from the perspective of white with a8 first
+    #[serde(with = "BigArray")]
+    pub pawn_table_middle_game: [i32; 64],
+    #[serde(with = "BigArray")]
+    pub pawn_table_end_game: [i32; 64],
+    #[serde(with = "BigArray")]
+    pub knight_table_middle_game: [i32; 64],
+    #[serde(with = "BigArray")]
+    pub knight_table_end_game: [i32; 64],
+    #[serde(with = "BigArray")]
+    pub bishop_table_middle_game: [i32; 64],
+    #[serde(with = "BigArray")]
+    pub bishop_table_end_game: [i32; 64],
+    #[serde(with = "BigArray")]
+    pub rook_table_middle_game: [i32; 64],
+    #[serde(with = "BigArray")]
+    pub rook_table_end_game: [i32; 64],
+    #[serde(with = "BigArray")]
+    pub queen_table_middle_game: [i32; 64],
+    #[serde(with = "BigArray")]
+    pub queen_table_end_game: [i32; 64],
+    #[serde(with = "BigArray")]
+    pub king_table_middle_game: [i32; 64],
+    #[serde(with = "BigArray")]
+    pub king_table_end_game: [i32; 64],
+    // pawn structure, penalties are given per pawn and bonuses are indexed by the rank seen from the pawn's side
+    pub doubled_pawn: Score,
+    pub isolated_pawn: Score,
+    pub backward_pawn: Score,
+    pub passed_pawn_middle_game: [i32; 8],
+    pub passed_pawn_end_game: [i32; 8],
+    pub connected_pawn_middle_game: [i32; 8],
+    pub connected_pawn_end_game: [i32; 8],
+    // mobility is scored per square a piece can go to that is not blocked by an own piece or attacked by an
+    // enemy pawn, counted relative to the average number of such squares so that the scores stay around zero
+    pub knight_mobility: Score,
+    pub bishop_mobility: Score,
+    pub rook_mobility: Score,
+    pub queen_mobility: Score,
+    pub knight_average_mobility: i32,
+    pub bishop_average_mobility: i32,
+    pub rook_average_mobility: i32,
+    pub queen_average_mobility: i32,
+    // king safety, the attack weights are summed for all pieces that attack the zone around the enemy king and
+    // scaled by a percentage that grows with the number of attackers, since a lone attacker is rarely dangerous
+    pub knight_king_attack: i32,
+    pub bishop_king_attack: i32,
+    pub rook_king_attack: i32,
+    pub queen_king_attack: i32,
+    pub king_attackers_scale: [i32; 8],
+    pub pawn_shield_close: Score,
+    pub pawn_shield_far: Score,
+    pub king_semi_open_file: Score,
+    pub king_open_file: Score,
+    // pieces
+    pub bishop_pair: Score,
+    pub rook_open_file: Score,
+    pub rook_semi_open_file: Score,
+    pub rook_on_seventh: Score,
+    pub knight_outpost: Score
+}
+
+impl Default for EvalParams {
+    fn default() -> EvalParams {
+        EvalParams::new()
+    }
+}
+
+fn collect_names(value: &Value, prefix: String, names: &mut Vec<String>) {
+    // walks through the json tree and collects the path to every number
+    let join = |key: String| if prefix.is_empty() {key} else {format!("{}.{}", prefix, key)};
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                collect_names(child, join(key.clone()), names);
+            }
+        }
+        Value::Array(array) => {
+            for (i, child) in array.iter().enumerate() {
+                collect_names(child, join(i.to_string()), names);
+            }
+        }
+        Value::Number(_) => names.push(prefix),
+        _ => ()
+    }
+}
+
+fn name_to_pointer(name: &str) -> String {
+    format!("/{}", name.replace('.', "/"))
+}
+
+impl EvalParams {
+    pub fn total_phase(&self) -> i32 {
+        // the phase of the start position
+        4 * self.knight_phase + 4 * self.bishop_phase + 4 * self.rook_phase + 2 * self.queen_phase
+    }
+    pub fn load_file(filepath: &str) -> Result<EvalParams, std::io::Error> {
+        let f = fs::File::open(filepath)?;
+        let reader = BufReader::new(f);
+        Ok(serde_json::from_reader(reader)?)
+    }
+    pub fn save_file(&self, filepath: &str) -> Result<(), std::io::Error> {
+        let file = fs::File::create(filepath)?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+    pub fn names(&self) -> Vec<String> {
+        // the names of all parameters in a fixed order
+        let mut names = Vec::new();
+        collect_names(&serde_json::to_value(self).expect("parameters can always be written as json"), String::new(), &mut names);
+        names
+    }
+    pub fn values(&self) -> Vec<i32> {
+        // all parameters in the same order as names
+        let value = serde_json::to_value(self).expect("parameters can always be written as json");
+        self.names().iter().map(|name| value.pointer(&name_to_pointer(name)).and_then(Value::as_i64).unwrap_or(0) as i32).collect()
+    }
+    pub fn set_values(&mut self, values: &[i32]) -> Result<(), InvalidOptionError> {
+        // sets all parameters at once in the order of names
+        let names = self.names();
+        if names.len() != values.len() {
+            return Err(InvalidOptionError)
+        }
+        let mut json = serde_json::to_value(&*self).map_err(|_| InvalidOptionError)?;
+        for (name, value) in names.iter().zip(values) {
+            *json.pointer_mut(&name_to_pointer(name)).ok_or(InvalidOptionError)? = Value::from(*value);
+        }
+        *self = serde_json::from_value(json).map_err(|_| InvalidOptionError)?;
+        Ok(())
+    }
+    pub fn get_value(&self, name: &str) -> Result<i32, InvalidOptionError> {
+        let json = serde_json::to_value(self).map_err(|_| InvalidOptionError)?;
+        json.pointer(&name_to_pointer(name)).and_then(Value::as_i64).map(|value| value as i32).ok_or(InvalidOptionError)
+    }
+    pub fn set_value(&mut self, name: &str, value: i32) -> Result<(), InvalidOptionError> {
+        let mut json = serde_json::to_value(&*self).map_err(|_| InvalidOptionError)?;
+        match json.pointer_mut(&name_to_pointer(name)) {
+            Some(slot) if slot.is_number() => *slot = Value::from(value),
+            _ => return Err(InvalidOptionError)
+        }
+        *self = serde_json::from_value(json).map_err(|_| InvalidOptionError)?;
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl EvalParams {
+    #[new]
+    pub fn new() -> EvalParams {
+        EvalParams {
+            pawn_value: Score::new(100, 120),
+            knight_value: Score::new(320, 300),
+            bishop_value: Score::new(330, 320),
+            rook_value: Score::new(500, 530),
+            queen_value: Score::new(900, 950),
+            knight_phase: 1,
+            bishop_phase: 1,
+            rook_phase: 2,
+            queen_phase: 4,
+            pawn_table_middle_game: PAWN_SCORE_MIDDLE_GAME,
+            pawn_table_end_game: PAWN_SCORE_END_GAME,
+            knight_table_middle_game: KNIGHT_SCORE_MIDDLE_GAME,
+            knight_table_end_game: KNIGHT_SCORE_END_GAME,
+            bishop_table_middle_game: BISHOP_SCORE_MIDDLE_GAME,
+            bishop_table_end_game: BISHOP_SCORE_END_GAME,
+            rook_table_middle_game: ROOK_SCORE_MIDDLE_GAME,
+            rook_table_end_game: ROOK_SCORE_END_GAME,
+            queen_table_middle_game: QUEEN_SCORE_MIDDLE_GAME,
+            queen_table_end_game: QUEEN_SCORE_END_GAME,
+            king_table_middle_game: KING_SCORE_MIDDLE_GAME,
+            king_table_end_game: KING_SCORE_END_GAME,
+            doubled_pawn: Score::new(-10, -20),
+            isolated_pawn: Score::new(-10, -15),
+            backward_pawn: Score::new(-8, -12),
+            passed_pawn_middle_game: [0, 0, 5, 10, 20, 35, 60, 0],
+            passed_pawn_end_game: [0, 10, 15, 25, 45, 75, 120, 0],
+            connected_pawn_middle_game: [0, 0, 5, 8, 12, 20, 35, 0],
+            connected_pawn_end_game: [0, 0, 3, 6, 10, 20, 40, 0],
+            knight_mobility: Score::new(4, 4),
+            bishop_mobility: Score::new(5, 5),
+            rook_mobility: Score::new(2, 4),
+            queen_mobility: Score::new(1, 2),
+            knight_average_mobility: 4,
+            bishop_average_mobility: 6,
+            rook_average_mobility: 7,
+            queen_average_mobility: 13,
+            knight_king_attack: 20,
+            bishop_king_attack: 20,
+            rook_king_attack: 40,
+            queen_king_attack: 80,
+            king_attackers_scale: [0, 0, 50, 75, 88, 94, 97, 99],
+            pawn_shield_close: Score::new(12, 0),
+            pawn_shield_far: Score::new(6, 0),
+            king_semi_open_file: Score::new(-15, 0),
+            king_open_file: Score::new(-25, 0),
+            bishop_pair: Score::new(30, 50),
+            rook_open_file: Score::new(25, 10),
+            rook_semi_open_file: Score::new(12, 6),
+            rook_on_seventh: Score::new(20, 30),
+            knight_outpost: Score::new(25, 15)
+        }
+    }
+    #[staticmethod]
+    pub fn load(filepath: &str) -> PyResult<EvalParams> {
+        EvalParams::load_file(filepath).map_err(|err| PyIOError::new_err(err.to_string()))
+    }
+    pub fn save(&self, filepath: &str) -> PyResult<()> {
+        self.save_file(filepath).map_err(|err| PyIOError::new_err(err.to_string()))
+    }
+    #[staticmethod]
+    pub fn from_json(json: &str) -> PyResult<EvalParams> {
+        serde_json::from_str(json).map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("parameters can always be written as json")
+    }
+    pub fn get_names(&self) -> Vec<String> {
+        self.names()
+    }
+    pub fn get(&self, name: &str) -> PyResult<i32> {
+        self.get_value(name).map_err(|_| PyValueError::new_err(format!("unknown parameter {}", name)))
+    }
+    pub fn set(&mut self, name: &str, value: i32) -> PyResult<()> {
+        self.set_value(name, value).map_err(|_| PyValueError::new_err(format!("unknown parameter {}", name)))
+    }
+}
+
+const PAWN_SCORE_MIDDLE_GAME: [i32; 64] = [
+0,  0,  0,  0,  0,  0,  0,  0,
+50, 50, 50, 50, 50, 50, 50, 50,
+10, 10, 20, 30, 30, 20, 10, 10,
+ 5,  5, 10, 25, 25, 10,  5,  5,
+ 0,  0,  0, 20, 20,  0,  0,  0,
+ 5, -5,-10,  0,  0,-10, -5,  5,
+ 5, 10, 10,-20,-20, 10, 10,  5,
+ 0,  0,  0,  0,  0,  0,  0,  0];
+
+const PAWN_SCORE_END_GAME: [i32; 64] = [
+ 0,  0,  0,  0,  0,  0,  0,  0,
+80, 80, 80, 80, 80, 80, 80, 80,
+50, 50, 50, 50, 50, 50, 50, 50,
+30, 30, 30, 30, 30, 30, 30, 30,
+15, 15, 15, 15, 15, 15, 15, 15,
+ 5,  5,  5,  5,  5,  5,  5,  5,
+ 0,  0,  0,  0,  0,  0,  0,  0,
+ 0,  0,  0,  0,  0,  0,  0,  0];
+
+const KNIGHT_SCORE_MIDDLE_GAME: [i32; 64] = [
+-50,-40,-30,-30,-30,-30,-40,-50,
+-40,-20,  0,  0,  0,  0,-20,-40,
+-30,  0, 10, 15, 15, 10,  0,-30,
+-30,  5, 15, 20, 20, 15,  5,-30,
+-30,  0, 15, 20, 20, 15,  0,-30,
+-30,  5, 10, 15, 15, 10,  5,-30,
+-40,-20,  0,  5,  5,  0,-20,-40,
+-50,-40,-30,-30,-30,-30,-40,-50];
+
+const KNIGHT_SCORE_END_GAME: [i32; 64] = [
+-40,-30,-20,-20,-20,-20,-30,-40,
+-30,-15, -5,  0,  0, -5,-15,-30,
+-20, -5, 10, 15, 15, 10, -5,-20,
+-20,  0, 15, 20, 20, 15,  0,-20,
+-20,  0, 15, 20, 20, 15,  0,-20,
+-20, -5, 10, 15, 15, 10, -5,-20,
+-30,-15, -5,  0,  0, -5,-15,-30,
+-40,-30,-20,-20,-20,-20,-30,-40];
+
+const BISHOP_SCORE_MIDDLE_GAME: [i32; 64] = [
+-20,-10,-10,-10,-10,-10,-10,-20,
+-10,  0,  0,  0,  0,  0,  0,-10,
+-10,  0,  5, 10, 10,  5,  0,-10,
+-10,  5,  5, 10, 10,  5,  5,-10,
+-10,  0, 10, 10, 10, 10,  0,-10,
+-10, 10, 10, 10, 10, 10, 10,-10,
+-10,  5,  0,  0,  0,  0,  5,-10,
+-20,-10,-10,-10,-10,-10,-10,-20];
+
+const BISHOP_SCORE_END_GAME: [i32; 64] = [
+-15,-10,-10,-10,-10,-10,-10,-15,
+-10, -5,  0,  0,  0,  0, -5,-10,
+-10,  0,  5,  5,  5,  5,  0,-10,
+-10,  0,  5, 10, 10,  5,  0,-10,
+-10,  0,  5, 10, 10,  5,  0,-10,
+-10,  0,  5,  5,  5,  5,  0,-10,
+-10, -5,  0,  0,  0,  0, -5,-10,
+-15,-10,-10,-10,-10,-10,-10,-15];
+
+const ROOK_SCORE_MIDDLE_GAME: [i32; 64] = [
+0,  0,  0,  0,  0,  0,  0,  0,
+5, 10, 10, 10, 10, 10, 10,  5,
+-5,  0,  0,  0,  0,  0,  0, -5,
+-5,  0,  0,  0,  0,  0,  0, -5,
+-5,  0,  0,  0,  0,  0,  0, -5,
+-5,  0,  0,  0,  0,  0,  0, -5,
+-5,  0,  0,  0,  0,  0,  0, -5,
+0,  0,  0,  5,  5,  0,  0,  0];
+
+const ROOK_SCORE_END_GAME: [i32; 64] = [
+ 5,  5,  5,  5,  5,  5,  5,  5,
+10, 10, 10, 10, 10, 10, 10, 10,
+ 0,  0,  0,  0,  0,  0,  0,  0,
+ 0,  0,  0,  0,  0,  0,  0,  0,
+ 0,  0,  0,  0,  0,  0,  0,  0,
+ 0,  0,  0,  0,  0,  0,  0,  0,
+ 0,  0,  0,  0,  0,  0,  0,  0,
+ 0,  0,  0,  0,  0,  0,  0,  0];
+
+const QUEEN_SCORE_MIDDLE_GAME: [i32; 64] = [
+-20,-10,-10, -5, -5,-10,-10,-20,
+-10,  0,  0,  0,  0,  0,  0,-10,
+-10,  0,  5,  5,  5,  5,  0,-10,
+ -5,  0,  5,  5,  5,  5,  0, -5,
+  0,  0,  5,  5,  5,  5,  0, -5,
+-10,  5,  5,  5,  5,  5,  0,-10,
+-10,  0,  5,  0,  0,  0,  0,-10,
+-20,-10,-10, -5, -5,-10,-10,-20];
+
+const QUEEN_SCORE_END_GAME: [i32; 64] = [
+-20,-10,-10, -5, -5,-10,-10,-20,
+-10,  0,  5,  5,  5,  5,  0,-10,
+-10,  5, 10, 10, 10, 10,  5,-10,
+ -5,  5, 10, 15, 15, 10,  5, -5,
+ -5,  5, 10, 15, 15, 10,  5, -5,
+-10,  5, 10, 10, 10, 10,  5,-10,
+-10,  0,  5,  5,  5,  5,  0,-10,
+-20,-10,-10, -5, -5,-10,-10,-20];
+
+const KING_SCORE_MIDDLE_GAME: [i32; 64] = [
+-30,-40,-40,-50,-50,-40,-40,-30,
+-30,-40,-40,-50,-50,-40,-40,-30,
+-30,-40,-40,-50,-50,-40,-40,-30,
+-30,-40,-40,-50,-50,-40,-40,-30,
+-20,-30,-30,-40,-40,-30,-30,-20,
+-10,-20,-20,-20,-20,-20,-20,-10,
+ 20, 20,  0,  0,  0,  0, 20, 20,
+ 20, 30, 10,  0,  0, 10, 30, 20];
+
+const KING_SCORE_END_GAME: [i32; 64] = [
+-50,-40,-30,-20,-20,-30,-40,-50,
+-30,-20,-10,  0,  0,-10,-20,-30,
+-30,-10, 20, 30, 30, 20,-10,-30,
+-30,-10, 30, 40, 40, 30,-10,-30,
+-30,-10, 30, 40, 40, 30,-10,-30,
+-30,-10, 20, 30, 30, 20,-10,-30,
+-30,-30,  0,  0,  0,  0,-30,-30,
+-50,-30,-30,-30,-30,-30,-30,-50];
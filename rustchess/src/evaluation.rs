@@ -0,0 +1,594 @@
+use pyo3::prelude::*;
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+use crate::chessboard_helper::*;
+use crate::lookuptables::LoadMoves;
+use crate::zobrist::hash_pawns;
+use crate::bitbase::bitbase_score;
+use crate::eval_params::EvalParams;
+use serde::{Serialize, Deserialize};
+
+// The static evaluation. Every term has a middlegame and an endgame value, the final score blends the two
+// according to how much material is left on the board (a tapered evaluation). All scores are from the
+// perspective of white and all tables are written from the perspective of white with a8 at index 0.
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32
+}
+
+impl Score {
+    pub const fn new(mg: i32, eg: i32) -> Score {
+        Score { mg, eg }
+    }
+    pub fn taper(&self, phase: i32, total_phase: i32) -> i32 {
+        // phase is total_phase at the start of the game and 0 when only kings and pawns are left
+        (self.mg * phase + self.eg * (total_phase - phase)) / total_phase.max(1)
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+    fn add(self, other: Score) -> Score {
+        Score::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+    fn sub(self, other: Score) -> Score {
+        Score::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Score) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, other: Score) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+    fn neg(self) -> Score {
+        Score::new(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Score;
+    fn mul(self, factor: i32) -> Score {
+        Score::new(self.mg * factor, self.eg * factor)
+    }
+}
+
+pub const FILE_A: u64 = 0x0101_0101_0101_0101;
+pub const FILE_H: u64 = FILE_A << 7;
+
+const fn generate_adjacent_files() -> [u64; 8] {
+    let mut masks = [0; 8];
+    let mut file = 0;
+    while file < 8 {
+        if file > 0 {
+            masks[file] |= FILE_A << (file - 1);
+        }
+        if file < 7 {
+            masks[file] |= FILE_A << (file + 1);
+        }
+        file += 1;
+    }
+    masks
+}
+
+const fn generate_span(white: bool, include_adjacent: bool, include_own_file: bool, include_own_row: bool) -> [u64; 64] {
+    // for every square the squares on the given files that lie in front of it seen from white or black,
+    // when include_own_row is set we look behind instead and include the row of the square itself
+    let mut masks = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let file = square % 8;
+        let row = square / 8;
+        let mut mask = 0;
+        let mut r = 0;
+        while r < 8 {
+            let selected = if include_own_row {
+                if white {r >= row} else {r <= row}
+            }
+            else if white {r < row} else {r > row};
+            if selected {
+                let mut f = 0;
+                while f < 8 {
+                    let is_adjacent = f + 1 == file || f == file + 1;
+                    if (is_adjacent && include_adjacent) || (f == file && include_own_file) {
+                        mask |= 1 << (r * 8 + f);
+                    }
+                    f += 1;
+                }
+            }
+            r += 1;
+        }
+        masks[square] = mask;
+        square += 1;
+    }
+    masks
+}
+
+const ADJACENT_FILES: [u64; 8] = generate_adjacent_files();
+// squares an enemy pawn must not be on for a pawn to be passed
+const WHITE_PASSED_SPAN: [u64; 64] = generate_span(true, true, true, false);
+const BLACK_PASSED_SPAN: [u64; 64] = generate_span(false, true, true, false);
+// squares in front of a pawn on its own file
+const WHITE_FRONT_SPAN: [u64; 64] = generate_span(true, false, true, false);
+const BLACK_FRONT_SPAN: [u64; 64] = generate_span(false, false, true, false);
+// squares on the adjacent files next to and behind a pawn, a pawn without own pawns here can never be defended
+const WHITE_SUPPORT_SPAN: [u64; 64] = generate_span(true, true, false, true);
+const BLACK_SUPPORT_SPAN: [u64; 64] = generate_span(false, true, false, true);
+
+pub fn pawn_attacks(pawns: u64, white: bool) -> u64 {
+    // all squares attacked by the pawns, white pawns move towards index 0
+    if white {
+        ((pawns & !FILE_A) >> 9) | ((pawns & !FILE_H) >> 7)
+    }
+    else {
+        ((pawns & !FILE_A) << 7) | ((pawns & !FILE_H) << 9)
+    }
+}
+
+#[inline]
+pub fn relative_square(square: usize, white: bool) -> usize {
+    // the tables are written for white, black looks them up on the mirrored square
+    if white {square} else {square ^ 56}
+}
+
+#[inline]
+pub fn relative_rank(square: usize, white: bool) -> usize {
+    // 0 for the first rank up to 7 for the eighth rank seen from the given side
+    if white {7 - square / 8} else {square / 8}
+}
+
+pub struct SquareIter(pub u64);
+
+impl Iterator for SquareIter {
+    // iterates over the indices of all set bits of a bitboard
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None
+        }
+        let square = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+}
+
+fn table_score(bb: u64, white: bool, middle_game: &[i32; 64], end_game: &[i32; 64]) -> Score {
+    let mut score = Score::default();
+    for square in SquareIter(bb) {
+        let square = relative_square(square, white);
+        score += Score::new(middle_game[square], end_game[square]);
+    }
+    score
+}
+
+fn material(pieces: &Pieces, params: &EvalParams) -> Score {
+    let mut score = Score::default();
+    score += params.pawn_value * pieces.get_bb_pawns().count_ones() as i32;
+    score += params.knight_value * pieces.get_bb_knights().count_ones() as i32;
+    score += params.bishop_value * pieces.get_bb_bishops().count_ones() as i32;
+    score += params.rook_value * pieces.get_bb_rooks().count_ones() as i32;
+    score += params.queen_value * pieces.get_bb_queens().count_ones() as i32;
+    score
+}
+
+const PIECE_TABLE_NAMES: [&str; 6] = ["pawn table", "knight table", "bishop table", "rook table", "queen table", "king table"];
+
+fn piece_tables(pieces: &Pieces, white: bool, params: &EvalParams) -> [Score; 6] {
+    // one score per piece type, in the order of PIECE_TABLE_NAMES
+    [table_score(pieces.get_bb_pawns(), white, &params.pawn_table_middle_game, &params.pawn_table_end_game),
+        table_score(pieces.get_bb_knights(), white, &params.knight_table_middle_game, &params.knight_table_end_game),
+        table_score(pieces.get_bb_bishops(), white, &params.bishop_table_middle_game, &params.bishop_table_end_game),
+        table_score(pieces.get_bb_rooks(), white, &params.rook_table_middle_game, &params.rook_table_end_game),
+        table_score(pieces.get_bb_queens(), white, &params.queen_table_middle_game, &params.queen_table_end_game),
+        table_score(pieces.get_bb_king(), white, &params.king_table_middle_game, &params.king_table_end_game)]
+}
+
+pub fn game_phase(position: &Position, params: &EvalParams) -> i32 {
+    let mut phase = 0;
+    for pieces in [&position.white_pieces, &position.black_pieces] {
+        phase += params.knight_phase * pieces.get_bb_knights().count_ones() as i32;
+        phase += params.bishop_phase * pieces.get_bb_bishops().count_ones() as i32;
+        phase += params.rook_phase * pieces.get_bb_rooks().count_ones() as i32;
+        phase += params.queen_phase * pieces.get_bb_queens().count_ones() as i32;
+    }
+    // promotions can push the phase above the phase of the start position
+    phase.min(params.total_phase())
+}
+
+fn pawn_structure_side(own: u64, enemy: u64, white: bool, params: &EvalParams) -> Score {
+    let mut score = Score::default();
+    let (passed_span, front_span, support_span) = if white {
+        (&WHITE_PASSED_SPAN, &WHITE_FRONT_SPAN, &WHITE_SUPPORT_SPAN)
+    }
+    else {
+        (&BLACK_PASSED_SPAN, &BLACK_FRONT_SPAN, &BLACK_SUPPORT_SPAN)
+    };
+    let own_attacks = pawn_attacks(own, white);
+    let enemy_attacks = pawn_attacks(enemy, !white);
+    // pawns that stand next to an own pawn on the same rank
+    let phalanx = ((own & !FILE_H) << 1) | ((own & !FILE_A) >> 1);
+
+    for file in 0..8 {
+        let pawns_on_file = (own & (FILE_A << file)).count_ones() as i32;
+        if pawns_on_file > 1 {
+            score += params.doubled_pawn * (pawns_on_file - 1);
+        }
+    }
+    for square in SquareIter(own) {
+        let file = square % 8;
+        let rank = relative_rank(square, white);
+        let square_bb = 1u64 << square;
+        if own & ADJACENT_FILES[file] == 0 {
+            score += params.isolated_pawn;
+        }
+        else if own & support_span[square] == 0 {
+            // a pawn is backward when it can never be defended by another pawn and cannot safely advance,
+            // a pawn on the last rank of a broken position has no square in front of it
+            let stop_square = if white {square.checked_sub(8)} else {Some(square + 8).filter(|square| *square < 64)};
+            if stop_square.is_some_and(|stop_square| enemy_attacks & (1u64 << stop_square) != 0) {
+                score += params.backward_pawn;
+            }
+        }
+        if enemy & passed_span[square] == 0 && own & front_span[square] == 0 {
+            score += Score::new(params.passed_pawn_middle_game[rank], params.passed_pawn_end_game[rank]);
+        }
+        if (own_attacks | phalanx) & square_bb != 0 {
+            score += Score::new(params.connected_pawn_middle_game[rank], params.connected_pawn_end_game[rank]);
+        }
+    }
+    score
+}
+
+pub fn pawn_structure(position: &Position, params: &EvalParams) -> Score {
+    // all pawn structure terms, they only depend on the pawns so they can be stored in the pawn hash table
+    let white_pawns = position.white_pieces.get_bb_pawns();
+    let black_pawns = position.black_pieces.get_bb_pawns();
+    pawn_structure_side(white_pawns, black_pawns, true, params) - pawn_structure_side(black_pawns, white_pawns, false, params)
+}
+
+#[derive(Clone, Copy, Default)]
+struct PawnEntry {
+    key: u64,
+    score: Score
+}
+
+pub struct PawnHashTable {
+    // Pawns move rarely, so most positions of a search share their pawn structure with many others. Every
+    // search thread has its own table, the table is small enough to stay in the cache of the processor.
+    entries: Vec<PawnEntry>
+}
+
+impl PawnHashTable {
+    pub fn new() -> PawnHashTable {
+        // the empty entry has key 0, which is exactly the hash and the score of a position without pawns
+        PawnHashTable { entries: vec![PawnEntry::default(); 1 << 14] }
+    }
+    pub fn probe(&mut self, position: &Position, params: &EvalParams) -> Score {
+        // the table must be cleared when the parameters change
+        let key = hash_pawns(position);
+        let index = (key as usize) & (self.entries.len() - 1);
+        let entry = &mut self.entries[index];
+        if entry.key != key {
+            *entry = PawnEntry { key, score: pawn_structure(position, params) };
+        }
+        entry.score
+    }
+}
+
+fn all_pieces(pieces: &Pieces) -> u64 {
+    pieces.get_bb_pawns() | pieces.get_bb_knights() | pieces.get_bb_bishops() | pieces.get_bb_rooks()
+        | pieces.get_bb_queens() | pieces.get_bb_king()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ActivityScore {
+    // the terms that need the attacks of the pieces, computed in one pass over the pieces of a side
+    pub mobility: Score,
+    pub king_attack: Score,
+    pub pieces: Score
+}
+
+pub fn activity(position: &Position, moves: &LoadMoves, white: bool, params: &EvalParams) -> ActivityScore {
+    // mobility, attacks on the zone around the enemy king, the bishop pair, rook files and knight outposts of one side
+    let (own, enemy) = if white {
+        (&position.white_pieces, &position.black_pieces)
+    }
+    else {
+        (&position.black_pieces, &position.white_pieces)
+    };
+    let occupied = all_pieces(own) | all_pieces(enemy);
+    let own_pawns = own.get_bb_pawns();
+    let enemy_pawns = enemy.get_bb_pawns();
+    let own_pawn_attacks = pawn_attacks(own_pawns, white);
+    let mobility_area = !all_pieces(own) & !pawn_attacks(enemy_pawns, !white);
+    let enemy_king = enemy.get_bb_king().trailing_zeros() as usize;
+    let king_zone = if enemy_king < 64 {moves.king(enemy_king) | 1 << enemy_king} else {0};
+
+    let mut score = ActivityScore::default();
+    let mut attackers = 0;
+    let mut attack_weight = 0;
+    let mut add_piece = |attacks: u64, mobility: Score, average: i32, king_attack: i32| {
+        score.mobility += mobility * ((attacks & mobility_area).count_ones() as i32 - average);
+        if attacks & king_zone != 0 {
+            attackers += 1;
+            attack_weight += king_attack;
+        }
+    };
+    for square in SquareIter(own.get_bb_knights()) {
+        add_piece(moves.knight(square), params.knight_mobility, params.knight_average_mobility, params.knight_king_attack);
+    }
+    for square in SquareIter(own.get_bb_bishops()) {
+        let attacks = moves.bishop(square, occupied).copied().unwrap_or(0);
+        add_piece(attacks, params.bishop_mobility, params.bishop_average_mobility, params.bishop_king_attack);
+    }
+    for square in SquareIter(own.get_bb_rooks()) {
+        let attacks = moves.rook(square, occupied).copied().unwrap_or(0);
+        add_piece(attacks, params.rook_mobility, params.rook_average_mobility, params.rook_king_attack);
+    }
+    for square in SquareIter(own.get_bb_queens()) {
+        let attacks = moves.queen(square, occupied).unwrap_or(0);
+        add_piece(attacks, params.queen_mobility, params.queen_average_mobility, params.queen_king_attack);
+    }
+    let attack = attack_weight * params.king_attackers_scale[attackers.min(7)] / 100;
+    score.king_attack = Score::new(attack, 0);
+
+    if own.get_bb_bishops().count_ones() >= 2 {
+        score.pieces += params.bishop_pair;
+    }
+    for square in SquareIter(own.get_bb_rooks()) {
+        let file = FILE_A << (square % 8);
+        if (own_pawns | enemy_pawns) & file == 0 {
+            score.pieces += params.rook_open_file;
+        }
+        else if own_pawns & file == 0 {
+            score.pieces += params.rook_semi_open_file;
+        }
+        // a rook on the seventh rank is strong when it attacks pawns there or cuts off the enemy king
+        if relative_rank(square, white) == 6 {
+            let seventh = 0xFF << (if white {8} else {48});
+            let eighth = 0xFF << (if white {0} else {56});
+            if enemy_pawns & seventh != 0 || enemy.get_bb_king() & eighth != 0 {
+                score.pieces += params.rook_on_seventh;
+            }
+        }
+    }
+    // an outpost is a square in the enemy half that is defended by an own pawn and that no enemy pawn can attack
+    let passed_span = if white {&WHITE_PASSED_SPAN} else {&BLACK_PASSED_SPAN};
+    for square in SquareIter(own.get_bb_knights()) {
+        let rank = relative_rank(square, white);
+        if (3..=5).contains(&rank) && own_pawn_attacks & 1 << square != 0
+            && enemy_pawns & passed_span[square] & ADJACENT_FILES[square % 8] == 0 {
+            score.pieces += params.knight_outpost;
+        }
+    }
+    score
+}
+
+pub fn king_shelter(position: &Position, white: bool, params: &EvalParams) -> Score {
+    // the pawns in front of the king and the open files next to it, only matters in the middlegame
+    let (own, enemy) = if white {
+        (&position.white_pieces, &position.black_pieces)
+    }
+    else {
+        (&position.black_pieces, &position.white_pieces)
+    };
+    let king = own.get_bb_king().trailing_zeros() as usize;
+    if king >= 64 {
+        return Score::default()
+    }
+    let own_pawns = own.get_bb_pawns();
+    let all_pawns = own_pawns | enemy.get_bb_pawns();
+    let king_file = king % 8;
+    let king_row = king / 8;
+    let mut score = Score::default();
+    for file in king_file.saturating_sub(1)..=(king_file + 1).min(7) {
+        let file_mask = FILE_A << file;
+        if all_pawns & file_mask == 0 {
+            score += params.king_open_file;
+        }
+        else if own_pawns & file_mask == 0 {
+            score += params.king_semi_open_file;
+        }
+        // a shield is only of use for a king that stays on its first two ranks
+        if relative_rank(king, white) <= 1 {
+            let row_ahead = |distance: usize| -> Option<usize> {
+                if white {king_row.checked_sub(distance)} else {Some(king_row + distance).filter(|row| *row < 8)}
+            };
+            let on_row = |row: Option<usize>| row.is_some_and(|row| own_pawns & file_mask & 0xFF << (row * 8) != 0);
+            if on_row(row_ahead(1)) {
+                score += params.pawn_shield_close;
+            }
+            else if on_row(row_ahead(2)) {
+                score += params.pawn_shield_far;
+            }
+        }
+    }
+    score
+}
+
+pub fn evaluate(position: &Position, moves: &LoadMoves, params: &EvalParams, pawn_table: &mut PawnHashTable, bitbases: bool) -> i32 {
+    // evaluate the position it is given, positive evaluation means good for white
+    // while negative evaluation means good for black, with bitbases the endgames that are in them are scored exactly
+    evaluate_terms(position, moves, params, Some(pawn_table), bitbases, None)
+}
+
+fn evaluate_terms(position: &Position, moves: &LoadMoves, params: &EvalParams, pawn_table: Option<&mut PawnHashTable>,
+    bitbases: bool, mut trace: Option<&mut Vec<EvalTerm>>) -> i32 {
+    // Every term of the evaluation is added here, with a trace every term is also kept apart. The pawn hash table
+    // only stores the difference between the sides, so the pawn structure is computed per side when there is none.
+    // A position of the bitbases is only scored by them.
+    let phase = game_phase(position, params);
+    let total_phase = params.total_phase();
+    if let Some(score) = bitbase_score(position).filter(|_| bitbases) {
+        if let Some(terms) = trace {
+            terms.push(EvalTerm { name: "bitbase".to_string(), white: Score::new(score, score), black: Score::default(), score });
+        }
+        return score
+    }
+    let mut score = Score::default();
+    let mut add_term = |name: &str, white: Score, black: Score| {
+        score += white - black;
+        if let Some(terms) = trace.as_mut() {
+            terms.push(EvalTerm { name: name.to_string(), white, black, score: (white - black).taper(phase, total_phase) });
+        }
+    };
+    let (white, black) = (&position.white_pieces, &position.black_pieces);
+    add_term("material", material(white, params), material(black, params));
+    let white_tables = piece_tables(white, true, params);
+    let black_tables = piece_tables(black, false, params);
+    for (i, name) in PIECE_TABLE_NAMES.iter().enumerate() {
+        add_term(name, white_tables[i], black_tables[i]);
+    }
+    match pawn_table {
+        Some(pawn_table) => add_term("pawn structure", pawn_table.probe(position, params), Score::default()),
+        None => {
+            let (white_pawns, black_pawns) = (white.get_bb_pawns(), black.get_bb_pawns());
+            add_term("pawn structure", pawn_structure_side(white_pawns, black_pawns, true, params),
+                pawn_structure_side(black_pawns, white_pawns, false, params));
+        }
+    }
+    let white_activity = activity(position, moves, true, params);
+    let black_activity = activity(position, moves, false, params);
+    add_term("mobility", white_activity.mobility, black_activity.mobility);
+    add_term("king attack", white_activity.king_attack, black_activity.king_attack);
+    add_term("king shelter", king_shelter(position, true, params), king_shelter(position, false, params));
+    add_term("pieces", white_activity.pieces, black_activity.pieces);
+    score.taper(phase, total_phase)
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct EvalTerm {
+    // one term of the evaluation, split into the middlegame and endgame values of both sides
+    pub name: String,
+    pub white: Score,
+    pub black: Score,
+    // the tapered difference between white and black, what the term adds to the evaluation
+    pub score: i32
+}
+
+#[pymethods]
+impl EvalTerm {
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+    pub fn get_white(&self) -> (i32, i32) {
+        (self.white.mg, self.white.eg)
+    }
+    pub fn get_black(&self) -> (i32, i32) {
+        (self.black.mg, self.black.eg)
+    }
+    pub fn get_score(&self) -> i32 {
+        self.score
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct EvalTrace {
+    // The evaluation taken apart into its terms. The score is the same as the one evaluate returns, it can
+    // differ slightly from the sum of the tapered terms because every taper rounds on its own.
+    pub terms: Vec<EvalTerm>,
+    pub phase: i32,
+    pub total_phase: i32,
+    pub score: i32
+}
+
+#[pymethods]
+impl EvalTrace {
+    pub fn get_terms(&self) -> Vec<EvalTerm> {
+        self.terms.clone()
+    }
+    pub fn get_phase(&self) -> i32 {
+        self.phase
+    }
+    pub fn get_total_phase(&self) -> i32 {
+        self.total_phase
+    }
+    pub fn get_score(&self) -> i32 {
+        self.score
+    }
+    pub fn __str__(&self) -> String {
+        format!("{}", self)
+    }
+}
+
+impl fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<16}{:>14}{:>14}{:>8}", "term", "white mg/eg", "black mg/eg", "score")?;
+        for term in self.terms.iter() {
+            writeln!(f, "{:<16}{:>14}{:>14}{:>8}", term.name, format!("{} {}", term.white.mg, term.white.eg),
+                format!("{} {}", term.black.mg, term.black.eg), term.score)?;
+        }
+        write!(f, "phase {}/{}, score {}", self.phase, self.total_phase, self.score)
+    }
+}
+
+pub fn evaluate_trace(position: &Position, moves: &LoadMoves, params: &EvalParams, bitbases: bool) -> EvalTrace {
+    // the same evaluation as evaluate, but every term is kept apart to show where the score comes from
+    let mut terms = Vec::new();
+    let score = evaluate_terms(position, moves, params, None, bitbases, Some(&mut terms));
+    EvalTrace { terms, phase: game_phase(position, params), total_phase: params.total_phase(), score }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::*;
+
+    #[test]
+    fn trace_has_the_score_of_evaluate() {
+        let params = EvalParams::default();
+        let mut pawn_table = PawnHashTable::new();
+        for fen in [START_FEN, KIWIPETE_FEN, "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+                "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10"] {
+            let chessboard = chessboard(fen);
+            let (position, moves) = (chessboard.get_position(), chessboard.get_pseudo_moves());
+            let trace = evaluate_trace(position, moves, &params, false);
+            assert_eq!(trace.score, evaluate(position, moves, &params, &mut pawn_table, false));
+            // every taper rounds on its own, so the terms add up to the score within one centipawn per term
+            let sum: i32 = trace.terms.iter().map(|term| term.score).sum();
+            assert!((sum - trace.score).abs() <= trace.terms.len() as i32, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn pawns_on_the_last_rank_do_not_overflow() {
+        // such positions never come from a game, but the tuner reads them from files
+        let params = EvalParams::default();
+        for fen in ["P3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "4k3/8/8/8/8/8/6p1/4K2p b - - 0 1"] {
+            let chessboard = chessboard(fen);
+            let position = chessboard.get_position();
+            pawn_structure(position, &params);
+            evaluate(position, chessboard.get_pseudo_moves(), &params, &mut PawnHashTable::new(), false);
+        }
+    }
+
+    #[test]
+    fn evaluation_is_symmetric() {
+        // the start position is equal and the mirrored kiwipete scores the same for the other side
+        let params = EvalParams::default();
+        let mut pawn_table = PawnHashTable::new();
+        let start = chessboard(START_FEN);
+        assert_eq!(evaluate(start.get_position(), start.get_pseudo_moves(), &params, &mut pawn_table, false), 0);
+        let kiwipete = chessboard(KIWIPETE_FEN);
+        let mirrored = chessboard("r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1");
+        assert_eq!(evaluate(kiwipete.get_position(), kiwipete.get_pseudo_moves(), &params, &mut pawn_table, false),
+            -evaluate(mirrored.get_position(), mirrored.get_pseudo_moves(), &params, &mut pawn_table, false));
+    }
+}
@@ -4,6 +4,22 @@ mod chessboard;
 mod lookuptables;
 mod chess_computer;
 mod chessboard_coordinator;
+mod search_helper;
+mod zobrist;
+mod transposition_table;
+mod evaluation;
+mod eval_params;
+mod tuner;
+mod nnue;
+mod datagen;
+mod polyglot;
+mod syzygy;
+mod bitbase;
+mod mcts;
+mod computer_registry;
+mod mate_solver;
+#[cfg(test)]
+mod test_helper;
 use pyo3::prelude::*;
 
 
@@ -17,9 +33,32 @@ fn create_lookup(search_number: usize) -> PyResult<()> {
 #[pymodule]
 fn RustEngine(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(create_lookup, m)?)?;
+    m.add_function(wrap_pyfunction!(tuner::tune_evaluation, m)?)?;
+    m.add_function(wrap_pyfunction!(datagen::generate_data, m)?)?;
+    m.add_function(wrap_pyfunction!(polyglot::build_book, m)?)?;
+    m.add_function(wrap_pyfunction!(computer_registry::list_computers, m)?)?;
     m.add_class::<chessboard::Chessboard>()?;
+    m.add_class::<chessboard::MoveClass>()?;
     m.add_class::<chess_computer::RandomComputer>()?;
     m.add_class::<chessboard_coordinator::Coordinator>()?;
+    m.add_class::<search_helper::SearchResult>()?;
+    m.add_class::<search_helper::PvLine>()?;
+    m.add_class::<eval_params::EvalParams>()?;
+    m.add_class::<tuner::TuningReport>()?;
+    m.add_class::<evaluation::EvalTrace>()?;
+    m.add_class::<evaluation::EvalTerm>()?;
+    m.add_class::<nnue::NnueNetwork>()?;
+    m.add_class::<datagen::DatagenOptions>()?;
+    m.add_class::<datagen::DatagenReport>()?;
+    m.add_class::<polyglot::OpeningBook>()?;
+    m.add_class::<syzygy::Tablebase>()?;
+    m.add_class::<bitbase::Bitbase>()?;
+    m.add_class::<computer_registry::ComputerInfo>()?;
+    m.add_class::<computer_registry::ComputerOption>()?;
+    m.add_class::<mate_solver::MateSolution>()?;
+    m.add_class::<mate_solver::MateNode>()?;
+    m.add_class::<search_helper::SearchLimits>()?;
+    m.add_class::<search_helper::StopHandle>()?;
     
     Ok(())
 }
\ No newline at end of file
@@ -440,6 +440,7 @@ impl CreateLookUpTables {
 
 
 
+#[derive(Clone)]
 pub struct LoadMoves {
     knight_masks: [u64; 64],
     white_pawn_masks: [u64; 64],
@@ -0,0 +1,288 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use crate::chessboard::*;
+use crate::chessboard_helper::*;
+
+// Proves or refutes a mate in n moves for the player to move. The search is an alpha-beta that only knows two
+// results: the attacker forces mate within the moves that are left or not. There is no evaluation and every
+// defence has to be refuted, so the answer is exact. In the last move of the attacker only checks can mate,
+// the other moves are skipped without being played out. The answers are kept by position and moves left.
+//
+// The solution tree starts with every key move, a move that forces mate within n moves. Below a key move are
+// all defences, and below every defence all moves that mate the quickest from there, so duals show up as
+// siblings. Draws by repetition and the fifty move rule are not looked at, the puzzles do not need them.
+
+// the longest mate we try to prove, the tree gets far too big long before that
+pub const MAX_MATE_MOVES: u8 = 32;
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct MateNode {
+    played: Move,
+    // the move checkmates, there is nothing below it
+    mate: bool,
+    // the defences after a move of the attacker, the mating moves after a defence
+    children: Vec<MateNode>
+}
+
+#[pymethods]
+impl MateNode {
+    pub fn get_move(&self) -> String {
+        self.played.to_string()
+    }
+    pub fn is_mate(&self) -> bool {
+        self.mate
+    }
+    pub fn get_children(&self) -> Vec<MateNode> {
+        self.children.clone()
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct MateSolution {
+    // the number of moves that were asked for
+    moves: u8,
+    // the quickest mate, None when there is no mate within moves
+    mate_in: Option<u8>,
+    key_moves: Vec<Move>,
+    tree: Vec<MateNode>,
+    nodes: u64
+}
+
+impl MateSolution {
+    pub fn key_moves(&self) -> &Vec<Move> {
+        &self.key_moves
+    }
+    pub fn tree(&self) -> &Vec<MateNode> {
+        &self.tree
+    }
+}
+
+#[pymethods]
+impl MateSolution {
+    pub fn is_mate(&self) -> bool {
+        self.mate_in.is_some()
+    }
+    pub fn get_moves(&self) -> u8 {
+        self.moves
+    }
+    pub fn get_mate_in(&self) -> Option<u8> {
+        self.mate_in
+    }
+    pub fn get_key_moves(&self) -> Vec<String> {
+        self.key_moves.iter().map(|key_move| key_move.to_string()).collect()
+    }
+    pub fn get_tree(&self) -> Vec<MateNode> {
+        self.tree.clone()
+    }
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes
+    }
+    pub fn __str__(&self) -> String {
+        format!("{}", self)
+    }
+}
+
+impl fmt::Display for MateSolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // one move per line, the answers are indented below the move they answer
+        match self.mate_in {
+            Some(mate_in) => writeln!(f, "mate in {}", mate_in)?,
+            None => write!(f, "no mate in {}", self.moves)?
+        }
+        write_tree(f, &self.tree, 0)
+    }
+}
+
+fn write_tree(f: &mut fmt::Formatter, nodes: &[MateNode], indent: usize) -> fmt::Result {
+    for node in nodes {
+        let mate = if node.mate { "#" } else { "" };
+        writeln!(f, "{}{}{}", "  ".repeat(indent), node.played.to_string(), mate)?;
+        write_tree(f, &node.children, indent + 1)?;
+    }
+    Ok(())
+}
+
+struct MateSolver {
+    // whether the attacker mates from a position within a number of moves
+    table: HashMap<(u64, u8), bool>,
+    nodes: u64
+}
+
+impl MateSolver {
+    fn new() -> MateSolver {
+        MateSolver { table: HashMap::new(), nodes: 0 }
+    }
+    fn attacker_moves(&mut self, chessboard: &mut Chessboard, moves_left: u8) -> Vec<Move> {
+        // the checks come first, they are the most forcing. With one move left they are the only moves
+        let mut checks = Vec::new();
+        let mut quiet = Vec::new();
+        for new_move in chessboard.all_moves() {
+            if chessboard.gives_check(&new_move) {
+                checks.push(new_move);
+            }
+            else if moves_left > 1 {
+                quiet.push(new_move);
+            }
+        }
+        checks.extend(quiet);
+        checks
+    }
+    fn attacker_mates(&mut self, chessboard: &mut Chessboard, moves_left: u8) -> bool {
+        let key = (chessboard.get_hash(), moves_left);
+        if let Some(mates) = self.table.get(&key) {
+            return *mates
+        }
+        self.nodes += 1;
+        let mut mates = false;
+        for new_move in self.attacker_moves(chessboard, moves_left) {
+            chessboard.move_piece(&new_move).unwrap();
+            mates = self.defender_lost(chessboard, moves_left);
+            chessboard.undo();
+            if mates {
+                break
+            }
+        }
+        self.table.insert(key, mates);
+        mates
+    }
+    fn defender_lost(&mut self, chessboard: &mut Chessboard, moves_left: u8) -> bool {
+        // the attacker just moved, moves_left still counts that move
+        self.nodes += 1;
+        let defences = chessboard.all_moves();
+        if defences.is_empty() {
+            // stalemate is no win
+            return chessboard.is_in_check()
+        }
+        if moves_left == 1 {
+            return false
+        }
+        for defence in defences {
+            chessboard.move_piece(&defence).unwrap();
+            let mates = self.attacker_mates(chessboard, moves_left - 1);
+            chessboard.undo();
+            if !mates {
+                return false
+            }
+        }
+        true
+    }
+    fn quickest_mate(&mut self, chessboard: &mut Chessboard, moves_left: u8) -> Option<u8> {
+        (1..=moves_left).find(|moves| self.attacker_mates(chessboard, *moves))
+    }
+    fn attacker_tree(&mut self, chessboard: &mut Chessboard, moves_left: u8) -> Vec<MateNode> {
+        // every move that mates within moves_left, with all the defences against it
+        let mut nodes = Vec::new();
+        for new_move in self.attacker_moves(chessboard, moves_left) {
+            chessboard.move_piece(&new_move).unwrap();
+            if self.defender_lost(chessboard, moves_left) {
+                let children = self.defender_tree(chessboard, moves_left);
+                nodes.push(MateNode { played: new_move, mate: children.is_empty(), children });
+            }
+            chessboard.undo();
+        }
+        nodes
+    }
+    fn defender_tree(&mut self, chessboard: &mut Chessboard, moves_left: u8) -> Vec<MateNode> {
+        let mut nodes = Vec::new();
+        for defence in chessboard.all_moves() {
+            chessboard.move_piece(&defence).unwrap();
+            // the attacker mates within moves_left - 1 after every defence, we show the quickest mates
+            let mate_in = self.quickest_mate(chessboard, moves_left - 1).unwrap_or(moves_left - 1);
+            let children = self.attacker_tree(chessboard, mate_in);
+            chessboard.undo();
+            nodes.push(MateNode { played: defence, mate: false, children });
+        }
+        nodes
+    }
+}
+
+pub fn solve_mate(chessboard: &mut Chessboard, moves: u8) -> MateSolution {
+    // moves is clamped to 1..=MAX_MATE_MOVES, the chessboard is in the same position afterwards
+    let moves = moves.clamp(1, MAX_MATE_MOVES);
+    let mut solver = MateSolver::new();
+    let mate_in = solver.quickest_mate(chessboard, moves);
+    let tree = match mate_in {
+        Some(_) => solver.attacker_tree(chessboard, moves),
+        None => Vec::new()
+    };
+    let key_moves = tree.iter().map(|node| node.played).collect();
+    MateSolution { moves, mate_in, key_moves, tree, nodes: solver.nodes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::*;
+
+    fn moves(notations: &[&str]) -> Vec<Move> {
+        notations.iter().map(|notation| Move::from_string(notation).unwrap()).collect()
+    }
+
+    fn solve(fen: &str, moves: u8) -> MateSolution {
+        let mut chessboard = chessboard(fen);
+        let hash = chessboard.get_hash();
+        let solution = solve_mate(&mut chessboard, moves);
+        assert_eq!(chessboard.get_hash(), hash, "{}", fen);
+        solution
+    }
+
+    #[test]
+    fn mate_in_one() {
+        let solution = solve("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1", 1);
+        assert_eq!(solution.get_mate_in(), Some(1));
+        assert_eq!(solution.key_moves(), &moves(&["a1a8"]));
+        assert!(solution.tree()[0].is_mate());
+        assert!(solution.tree()[0].children.is_empty());
+    }
+
+    #[test]
+    fn mate_in_two() {
+        // Rb7 and Kb6 stalemate, only Kc7 leaves the king a square and Ra1 mates on it
+        let fen = "k7/8/2K5/8/8/8/8/1R6 w - - 0 1";
+        let solution = solve(fen, 2);
+        assert_eq!(solution.get_mate_in(), Some(2));
+        assert_eq!(solution.key_moves(), &moves(&["c6c7"]));
+        let defences = &solution.tree()[0].children;
+        assert_eq!(defences.len(), 1);
+        assert_eq!(defences[0].get_move(), "a8a7");
+        assert_eq!(defences[0].children.len(), 1);
+        assert_eq!(defences[0].children[0].get_move(), "b1a1");
+        assert!(defences[0].children[0].is_mate());
+        // asking for more moves still finds the quickest mate
+        assert_eq!(solve(fen, 3).get_mate_in(), Some(2));
+    }
+
+    #[test]
+    fn duals_are_siblings() {
+        // both rooks mate on the back rank
+        let solution = solve("6k1/5ppp/8/8/8/8/5PPP/RR4K1 w - - 0 1", 1);
+        assert_eq!(solution.get_mate_in(), Some(1));
+        assert_eq!(solution.key_moves(), &moves(&["a1a8", "b1b8"]));
+        assert!(solution.tree().iter().all(|node| node.is_mate() && node.children.is_empty()));
+    }
+
+    #[test]
+    fn stalemate_is_no_mate() {
+        // the king and the pawn on h6 already take every square of the black king, so every quiet move
+        // stalemates, and the only check Ng6 is taken by the pawn
+        let solution = solve("7k/5K1p/7P/4N3/8/8/8/8 w - - 0 1", 2);
+        assert!(!solution.is_mate());
+        assert!(solution.key_moves().is_empty());
+        assert!(solution.tree().is_empty());
+        assert!(solution.get_nodes() > 0);
+    }
+
+    #[test]
+    fn mate_in_three_is_no_mate_in_two() {
+        let fen = "k7/8/8/2K5/8/8/8/1R6 w - - 0 1";
+        let solution = solve(fen, 2);
+        assert_eq!(solution.get_moves(), 2);
+        assert!(!solution.is_mate());
+        assert!(solution.tree().is_empty());
+        assert!(solution.to_string().starts_with("no mate in 2"));
+        assert_eq!(solve(fen, 3).get_mate_in(), Some(3));
+    }
+}
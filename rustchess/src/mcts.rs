@@ -0,0 +1,512 @@
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::time::{Duration, Instant};
+use crate::chessboard::*;
+use crate::chessboard_helper::*;
+use crate::chess_computer::RecieveAndReturnMove;
+use crate::datagen::insufficient_material;
+use crate::evaluation::*;
+use crate::eval_params::EvalParams;
+use crate::search_helper::*;
+
+// Monte Carlo tree search. Every iteration walks down the tree by always picking the child with the best mix
+// of result and uncertainty, adds the children of the position it ends in and scores that position with a
+// playout or the evaluation. The results are averaged in every node on the way back, and the move that was
+// visited most often is played. Results are win probabilities: 1 is a win, 0.5 a draw and 0 a loss.
+
+// the centipawn scale of the conversion between evaluations and win probabilities
+const WIN_PROBABILITY_SCALE: f64 = 400.0;
+// how much better a move has to evaluate to get a noticeably higher prior
+const PRIOR_TEMPERATURE: f64 = 100.0;
+// searches without a limit, like pondering, stop once the tree has this many nodes, a few hundred megabytes
+const MAX_TREE_NODES: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    // upper confidence bound, every move is tried once before any is tried twice
+    Uct,
+    // the upper confidence bound of AlphaZero, weighted by the prior of every move
+    Puct
+}
+
+impl Selection {
+    pub fn from_name(name: &str) -> Option<Selection> {
+        match name {
+            "uct" => Some(Selection::Uct),
+            "puct" => Some(Selection::Puct),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rollout {
+    // random moves until the game ends or the rollout depth is reached
+    Random,
+    // the static evaluation of the new position
+    Evaluation
+}
+
+impl Rollout {
+    pub fn from_name(name: &str) -> Option<Rollout> {
+        match name {
+            "random" => Some(Rollout::Random),
+            "evaluation" => Some(Rollout::Evaluation),
+            _ => None
+        }
+    }
+}
+
+fn win_probability(score: i32) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-score as f64 / WIN_PROBABILITY_SCALE))
+}
+
+fn centipawns(probability: f64) -> i32 {
+    let probability = probability.clamp(0.001, 0.999);
+    (WIN_PROBABILITY_SCALE * (probability / (1.0 - probability)).log10()).round() as i32
+}
+
+struct Node {
+    new_move: Option<Move>,
+    // the zobrist hash of the position, used to find the position again when the tree is reused
+    hash: u64,
+    prior: f64,
+    visits: u32,
+    // the sum of all results from the perspective of the player that made new_move
+    value: f64,
+    children: Vec<usize>,
+    expanded: bool,
+    // the result of a finished game from the perspective of the player to move
+    terminal: Option<f64>
+}
+
+impl Node {
+    fn new(new_move: Option<Move>, hash: u64, prior: f64) -> Node {
+        Node { new_move, hash, prior, visits: 0, value: 0.0, children: Vec::new(), expanded: false, terminal: None }
+    }
+    fn mean(&self) -> f64 {
+        if self.visits == 0 {0.5} else {self.value / self.visits as f64}
+    }
+}
+
+pub struct MctsComputer {
+    // the tree is a list of nodes that refer to their children by index, the root is always the first node
+    tree: Vec<Node>,
+    // the number of iterations per move when the search has no other limit
+    iterations: u64,
+    exploration: f64,
+    selection: Selection,
+    rollout: Rollout,
+    // random playouts that have not ended after this many plies are scored by the evaluation
+    rollout_depth: usize,
+    // keeps the part of the tree below the new position after a move was played
+    tree_reuse: bool,
+    params: EvalParams,
+    pawn_table: PawnHashTable,
+    rng: StdRng,
+    seldepth: u8,
+    last_result: Option<SearchResult>
+}
+
+impl MctsComputer {
+    pub fn new() -> MctsComputer {
+        MctsComputer { tree: Vec::new(), iterations: 20_000, exploration: 1.4, selection: Selection::Puct,
+            rollout: Rollout::Evaluation, rollout_depth: 40, tree_reuse: true, params: EvalParams::new(),
+            pawn_table: PawnHashTable::new(), rng: StdRng::from_entropy(), seldepth: 0, last_result: None }
+    }
+
+    fn relative_evaluate(&mut self, chessboard: &Chessboard) -> i32 {
+        // static evaluation from the perspective of the player that has to move
+        let eval = evaluate(chessboard.get_position(), chessboard.get_pseudo_moves(), &self.params, &mut self.pawn_table, false);
+        match chessboard.get_to_move() {
+            ToMove::White => eval,
+            ToMove::Black => -eval
+        }
+    }
+
+    fn game_result(chessboard: &mut Chessboard, moves: &[Move]) -> Option<f64> {
+        // the result for the player to move if the game is over
+        if moves.is_empty() {
+            return Some(if chessboard.is_in_check() {0.0} else {0.5})
+        }
+        let position = chessboard.get_position();
+        if position.halfmove_clock >= 100 || insufficient_material(position) {
+            return Some(0.5)
+        }
+        None
+    }
+
+    fn find_root(&self, hash: u64) -> Option<usize> {
+        // the new position is the old root or lies one or two plies below it
+        if self.tree.is_empty() {
+            return None
+        }
+        let mut candidates = vec![0];
+        for _ in 0..3 {
+            if let Some(found) = candidates.iter().find(|index| self.tree[**index].hash == hash) {
+                return Some(*found)
+            }
+            candidates = candidates.iter().flat_map(|index| self.tree[*index].children.iter().copied()).collect();
+        }
+        None
+    }
+
+    fn subtree_size(&self, index: usize) -> usize {
+        let mut size = 0;
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            size += 1;
+            stack.extend(self.tree[index].children.iter().copied());
+        }
+        size
+    }
+
+    fn set_root(&mut self, chessboard: &Chessboard) {
+        let hash = chessboard.get_hash();
+        let root = if self.tree_reuse {self.find_root(hash)} else {None};
+        // a subtree that already fills most of the node limit would leave the search no room to grow
+        let root = root.filter(|root| self.subtree_size(*root) < MAX_TREE_NODES / 2);
+        match root {
+            Some(0) => (),
+            Some(root) => {
+                // copy the subtree into a new list so that the rest of the old tree is freed
+                let mut old_tree: Vec<Option<Node>> = std::mem::take(&mut self.tree).into_iter().map(Some).collect();
+                let mut stack = vec![(root, None)];
+                while let Some((old_index, parent)) = stack.pop() {
+                    let mut node = old_tree[old_index].take().unwrap();
+                    let children = std::mem::take(&mut node.children);
+                    let index = self.tree.len();
+                    self.tree.push(node);
+                    if let Some(parent) = parent {
+                        let parent: &mut Node = &mut self.tree[parent];
+                        parent.children.push(index);
+                    }
+                    // reversed so that the children keep their order
+                    stack.extend(children.into_iter().rev().map(|child| (child, Some(index))));
+                }
+                self.tree[0].new_move = None;
+            }
+            None => self.tree = vec![Node::new(None, hash, 1.0)]
+        }
+        // the game goes on from the root even when the rules would allow a draw to be claimed
+        if self.tree[0].terminal.is_some() {
+            self.tree[0].terminal = None;
+            self.tree[0].expanded = false;
+        }
+    }
+
+    fn select_child(&self, index: usize) -> usize {
+        let node = &self.tree[index];
+        let parent_visits = node.visits.max(1) as f64;
+        let score = |child: &Node| match self.selection {
+            Selection::Uct => {
+                if child.visits == 0 {
+                    return f64::INFINITY
+                }
+                child.mean() + self.exploration * (parent_visits.ln() / child.visits as f64).sqrt()
+            }
+            Selection::Puct => {
+                child.mean() + self.exploration * child.prior * parent_visits.sqrt() / (1.0 + child.visits as f64)
+            }
+        };
+        // the first of the best children wins, the children are sorted by their priors
+        let mut best = node.children[0];
+        let mut best_score = f64::NEG_INFINITY;
+        for child in node.children.iter() {
+            let child_score = score(&self.tree[*child]);
+            if child_score > best_score {
+                best = *child;
+                best_score = child_score;
+            }
+        }
+        best
+    }
+
+    fn expand(&mut self, index: usize, chessboard: &mut Chessboard) {
+        self.tree[index].expanded = true;
+        let moves = chessboard.all_moves();
+        if let Some(result) = MctsComputer::game_result(chessboard, &moves) {
+            if index != 0 || moves.is_empty() {
+                self.tree[index].terminal = Some(result);
+                return
+            }
+        }
+        // the priors are a softmax over the evaluation after every move, only PUCT needs them
+        let mut children = Vec::with_capacity(moves.len());
+        for new_move in moves {
+            chessboard.move_piece(&new_move).unwrap();
+            let hash = chessboard.get_hash();
+            let score = if self.selection == Selection::Puct {-self.relative_evaluate(chessboard)} else {0};
+            chessboard.undo();
+            children.push((new_move, hash, score));
+        }
+        children.sort_by_key(|(_, _, score)| -score);
+        let best_score = children[0].2;
+        let weights: Vec<f64> = children.iter().map(|(_, _, score)| ((score - best_score) as f64 / PRIOR_TEMPERATURE).exp()).collect();
+        let total: f64 = weights.iter().sum();
+        for ((new_move, hash, _), weight) in children.into_iter().zip(weights) {
+            let child = self.tree.len();
+            self.tree.push(Node::new(Some(new_move), hash, weight / total));
+            self.tree[index].children.push(child);
+        }
+    }
+
+    fn rollout(&mut self, chessboard: &mut Chessboard) -> f64 {
+        // the result of the position for the player to move
+        if self.rollout == Rollout::Evaluation {
+            return win_probability(self.relative_evaluate(chessboard))
+        }
+        let mut plies = 0;
+        let mut result = None;
+        while plies < self.rollout_depth {
+            let moves = chessboard.all_moves();
+            if let Some(game_result) = MctsComputer::game_result(chessboard, &moves) {
+                result = Some(game_result);
+                break
+            }
+            chessboard.move_piece(moves.choose(&mut self.rng).unwrap()).unwrap();
+            plies += 1;
+        }
+        let mut result = match result {
+            Some(result) => result,
+            None => win_probability(self.relative_evaluate(chessboard))
+        };
+        // the result was for the player to move at the end of the playout
+        if plies % 2 == 1 {
+            result = 1.0 - result;
+        }
+        for _ in 0..plies {
+            chessboard.undo();
+        }
+        result
+    }
+
+    fn iterate(&mut self, chessboard: &mut Chessboard) {
+        // one walk from the root to a new node and back
+        let mut path = vec![0];
+        let mut index = 0;
+        while self.tree[index].expanded && self.tree[index].terminal.is_none() {
+            index = self.select_child(index);
+            chessboard.move_piece(&self.tree[index].new_move.unwrap()).unwrap();
+            path.push(index);
+        }
+        let mut result = match self.tree[index].terminal {
+            Some(result) => result,
+            None => {
+                self.expand(index, chessboard);
+                match self.tree[index].terminal {
+                    Some(result) => result,
+                    None => self.rollout(chessboard)
+                }
+            }
+        };
+        self.seldepth = self.seldepth.max((path.len() - 1).min(u8::MAX as usize) as u8);
+        for _ in 1..path.len() {
+            chessboard.undo();
+        }
+        // every node keeps the result of the player that moved into it
+        for index in path.into_iter().rev() {
+            result = 1.0 - result;
+            let node = &mut self.tree[index];
+            node.visits += 1;
+            node.value += result;
+        }
+    }
+
+    fn best_child(&self, index: usize) -> Option<usize> {
+        self.tree[index].children.iter().copied().max_by_key(|child| self.tree[*child].visits).filter(|child| self.tree[*child].visits > 0)
+    }
+
+    fn result(&self, iterations: u64, elapsed: Duration) -> SearchResult {
+        let mut result = SearchResult::new();
+        let mut index = 0;
+        while let Some(child) = self.best_child(index) {
+            result.pv.push(self.tree[child].new_move.unwrap());
+            index = child;
+        }
+        result.best_move = result.pv.first().copied();
+        if let Some(best) = self.best_child(0) {
+            let best = &self.tree[best];
+            // a move that mates right away is the only result that is certain
+            result.score = if best.terminal == Some(0.0) {MATE_SCORE - 1} else {centipawns(best.mean())};
+        }
+        result.depth = result.pv.len().min(u8::MAX as usize) as u8;
+        result.seldepth = self.seldepth;
+        result.nodes = iterations;
+        result.time_ms = elapsed.as_millis() as u64;
+        result.nps = (iterations as f64 / elapsed.as_secs_f64().max(0.001)) as u64;
+        result.lines = vec![PvLine { pv: result.pv.clone(), score: result.score }];
+        result
+    }
+}
+
+impl RecieveAndReturnMove for MctsComputer {
+    fn return_move(&mut self, chessboard: &mut Chessboard) -> Move {
+        let limits = SearchLimits::new(None, Some(self.iterations), None, None, None, None, None, None, false);
+        self.search(chessboard, &limits, &SearchControl::without_stop()).best_move.expect("No moves available.")
+    }
+    fn search(&mut self, chessboard: &mut Chessboard, limits: &SearchLimits, control: &SearchControl) -> SearchResult {
+        let start = Instant::now();
+        let deadline = limits.time_budget(chessboard.get_to_move()).map(|budget| start + budget);
+        // without any limit the search runs for the configured number of iterations
+        let max_iterations = match limits.node_limit() {
+            Some(nodes) => Some(nodes),
+            None if limits.infinite || deadline.is_some() => None,
+            None => Some(self.iterations)
+        };
+        self.set_root(chessboard);
+        self.seldepth = 0;
+        let mut iterations = 0;
+        let mut last_info = start;
+        loop {
+            self.iterate(chessboard);
+            iterations += 1;
+            if self.tree[0].terminal.is_some() || max_iterations.is_some_and(|max| iterations >= max) {
+                break
+            }
+            if iterations % 256 == 0 {
+                if control.stop_requested() || self.tree.len() >= MAX_TREE_NODES || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    break
+                }
+                if last_info.elapsed() >= Duration::from_millis(500) {
+                    control.send_info(&self.result(iterations, start.elapsed()));
+                    last_info = Instant::now();
+                }
+            }
+        }
+        let result = self.result(iterations, start.elapsed());
+        control.send_info(&result);
+        self.last_result = Some(result.clone());
+        result
+    }
+    fn last_search_result(&self) -> Option<SearchResult> {
+        self.last_result.clone()
+    }
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), InvalidOptionError> {
+        match name {
+            "iterations" => {
+                let iterations: u64 = value.parse().map_err(|_| InvalidOptionError)?;
+                if iterations == 0 {
+                    return Err(InvalidOptionError)
+                }
+                self.iterations = iterations;
+            }
+            "exploration" => {
+                let exploration: f64 = value.parse().map_err(|_| InvalidOptionError)?;
+                if exploration.is_nan() || exploration < 0.0 {
+                    return Err(InvalidOptionError)
+                }
+                self.exploration = exploration;
+            }
+            // uct or puct
+            "selection" => self.selection = Selection::from_name(value).ok_or(InvalidOptionError)?,
+            // random or evaluation
+            "rollout" => self.rollout = Rollout::from_name(value).ok_or(InvalidOptionError)?,
+            "rollout_depth" => self.rollout_depth = value.parse().map_err(|_| InvalidOptionError)?,
+            "tree_reuse" => {
+                self.tree_reuse = match value {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(InvalidOptionError)
+                };
+            }
+            "seed" => self.rng = StdRng::seed_from_u64(value.parse().map_err(|_| InvalidOptionError)?),
+            _ => return Err(InvalidOptionError)
+        }
+        // the old tree was built with the old settings
+        self.tree.clear();
+        Ok(())
+    }
+    fn set_eval_params(&mut self, params: EvalParams) -> Result<(), InvalidOptionError> {
+        self.params = params;
+        self.tree.clear();
+        Ok(())
+    }
+    fn eval_params(&self) -> Option<EvalParams> {
+        Some(self.params.clone())
+    }
+    fn new_game(&mut self) {
+        // the tree of the previous game is of no use
+        self.tree.clear();
+        self.last_result = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::*;
+
+    // white mates with Ra8, every other move lets the game go on
+    const MATE_IN_ONE_FEN: &str = "6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1";
+
+    fn computer(selection: &str, rollout: &str, seed: u64) -> MctsComputer {
+        let mut computer = MctsComputer::new();
+        computer.set_option("selection", selection).unwrap();
+        computer.set_option("rollout", rollout).unwrap();
+        computer.set_option("rollout_depth", "10").unwrap();
+        computer.set_option("seed", &seed.to_string()).unwrap();
+        computer
+    }
+
+    #[test]
+    fn every_mode_finds_a_mate_in_one() {
+        let limits = SearchLimits::new(None, Some(300), None, None, None, None, None, None, false);
+        for selection in ["uct", "puct"] {
+            for rollout in ["random", "evaluation"] {
+                let mut chessboard = chessboard(MATE_IN_ONE_FEN);
+                let result = computer(selection, rollout, 1).search(&mut chessboard, &limits, &SearchControl::without_stop());
+                assert_eq!(result.best_move, Move::from_string("a1a8"), "{} with {} rollouts", selection, rollout);
+                assert_eq!(result.score, MATE_SCORE - 1, "{} with {} rollouts", selection, rollout);
+            }
+        }
+    }
+
+    #[test]
+    fn the_same_seed_searches_the_same_tree() {
+        let limits = SearchLimits::new(None, Some(200), None, None, None, None, None, None, false);
+        let searches: Vec<(Vec<Move>, i32, Vec<u32>)> = [3, 3, 4].into_iter().map(|seed| {
+            let mut chessboard = chessboard(KIWIPETE_FEN);
+            let mut computer = computer("uct", "random", seed);
+            let result = computer.search(&mut chessboard, &limits, &SearchControl::without_stop());
+            // the visits of the root moves show the whole search, not just the chosen line
+            let visits = computer.tree[0].children.iter().map(|child| computer.tree[*child].visits).collect();
+            (result.pv, result.score, visits)
+        }).collect();
+        assert_eq!(searches[0], searches[1]);
+        // random playouts with another seed end differently
+        assert_ne!(searches[0], searches[2]);
+    }
+
+    #[test]
+    fn the_tree_is_reused_after_the_reply() {
+        let limits = SearchLimits::new(None, Some(300), None, None, None, None, None, None, false);
+        let mut chessboard = chessboard(START_FEN);
+        let mut computer = computer("puct", "evaluation", 1);
+        computer.search(&mut chessboard, &limits, &SearchControl::without_stop());
+        // the reply of the opponent to the move that was played
+        let played = computer.best_child(0).unwrap();
+        let reply = computer.best_child(played).unwrap();
+        let (visits, children) = (computer.tree[reply].visits, computer.tree[reply].children.len());
+        chessboard.move_piece(&computer.tree[played].new_move.unwrap()).unwrap();
+        chessboard.move_piece(&computer.tree[reply].new_move.unwrap()).unwrap();
+        computer.set_root(&chessboard);
+        assert_eq!(computer.tree[0].hash, chessboard.get_hash());
+        assert_eq!(computer.tree[0].new_move, None);
+        assert_eq!(computer.tree[0].visits, visits);
+        assert_eq!(computer.tree[0].children.len(), children);
+        assert_eq!(computer.tree.len(), computer.subtree_size(0));
+        // the children still belong to the new root
+        for child in computer.tree[0].children.clone() {
+            let mut next = chessboard.clone();
+            next.move_piece(&computer.tree[child].new_move.unwrap()).unwrap();
+            assert_eq!(computer.tree[child].hash, next.get_hash());
+        }
+
+        computer.set_option("tree_reuse", "false").unwrap();
+        computer.search(&mut chessboard, &limits, &SearchControl::without_stop());
+        computer.set_root(&chessboard);
+        assert_eq!(computer.tree.len(), 1);
+    }
+}
@@ -0,0 +1,473 @@
+use pyo3::prelude::*;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use crate::chessboard::Chessboard;
+use crate::chessboard_helper::*;
+use crate::search_helper::*;
+
+// An efficiently updatable neural network (NNUE) as evaluation. The input layer has one feature for every
+// combination of king square, piece and square, seen from each side (the perspective). Only a few of these
+// features change with a move, so instead of recomputing the first layer for every position we keep its
+// output, the accumulator, and only add and subtract the columns of the features that changed. The
+// accumulators of both perspectives go through a clipped relu into a single output neuron.
+//
+// All weights are quantized integers. A network file is little endian and looks like this:
+//   magic "RCNN", version u32 (1), feature set u32 (0 = HalfKP, 1 = HalfKA), hidden size u32,
+//   qa u32, qb u32, scale u32,
+//   feature weights i16 [inputs * hidden], feature biases i16 [hidden],
+//   output weights i16 [2 * hidden] (side to move first), output bias i32
+// The first layer is quantized by qa, so qa is the value of 1.0 after the clipped relu, the output weights
+// are quantized by qb and the output bias by qa * qb. The output is multiplied by scale to get centipawns.
+
+const MAGIC: &[u8; 4] = b"RCNN";
+const VERSION: u32 = 1;
+const MAX_HIDDEN: usize = 4096;
+// the network the nnue computer loads when it is created, relative to the directory the engine runs in
+pub const DEFAULT_NETWORK: &str = "networks/default.nnue";
+// networks are not trained on mates, their scores are kept well away from the mate scores of the search
+const SCORE_LIMIT: i32 = MATE_SCORE / 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureSet {
+    // HalfKP has features for all pieces but the kings, HalfKA also has the kings as features
+    HalfKP,
+    HalfKA
+}
+
+impl FeatureSet {
+    fn pieces_per_king(&self) -> usize {
+        match self {
+            FeatureSet::HalfKP => 10,
+            FeatureSet::HalfKA => 12
+        }
+    }
+    pub fn inputs(&self) -> usize {
+        64 * self.pieces_per_king() * 64
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            FeatureSet::HalfKP => "HalfKP",
+            FeatureSet::HalfKA => "HalfKA"
+        }
+    }
+    pub fn from_name(name: &str) -> Option<FeatureSet> {
+        match name.to_lowercase().as_str() {
+            "halfkp" => Some(FeatureSet::HalfKP),
+            "halfka" => Some(FeatureSet::HalfKA),
+            _ => None
+        }
+    }
+    fn index(&self, white_perspective: bool, king: usize, piece: usize, white_piece: bool, square: usize) -> Option<usize> {
+        // Black sees the board flipped, so both perspectives see their own pieces moving up the board.
+        // piece is 0 to 5 in the order pawn, knight, bishop, rook, queen, king.
+        let (king, square) = if white_perspective {(king, square)} else {(king ^ 56, square ^ 56)};
+        let own = white_piece == white_perspective;
+        let kind = match self {
+            FeatureSet::HalfKP if piece == 5 => return None,
+            FeatureSet::HalfKP => if own {piece} else {5 + piece},
+            FeatureSet::HalfKA => if own {piece} else {6 + piece}
+        };
+        Some((king * self.pieces_per_king() + kind) * 64 + square)
+    }
+}
+
+fn piece_bitboards(pieces: &Pieces) -> [u64; 6] {
+    [pieces.get_bb_pawns(), pieces.get_bb_knights(), pieces.get_bb_bishops(), pieces.get_bb_rooks(),
+        pieces.get_bb_queens(), pieces.get_bb_king()]
+}
+
+fn king_square(position: &Position, white: bool) -> usize {
+    // a position without a king can only come from a broken fen, we then use a1 so the index stays valid
+    let king = if white {position.white_pieces.get_bb_king()} else {position.black_pieces.get_bb_king()};
+    if king == 0 {56} else {king.trailing_zeros() as usize}
+}
+
+fn squares(mut bb: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if bb == 0 {
+            return None
+        }
+        let square = bb.trailing_zeros() as usize;
+        bb &= bb - 1;
+        Some(square)
+    })
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct NnueNetwork {
+    feature_set: FeatureSet,
+    hidden: usize,
+    qa: i32,
+    qb: i32,
+    scale: i32,
+    // column major, the weights of input i are feature_weights[i * hidden..(i + 1) * hidden]
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], Error> {
+        if self.offset + count > self.bytes.len() {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "the network file is too short"))
+        }
+        let bytes = &self.bytes[self.offset..self.offset + count];
+        self.offset += count;
+        Ok(bytes)
+    }
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn i32(&mut self) -> Result<i32, Error> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn i16s(&mut self, count: usize) -> Result<Vec<i16>, Error> {
+        Ok(self.take(count * 2)?.chunks_exact(2).map(|pair| i16::from_le_bytes([pair[0], pair[1]])).collect())
+    }
+}
+
+impl NnueNetwork {
+    pub fn load_file(filepath: &str) -> Result<NnueNetwork, Error> {
+        let bytes = fs::read(filepath)?;
+        let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message.to_string());
+        let mut reader = Reader { bytes: &bytes, offset: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(invalid("not a network file"))
+        }
+        if reader.u32()? != VERSION {
+            return Err(invalid("unsupported network version"))
+        }
+        let feature_set = match reader.u32()? {
+            0 => FeatureSet::HalfKP,
+            1 => FeatureSet::HalfKA,
+            _ => return Err(invalid("unknown feature set"))
+        };
+        let hidden = reader.u32()? as usize;
+        if hidden == 0 || hidden > MAX_HIDDEN {
+            return Err(invalid("unsupported hidden layer size"))
+        }
+        let (qa, qb, scale) = (reader.u32()? as i32, reader.u32()? as i32, reader.u32()? as i32);
+        if qa <= 0 || qb <= 0 || scale <= 0 {
+            return Err(invalid("the quantization factors must be positive"))
+        }
+        let feature_weights = reader.i16s(feature_set.inputs() * hidden)?;
+        let feature_biases = reader.i16s(hidden)?;
+        let output_weights = reader.i16s(2 * hidden)?;
+        let output_bias = reader.i32()?;
+        if reader.offset != bytes.len() {
+            return Err(invalid("the network file is too long"))
+        }
+        Ok(NnueNetwork { feature_set, hidden, qa, qb, scale, feature_weights, feature_biases, output_weights, output_bias })
+    }
+    pub fn save_file(&self, filepath: &str) -> Result<(), Error> {
+        let mut bytes = Vec::with_capacity(32 + 2 * (self.feature_weights.len() + 3 * self.hidden));
+        bytes.extend_from_slice(MAGIC);
+        let feature_set = match self.feature_set {
+            FeatureSet::HalfKP => 0u32,
+            FeatureSet::HalfKA => 1
+        };
+        for value in [VERSION, feature_set, self.hidden as u32, self.qa as u32, self.qb as u32, self.scale as u32] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in self.feature_weights.iter().chain(self.feature_biases.iter()).chain(self.output_weights.iter()) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+        fs::write(filepath, bytes)
+    }
+    pub fn random_network(feature_set: FeatureSet, hidden: usize, seed: u64) -> NnueNetwork {
+        // an untrained network with small weights, only useful to test the code around it
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (qa, qb) = (255, 64);
+        NnueNetwork {
+            feature_set,
+            hidden,
+            qa,
+            qb,
+            scale: 400,
+            feature_weights: (0..feature_set.inputs() * hidden).map(|_| rng.gen_range(-16..=16)).collect(),
+            feature_biases: (0..hidden).map(|_| rng.gen_range(0..=64)).collect(),
+            output_weights: (0..2 * hidden).map(|_| rng.gen_range(-16..=16)).collect(),
+            output_bias: 0
+        }
+    }
+    fn features(&self, position: &Position, white_perspective: bool) -> Vec<usize> {
+        // the indices of all active features of a position seen from one side
+        let king = king_square(position, white_perspective);
+        let mut features = Vec::with_capacity(32);
+        for (white_piece, pieces) in [(true, &position.white_pieces), (false, &position.black_pieces)] {
+            for (piece, bb) in piece_bitboards(pieces).iter().enumerate() {
+                for square in squares(*bb) {
+                    if let Some(index) = self.feature_set.index(white_perspective, king, piece, white_piece, square) {
+                        features.push(index);
+                    }
+                }
+            }
+        }
+        features
+    }
+    fn refresh(&self, position: &Position, white_perspective: bool, accumulator: &mut [i16]) {
+        // computes the accumulator of one perspective from scratch
+        accumulator.copy_from_slice(&self.feature_biases);
+        for feature in self.features(position, white_perspective) {
+            self.add_feature(accumulator, feature);
+        }
+    }
+    fn add_feature(&self, accumulator: &mut [i16], feature: usize) {
+        let weights = &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden];
+        for (value, weight) in accumulator.iter_mut().zip(weights) {
+            *value = value.wrapping_add(*weight);
+        }
+    }
+    fn sub_feature(&self, accumulator: &mut [i16], feature: usize) {
+        let weights = &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden];
+        for (value, weight) in accumulator.iter_mut().zip(weights) {
+            *value = value.wrapping_sub(*weight);
+        }
+    }
+    fn output(&self, to_move: &[i16], other: &[i16]) -> i32 {
+        // The integer forward pass, plain loops that run on any processor. The sum is kept in an i64 since
+        // qa times the largest weight times the number of neurons does not always fit into an i32.
+        let clipped = |value: &i16| (*value as i32).clamp(0, self.qa) as i64;
+        let (to_move_weights, other_weights) = self.output_weights.split_at(self.hidden);
+        let mut sum = self.output_bias as i64;
+        for (value, weight) in to_move.iter().zip(to_move_weights) {
+            sum += clipped(value) * *weight as i64;
+        }
+        for (value, weight) in other.iter().zip(other_weights) {
+            sum += clipped(value) * *weight as i64;
+        }
+        let score = sum * self.scale as i64 / (self.qa as i64 * self.qb as i64);
+        score.clamp(-SCORE_LIMIT as i64, SCORE_LIMIT as i64) as i32
+    }
+    pub fn evaluate_position(&self, position: &Position) -> i32 {
+        // the integer evaluation computed without any accumulator to start from, from the perspective of white
+        let mut white = vec![0; self.hidden];
+        let mut black = vec![0; self.hidden];
+        self.refresh(position, true, &mut white);
+        self.refresh(position, false, &mut black);
+        match position.to_move {
+            ToMove::White => self.output(&white, &black),
+            ToMove::Black => -self.output(&black, &white)
+        }
+    }
+    pub fn evaluate_float_position(&self, position: &Position) -> f64 {
+        // Reference implementation with the weights turned back into real numbers. It does the same as the
+        // integer path without rounding, the two should only differ by a few centipawns.
+        let (qa, qb) = (self.qa as f64, self.qb as f64);
+        let accumulate = |white_perspective: bool| -> Vec<f64> {
+            let mut accumulator: Vec<f64> = self.feature_biases.iter().map(|bias| *bias as f64 / qa).collect();
+            for feature in self.features(position, white_perspective) {
+                for (i, value) in accumulator.iter_mut().enumerate() {
+                    *value += self.feature_weights[feature * self.hidden + i] as f64 / qa;
+                }
+            }
+            accumulator
+        };
+        let (white, black) = (accumulate(true), accumulate(false));
+        let (to_move, other, sign) = match position.to_move {
+            ToMove::White => (white, black, 1.0),
+            ToMove::Black => (black, white, -1.0)
+        };
+        let mut output = self.output_bias as f64 / (qa * qb);
+        for (i, value) in to_move.iter().chain(other.iter()).enumerate() {
+            output += value.clamp(0.0, 1.0) * self.output_weights[i] as f64 / qb;
+        }
+        let limit = SCORE_LIMIT as f64;
+        sign * (output * self.scale as f64).clamp(-limit, limit)
+    }
+}
+
+#[pymethods]
+impl NnueNetwork {
+    #[staticmethod]
+    pub fn load(filepath: &str) -> PyResult<NnueNetwork> {
+        NnueNetwork::load_file(filepath).map_err(|err| PyIOError::new_err(err.to_string()))
+    }
+    pub fn save(&self, filepath: &str) -> PyResult<()> {
+        self.save_file(filepath).map_err(|err| PyIOError::new_err(err.to_string()))
+    }
+    #[staticmethod]
+    #[pyo3(signature = (feature_set="HalfKP", hidden=256, seed=0))]
+    pub fn random(feature_set: &str, hidden: usize, seed: u64) -> PyResult<NnueNetwork> {
+        let feature_set = FeatureSet::from_name(feature_set)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown feature set {}", feature_set)))?;
+        if hidden == 0 || hidden > MAX_HIDDEN {
+            return Err(PyValueError::new_err(format!("the hidden layer must have 1 to {} neurons", MAX_HIDDEN)))
+        }
+        Ok(NnueNetwork::random_network(feature_set, hidden, seed))
+    }
+    pub fn get_feature_set(&self) -> String {
+        self.feature_set.name().to_string()
+    }
+    pub fn get_hidden_size(&self) -> usize {
+        self.hidden
+    }
+    pub fn evaluate(&self, chessboard: &Chessboard) -> i32 {
+        // the evaluation of the integer path in centipawns from the perspective of white
+        self.evaluate_position(chessboard.get_position())
+    }
+    pub fn evaluate_float(&self, chessboard: &Chessboard) -> f64 {
+        self.evaluate_float_position(chessboard.get_position())
+    }
+}
+
+pub struct AccumulatorStack {
+    // The accumulators of every position from the root of the search to the current node. Each entry holds
+    // the white and then the black perspective, so an entry is 2 * hidden values long. Making a move
+    // copies the top entry and applies the changed features, undoing a move just drops the top entry.
+    values: Vec<i16>,
+    hidden: usize,
+    // the number of entries in use
+    len: usize
+}
+
+impl AccumulatorStack {
+    pub fn new() -> AccumulatorStack {
+        AccumulatorStack { values: Vec::new(), hidden: 0, len: 0 }
+    }
+    pub fn reset(&mut self, network: &NnueNetwork, position: &Position) {
+        // starts over with the given position as the only entry
+        self.hidden = network.hidden;
+        self.len = 0;
+        self.grow();
+        let (white, black) = self.values[..2 * self.hidden].split_at_mut(self.hidden);
+        network.refresh(position, true, white);
+        network.refresh(position, false, black);
+        self.len = 1;
+    }
+    fn grow(&mut self) {
+        let needed = (self.len + 1) * 2 * self.hidden;
+        if self.values.len() < needed {
+            self.values.resize(needed, 0);
+        }
+    }
+    pub fn push(&mut self, network: &NnueNetwork, before: &Position, after: &Position) {
+        // adds the accumulators of after, which is before with one move made on it
+        self.grow();
+        let size = 2 * self.hidden;
+        let (done, rest) = self.values.split_at_mut(self.len * size);
+        let previous = &done[(self.len - 1) * size..];
+        let current = &mut rest[..size];
+        current.copy_from_slice(previous);
+        for (white_perspective, accumulator) in [true, false].into_iter().zip(current.chunks_exact_mut(self.hidden)) {
+            let king = king_square(after, white_perspective);
+            // the features of a perspective depend on its king, when the king moves all of them change
+            if king != king_square(before, white_perspective) {
+                network.refresh(after, white_perspective, accumulator);
+                continue
+            }
+            for (white_piece, old, new) in [(true, &before.white_pieces, &after.white_pieces), (false, &before.black_pieces, &after.black_pieces)] {
+                let (old, new) = (piece_bitboards(old), piece_bitboards(new));
+                for piece in 0..6 {
+                    for square in squares(old[piece] & !new[piece]) {
+                        if let Some(feature) = network.feature_set.index(white_perspective, king, piece, white_piece, square) {
+                            network.sub_feature(accumulator, feature);
+                        }
+                    }
+                    for square in squares(new[piece] & !old[piece]) {
+                        if let Some(feature) = network.feature_set.index(white_perspective, king, piece, white_piece, square) {
+                            network.add_feature(accumulator, feature);
+                        }
+                    }
+                }
+            }
+        }
+        self.len += 1;
+    }
+    pub fn pop(&mut self) {
+        // every pop belongs to a push, the entry of the root stays
+        debug_assert!(self.len > 1, "popped the accumulators of the root");
+        self.len -= 1;
+    }
+    pub fn evaluate(&self, network: &NnueNetwork, to_move: &ToMove) -> i32 {
+        // the evaluation of the top entry from the perspective of white
+        let size = 2 * self.hidden;
+        let (white, black) = self.values[(self.len - 1) * size..self.len * size].split_at(self.hidden);
+        match to_move {
+            ToMove::White => network.output(white, black),
+            ToMove::Black => -network.output(black, white)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+    use super::*;
+    use crate::test_helper::*;
+
+    #[test]
+    fn incremental_accumulators_match_a_full_refresh() {
+        // random games with king moves, castling, captures and promotions, forward and back
+        let mut rng = StdRng::seed_from_u64(37);
+        for feature_set in [FeatureSet::HalfKP, FeatureSet::HalfKA] {
+            let network = NnueNetwork::random_network(feature_set, 32, 1);
+            for fen in [START_FEN, KIWIPETE_FEN, "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"] {
+                let mut chessboard = chessboard(fen);
+                let mut stack = AccumulatorStack::new();
+                stack.reset(&network, chessboard.get_position());
+                let mut evaluations = vec![network.evaluate_position(chessboard.get_position())];
+                for _ in 0..120 {
+                    let new_move = match chessboard.all_moves().choose(&mut rng) {
+                        Some(new_move) => *new_move,
+                        None => break
+                    };
+                    let before = *chessboard.get_position();
+                    chessboard.move_piece(&new_move).unwrap();
+                    stack.push(&network, &before, chessboard.get_position());
+                    let expected = network.evaluate_position(chessboard.get_position());
+                    assert_eq!(stack.evaluate(&network, chessboard.get_to_move()), expected, "{}", chessboard.get_fen());
+                    evaluations.push(expected);
+                }
+                evaluations.pop();
+                while let Some(expected) = evaluations.pop() {
+                    chessboard.undo();
+                    stack.pop();
+                    assert_eq!(stack.evaluate(&network, chessboard.get_to_move()), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn integer_path_matches_the_float_reference() {
+        // the integer accumulators hold the float values times qa exactly, only the last division rounds
+        let network = NnueNetwork::random_network(FeatureSet::HalfKP, 64, 2);
+        let mut rng = StdRng::seed_from_u64(38);
+        let mut chessboard = chessboard(START_FEN);
+        for _ in 0..80 {
+            let position = chessboard.get_position();
+            let difference = network.evaluate_position(position) as f64 - network.evaluate_float_position(position);
+            assert!(difference.abs() <= 1.0, "{} differs by {}", chessboard.get_fen(), difference);
+            let new_move = match chessboard.all_moves().choose(&mut rng) {
+                Some(new_move) => *new_move,
+                None => break
+            };
+            chessboard.move_piece(&new_move).unwrap();
+        }
+    }
+
+    #[test]
+    fn saved_network_loads_the_same() {
+        let network = NnueNetwork::random_network(FeatureSet::HalfKA, 16, 3);
+        let filepath = std::env::temp_dir().join("rustchess_test_network.nnue");
+        let filepath = filepath.to_str().unwrap();
+        network.save_file(filepath).unwrap();
+        let loaded = NnueNetwork::load_file(filepath).unwrap();
+        fs::remove_file(filepath).unwrap();
+        let chessboard = chessboard(KIWIPETE_FEN);
+        assert_eq!(loaded.evaluate_position(chessboard.get_position()), network.evaluate_position(chessboard.get_position()));
+        assert!(NnueNetwork::load_file("no/such/network.nnue").is_err());
+    }
+}
@@ -0,0 +1,405 @@
+use pyo3::prelude::*;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Sender;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use crate::chessboard_helper::*;
+
+// the maximum number of plies the search can go deep, used for sizing the pv table
+pub const MAX_PLY: usize = 64;
+// scores used by the search, a mate in n plies is scored as MATE_SCORE - n
+pub const INFINITY: i32 = 1_000_000;
+pub const MATE_SCORE: i32 = 100_000;
+// a position that the tablebases say is won, below the mate scores such that a real mate is still preferred
+pub const TB_WIN_SCORE: i32 = MATE_SCORE - 2 * MAX_PLY as i32;
+
+#[inline]
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_SCORE - MAX_PLY as i32
+}
+
+#[inline]
+pub fn is_decisive_score(score: i32) -> bool {
+    // mates and tablebase wins, both count the plies from the root
+    score.abs() >= TB_WIN_SCORE - MAX_PLY as i32
+}
+
+fn moves_to_mate(score: i32) -> Option<i32> {
+    // the number of moves until mate, negative when the side to move gets mated
+    if !is_mate_score(score) {
+        return None
+    }
+    let moves = (MATE_SCORE - score.abs() + 1) / 2;
+    Some(if score > 0 {moves} else {-moves})
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct InvalidOptionError;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SkillLevel {
+    // How strong a strength limited computer plays. The search is cut short by depth and nodes, and the move is
+    // picked from the best few lines after random centipawns are added to their scores. Once in a while the
+    // computer plays any of the lines, which is how weak players lose their pieces.
+    pub depth: u8,
+    pub nodes: Option<u64>,
+    pub candidates: usize,
+    // the largest number of centipawns that is added to or subtracted from the score of a line
+    pub score_noise: i32,
+    // the chance in percent that a random line is played instead of the best one
+    pub blunder_chance: u32
+}
+
+impl SkillLevel {
+    pub const MAX_LEVEL: u8 = 20;
+    pub const MIN_ELO: u32 = 600;
+    pub const MAX_ELO: u32 = 2400;
+
+    pub fn from_level(level: u8) -> SkillLevel {
+        // level 0 is a beginner, the highest level searches without node limit, noise or blunders
+        let level = level.min(SkillLevel::MAX_LEVEL);
+        let weakness = (SkillLevel::MAX_LEVEL - level) as i32;
+        SkillLevel {
+            depth: 1 + level / 4,
+            nodes: if level == SkillLevel::MAX_LEVEL {None} else {Some(200 << (level / 2))},
+            candidates: if level == SkillLevel::MAX_LEVEL {1} else {4},
+            score_noise: weakness * 15,
+            blunder_chance: weakness as u32 * 3 / 2
+        }
+    }
+    pub fn from_elo(elo: u32) -> SkillLevel {
+        // a rough linear mapping of the rating onto the levels, it is not measured against rated players
+        let elo = elo.clamp(SkillLevel::MIN_ELO, SkillLevel::MAX_ELO);
+        let level = (elo - SkillLevel::MIN_ELO) * SkillLevel::MAX_LEVEL as u32 / (SkillLevel::MAX_ELO - SkillLevel::MIN_ELO);
+        SkillLevel::from_level(level as u8)
+    }
+    pub fn limit(&self, limits: &SearchLimits) -> SearchLimits {
+        // the limits of the search are never looser than the skill allows, not even for an infinite search
+        let mut limited = *limits;
+        limited.infinite = false;
+        limited.depth = Some(limits.depth.map_or(self.depth, |depth| depth.min(self.depth)));
+        limited.nodes = match (limits.nodes, self.nodes) {
+            (Some(nodes), Some(max_nodes)) => Some(nodes.min(max_nodes)),
+            (nodes, max_nodes) => nodes.or(max_nodes)
+        };
+        limited
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone, Copy)]
+pub struct SearchLimits {
+    // All limits of a search, None means that the limit is not used. The search stops as soon as one of the
+    // limits is reached. Times are given in milliseconds like the go command of the UCI protocol.
+    #[pyo3(get, set)]
+    pub depth: Option<u8>,
+    #[pyo3(get, set)]
+    pub nodes: Option<u64>,
+    #[pyo3(get, set)]
+    pub movetime: Option<u64>,
+    #[pyo3(get, set)]
+    pub wtime: Option<u64>,
+    #[pyo3(get, set)]
+    pub btime: Option<u64>,
+    #[pyo3(get, set)]
+    pub winc: Option<u64>,
+    #[pyo3(get, set)]
+    pub binc: Option<u64>,
+    // search for a mate in this many moves
+    #[pyo3(get, set)]
+    pub mate: Option<u8>,
+    // search until the stop flag is set, all other limits are ignored
+    #[pyo3(get, set)]
+    pub infinite: bool
+}
+
+impl SearchLimits {
+    pub fn from_depth(depth: u8) -> SearchLimits {
+        let mut limits = SearchLimits::new(None, None, None, None, None, None, None, None, false);
+        limits.depth = Some(depth);
+        limits
+    }
+    pub fn max_depth(&self) -> u8 {
+        // the deepest iteration the search may start
+        if self.infinite {
+            return (MAX_PLY - 1) as u8
+        }
+        let mut max_depth = (MAX_PLY - 1) as u8;
+        if let Some(depth) = self.depth {
+            max_depth = max_depth.min(depth);
+        }
+        if let Some(mate) = self.mate {
+            // a mate in n moves is found within 2n - 1 plies
+            max_depth = max_depth.min(mate.saturating_mul(2).saturating_sub(1).max(1));
+        }
+        max_depth
+    }
+    pub fn node_limit(&self) -> Option<u64> {
+        if self.infinite {None} else {self.nodes}
+    }
+    pub fn time_budget(&self, to_move: &ToMove) -> Option<Duration> {
+        // returns how much time we may spend on this move, None if there is no time limit
+        if self.infinite {
+            return None
+        }
+        if let Some(movetime) = self.movetime {
+            return Some(Duration::from_millis(movetime))
+        }
+        let (time, inc) = match to_move {
+            ToMove::White => (self.wtime?, self.winc.unwrap_or(0)),
+            ToMove::Black => (self.btime?, self.binc.unwrap_or(0))
+        };
+        // spend a small part of the remaining time, but always keep a little reserve on the clock
+        let budget = time / 30 + inc * 3 / 4;
+        Some(Duration::from_millis(budget.min(time.saturating_sub(50)).max(10)))
+    }
+}
+
+#[pymethods]
+impl SearchLimits {
+    #[new]
+    #[pyo3(signature = (depth=None, nodes=None, movetime=None, wtime=None, btime=None, winc=None, binc=None, mate=None, infinite=false))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(depth: Option<u8>, nodes: Option<u64>, movetime: Option<u64>, wtime: Option<u64>, btime: Option<u64>,
+        winc: Option<u64>, binc: Option<u64>, mate: Option<u8>, infinite: bool) -> SearchLimits {
+        SearchLimits { depth, nodes, movetime, wtime, btime, winc, binc, mate, infinite }
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct StopHandle {
+    // A handle to the stop flag of a search. Python holds on to this handle so that it can stop a search
+    // from another thread, the coordinator itself is borrowed by the searching thread during the search.
+    flag: Arc<AtomicBool>
+}
+
+impl StopHandle {
+    pub fn new(flag: Arc<AtomicBool>) -> StopHandle {
+        StopHandle { flag }
+    }
+}
+
+#[pymethods]
+impl StopHandle {
+    pub fn stop(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+    pub fn is_stopped(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchControl {
+    // everything a running search listens to besides its limits
+    stop: Arc<AtomicBool>,
+    // a time limit that is given while the search already runs, like after a ponderhit. Every control has
+    // its own, so it can only ever end the search it was made for
+    deadline: Arc<Mutex<Option<Instant>>>,
+    // the search sends its intermediate results here, for example after every finished iteration
+    info: Option<Sender<SearchResult>>
+}
+
+impl SearchControl {
+    pub fn new(stop: Arc<AtomicBool>) -> SearchControl {
+        SearchControl { stop, deadline: Arc::new(Mutex::new(None)), info: None }
+    }
+    pub fn with_info(stop: Arc<AtomicBool>, info: Sender<SearchResult>) -> SearchControl {
+        SearchControl { stop, deadline: Arc::new(Mutex::new(None)), info: Some(info) }
+    }
+    pub fn without_stop() -> SearchControl {
+        // a control for searches that only end by their limits
+        SearchControl::new(Arc::new(AtomicBool::new(false)))
+    }
+    #[inline]
+    pub fn stop_requested(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+            || self.deadline.lock().unwrap().is_some_and(|deadline| Instant::now() >= deadline)
+    }
+    pub fn set_deadline(&self, deadline: Instant) {
+        // the search ends at deadline, no matter what its limits say
+        *self.deadline.lock().unwrap() = Some(deadline);
+    }
+    pub fn send_info(&self, result: &SearchResult) {
+        // nobody might be listening anymore, which is fine
+        if let Some(info) = &self.info {
+            let _ = info.send(result.clone());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOptions {
+    // Every enhancement of the alpha-beta search can be switched off on its own,
+    // so that we can play matches with and without it and measure what it is worth.
+    pub principal_variation_search: bool,
+    pub aspiration_windows: bool,
+    pub null_move_pruning: bool,
+    pub late_move_reductions: bool,
+    pub check_extensions: bool,
+    pub quiescence: bool,
+    pub see_pruning: bool,
+    pub bitbases: bool
+}
+
+impl SearchOptions {
+    pub fn new() -> SearchOptions {
+        SearchOptions {
+            principal_variation_search: true,
+            aspiration_windows: true,
+            null_move_pruning: true,
+            late_move_reductions: true,
+            check_extensions: true,
+            quiescence: true,
+            see_pruning: true,
+            bitbases: true
+        }
+    }
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), InvalidOptionError> {
+        // sets an option by its name, value must be either "true" or "false"
+        let value = match value {
+            "true" => true,
+            "false" => false,
+            _ => return Err(InvalidOptionError)
+        };
+        match name {
+            "pvs" => self.principal_variation_search = value,
+            "aspiration" => self.aspiration_windows = value,
+            "null_move" => self.null_move_pruning = value,
+            "lmr" => self.late_move_reductions = value,
+            "check_extensions" => self.check_extensions = value,
+            "quiescence" => self.quiescence = value,
+            "see_pruning" => self.see_pruning = value,
+            "bitbases" => self.bitbases = value,
+            _ => return Err(InvalidOptionError)
+        }
+        Ok(())
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct PvLine {
+    // one of the best lines of a multipv search, the score is from the perspective of the side to move
+    pub pv: Vec<Move>,
+    pub score: i32
+}
+
+#[pymethods]
+impl PvLine {
+    pub fn get_move(&self) -> Option<String> {
+        self.pv.first().map(|m| m.to_string())
+    }
+    pub fn get_pv(&self) -> Vec<String> {
+        self.pv.iter().map(|m| m.to_string()).collect()
+    }
+    pub fn get_score(&self) -> i32 {
+        self.score
+    }
+    pub fn get_mate(&self) -> Option<i32> {
+        moves_to_mate(self.score)
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    // The outcome of a search. The score is always given in centipawns from the perspective of the
+    // side that was to move when the search started, so a positive score means the mover is better.
+    pub best_move: Option<Move>,
+    // the principal variation, that is, the line the search expects both sides to play
+    pub pv: Vec<Move>,
+    pub score: i32,
+    pub depth: u8,
+    // the deepest ply that was reached anywhere in the tree
+    pub seldepth: u8,
+    pub nodes: u64,
+    pub nps: u64,
+    pub time_ms: u64,
+    // the best lines sorted from best to worst, the first line is the same as pv and score
+    pub lines: Vec<PvLine>
+}
+
+impl SearchResult {
+    pub fn new() -> SearchResult {
+        SearchResult { best_move: None, pv: Vec::new(), score: 0, depth: 0, seldepth: 0, nodes: 0, nps: 0, time_ms: 0, lines: Vec::new() }
+    }
+}
+
+impl fmt::Display for SearchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "depth {} seldepth {} score cp {} nodes {} nps {} time {} pv {}",
+            self.depth, self.seldepth, self.score, self.nodes, self.nps, self.time_ms,
+            self.pv.iter().map(|m| m.to_string()).collect::<Vec<String>>().join(" "))
+    }
+}
+
+#[pymethods]
+impl SearchResult {
+    // moves are handed to python in long algebraic notation, e.g. e2e4 or a7a8q
+    pub fn get_best_move(&self) -> Option<String> {
+        self.best_move.map(|m| m.to_string())
+    }
+    pub fn get_pv(&self) -> Vec<String> {
+        self.pv.iter().map(|m| m.to_string()).collect()
+    }
+    pub fn get_score(&self) -> i32 {
+        self.score
+    }
+    pub fn get_depth(&self) -> u8 {
+        self.depth
+    }
+    pub fn get_seldepth(&self) -> u8 {
+        self.seldepth
+    }
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes
+    }
+    pub fn get_nps(&self) -> u64 {
+        self.nps
+    }
+    pub fn get_time_ms(&self) -> u64 {
+        self.time_ms
+    }
+    pub fn get_mate(&self) -> Option<i32> {
+        moves_to_mate(self.score)
+    }
+    pub fn get_lines(&self) -> Vec<PvLine> {
+        self.lines.clone()
+    }
+    pub fn __str__(&self) -> String {
+        format!("{}", self)
+    }
+}
+
+pub struct PvTable {
+    // Triangular principal variation table. Row ply holds the best line found from that ply onwards,
+    // whenever a move improves the score at some ply we copy the line of the ply below behind it.
+    moves: [[Option<Move>; MAX_PLY]; MAX_PLY],
+    length: [usize; MAX_PLY]
+}
+
+impl PvTable {
+    pub fn new() -> PvTable {
+        PvTable { moves: [[None; MAX_PLY]; MAX_PLY], length: [0; MAX_PLY] }
+    }
+    #[inline]
+    pub fn clear_ply(&mut self, ply: usize) {
+        // must be called when entering a node, so that no stale line survives
+        self.length[ply] = 0;
+    }
+    pub fn update(&mut self, ply: usize, new_move: Move) {
+        // store new_move as the best move at ply followed by the line found one ply deeper
+        self.moves[ply][0] = Some(new_move);
+        let child_length = if ply + 1 < MAX_PLY { self.length[ply + 1].min(MAX_PLY - 1) } else { 0 };
+        for i in 0..child_length {
+            self.moves[ply][i + 1] = self.moves[ply + 1][i];
+        }
+        self.length[ply] = child_length + 1;
+    }
+    pub fn line(&self, ply: usize) -> Vec<Move> {
+        self.moves[ply][..self.length[ply]].iter().filter_map(|m| *m).collect()
+    }
+}
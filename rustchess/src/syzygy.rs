@@ -0,0 +1,1225 @@
+use pyo3::prelude::*;
+use pyo3::exceptions::PyIOError;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use crate::chessboard::*;
+use crate::chessboard_helper::*;
+
+// Probing of Syzygy endgame tablebases. Every material combination like KRvK has a WDL table (.rtbw) that
+// stores whether a position is won, drawn or lost and a DTZ table (.rtbz) that stores the number of plies
+// until the next capture or pawn move that keeps the result, so the distance to zeroing. The tables assume
+// that nobody can castle and that there is no en passant capture, captures are always resolved by a small
+// search before the table is looked at.
+//
+// Both kinds of files start with a magic number and a byte of flags, followed by the order in which the
+// pieces are encoded, the sizes of the compressed data and the data itself. A position is turned into an
+// index by mirroring it such that the leading piece is in the a1-d1-d4 triangle (or the leading pawn on
+// the queen side), after which the value is found in blocks that are compressed with recursive pairing and
+// canonical huffman codes. Squares in this module count from a1 to h8 like in the files, which is the
+// index of the board flipped vertically.
+//
+// The tables are only read when they are probed for the first time and then kept in memory.
+
+// the result of a position for the side to move, cursed wins and blessed losses are drawn by the 50 move rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            i32::MIN..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win
+        }
+    }
+    pub fn value(&self) -> i32 {
+        match self {
+            Wdl::Loss => -2,
+            Wdl::BlessedLoss => -1,
+            Wdl::Draw => 0,
+            Wdl::CursedWin => 1,
+            Wdl::Win => 2
+        }
+    }
+    pub fn flip(&self) -> Wdl {
+        Wdl::from_value(-self.value())
+    }
+}
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+// the most pieces a table can have
+const TB_PIECES: usize = 7;
+// ranks root moves, see rank_root_moves
+const MAX_DTZ: i32 = 1 << 18;
+
+// flags of the compressed data
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableKind {
+    Wdl,
+    Dtz
+}
+
+impl TableKind {
+    fn extension(&self) -> &'static str {
+        match self {
+            TableKind::Wdl => "rtbw",
+            TableKind::Dtz => "rtbz"
+        }
+    }
+    fn magic(&self) -> [u8; 4] {
+        match self {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC
+        }
+    }
+}
+
+// the lookup tables that turn a placement of pieces into an index, the same for every table
+struct Encoding {
+    // squares below the a1-h8 diagonal to 0..27
+    map_b1h1h7: [u64; 64],
+    // squares of the a1-d1-d4 triangle to 0..9, the squares on the diagonal last
+    map_a1d1d4: [usize; 64],
+    // the 462 legal placements of two kings where the first is in the triangle
+    map_kk: [[u64; 64]; 10],
+    binomial: [[u64; 64]; TB_PIECES],
+    // squares a2-h7 to 0..47, the leading pawn is the one with the highest value
+    map_pawns: [usize; 64],
+    lead_pawn_idx: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6]
+}
+
+static ENCODING: OnceLock<Encoding> = OnceLock::new();
+
+fn encoding() -> &'static Encoding {
+    ENCODING.get_or_init(Encoding::new)
+}
+
+#[inline]
+fn off_a1h8(square: usize) -> i32 {
+    (square >> 3) as i32 - (square & 7) as i32
+}
+
+impl Encoding {
+    #[allow(clippy::needless_range_loop)]
+    fn new() -> Encoding {
+        let mut map_b1h1h7 = [0; 64];
+        let mut code = 0;
+        for square in 0..64 {
+            if off_a1h8(square) < 0 {
+                map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        let mut map_a1d1d4 = [0; 64];
+        let mut diagonal = Vec::new();
+        let mut code = 0;
+        for square in 0..=27 {
+            if off_a1h8(square) < 0 && square & 7 <= 3 {
+                map_a1d1d4[square] = code;
+                code += 1;
+            }
+            else if off_a1h8(square) == 0 && square & 7 <= 3 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        // when the first king is on the diagonal the second one may not be above it, the placements with both
+        // kings on the diagonal come last
+        let mut map_kk = [[0; 64]; 10];
+        let mut both_on_diagonal = Vec::new();
+        let mut code = 0;
+        for idx in 0..10 {
+            for first in 0..=27 {
+                // b1 is the only square of the triangle that maps to 0
+                if map_a1d1d4[first] != idx || (idx == 0 && first != 1) {
+                    continue
+                }
+                for second in 0..64 {
+                    let distance = king_distance(first, second);
+                    if distance <= 1 || (off_a1h8(first) == 0 && off_a1h8(second) > 0) {
+                        continue
+                    }
+                    if off_a1h8(first) == 0 && off_a1h8(second) == 0 {
+                        both_on_diagonal.push((idx, second));
+                    }
+                    else {
+                        map_kk[idx][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            map_kk[idx][second] = code;
+            code += 1;
+        }
+
+        let mut binomial = [[0; 64]; TB_PIECES];
+        binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..TB_PIECES.min(n + 1) {
+                binomial[k][n] = if k > 0 {binomial[k - 1][n - 1]} else {0} + if k < n {binomial[k][n - 1]} else {0};
+            }
+        }
+
+        // the pawns on the edge and on the lowest rank get the highest values, every leading pawn square has
+        // two squares less left for the other pawns because of the mirroring
+        let mut map_pawns = [0; 64];
+        let mut lead_pawn_idx = [[0; 64]; 6];
+        let mut lead_pawns_size = [[0; 4]; 6];
+        let mut available: usize = 47;
+        for lead_pawns in 1..=5 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..=6 {
+                    let square = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        map_pawns[square] = available;
+                        map_pawns[square ^ 7] = available.saturating_sub(1);
+                        available = available.saturating_sub(2);
+                    }
+                    lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += binomial[lead_pawns - 1][map_pawns[square]];
+                }
+                lead_pawns_size[lead_pawns][file] = idx;
+            }
+        }
+        Encoding { map_b1h1h7, map_a1d1d4, map_kk, binomial, map_pawns, lead_pawn_idx, lead_pawns_size }
+    }
+}
+
+fn king_distance(first: usize, second: usize) -> usize {
+    // the number of king moves between two squares
+    let files = ((first & 7) as i32 - (second & 7) as i32).unsigned_abs();
+    let ranks = ((first >> 3) as i32 - (second >> 3) as i32).unsigned_abs();
+    files.max(ranks) as usize
+}
+
+fn read_u16_le(bytes: &[u8], pos: usize) -> u16 {
+    bytes.get(pos..pos + 2).map_or(0, |b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32_le(bytes: &[u8], pos: usize) -> u32 {
+    bytes.get(pos..pos + 4).map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn read_u32_be(bytes: &[u8], pos: usize) -> u32 {
+    bytes.get(pos..pos + 4).map_or(0, |b| u32::from_be_bytes(b.try_into().unwrap()))
+}
+
+fn read_u64_be(bytes: &[u8], pos: usize) -> u64 {
+    bytes.get(pos..pos + 8).map_or(0, |b| u64::from_be_bytes(b.try_into().unwrap()))
+}
+
+// how the values of one side of one file of a table are compressed, positions in the data are offsets in the file
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    pieces: [u8; TB_PIECES],
+    // the pieces are encoded in groups of equal pieces, the lengths end with a zero
+    group_len: [usize; TB_PIECES + 1],
+    group_idx: [u64; TB_PIECES + 1],
+    block_size: u64,
+    // every span values there is an entry in the sparse index
+    span: u64,
+    sparse_index: usize,
+    sparse_index_size: u64,
+    block_length: usize,
+    block_length_size: u64,
+    num_blocks: u64,
+    // the value of all positions when the single value flag is set
+    min_sym_len: u8,
+    lowest_sym: usize,
+    base64: Vec<u64>,
+    // the number of values a symbol expands into minus one
+    symlen: Vec<u8>,
+    btree: usize,
+    data: usize,
+    // where the values of a dtz table for every result start in the map
+    map_idx: [usize; 4]
+}
+
+impl PairsData {
+    fn left(&self, bytes: &[u8], symbol: usize) -> usize {
+        let pos = self.btree + 3 * symbol;
+        ((bytes[pos + 1] as usize & 0xF) << 8) | bytes[pos] as usize
+    }
+    fn right(&self, bytes: &[u8], symbol: usize) -> usize {
+        let pos = self.btree + 3 * symbol;
+        ((bytes[pos + 2] as usize) << 4) | (bytes[pos + 1] as usize >> 4)
+    }
+    fn set_symlen(&mut self, bytes: &[u8], symbol: usize, visited: &mut [bool]) -> Option<u8> {
+        // a symbol is either a value or a pair of two other symbols
+        visited[symbol] = true;
+        let right = self.right(bytes, symbol);
+        if right == 0xFFF {
+            return Some(0)
+        }
+        let left = self.left(bytes, symbol);
+        if left >= visited.len() || right >= visited.len() {
+            return None
+        }
+        if !visited[left] {
+            self.symlen[left] = self.set_symlen(bytes, left, visited)?;
+        }
+        if !visited[right] {
+            self.symlen[right] = self.set_symlen(bytes, right, visited)?;
+        }
+        Some(self.symlen[left].wrapping_add(self.symlen[right]).wrapping_add(1))
+    }
+    fn set_sizes(&mut self, bytes: &[u8], mut pos: usize) -> Option<usize> {
+        self.flags = *bytes.get(pos)?;
+        pos += 1;
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            self.min_sym_len = *bytes.get(pos)?;
+            return Some(pos + 1)
+        }
+        let groups = self.group_len.iter().position(|len| *len == 0)?;
+        let table_size = self.group_idx[groups];
+        self.block_size = 1 << bytes.get(pos)?;
+        self.span = 1 << bytes.get(pos + 1)?;
+        self.sparse_index_size = table_size.div_ceil(self.span);
+        let padding = *bytes.get(pos + 2)? as u64;
+        self.num_blocks = read_u32_le(bytes, pos + 3) as u64;
+        // padded so that the sparse index never points past the block lengths
+        self.block_length_size = self.num_blocks + padding;
+        let max_sym_len = *bytes.get(pos + 7)?;
+        self.min_sym_len = *bytes.get(pos + 8)?;
+        pos += 9;
+        if max_sym_len < self.min_sym_len {
+            return None
+        }
+        self.lowest_sym = pos;
+
+        // The codes are canonical huffman codes where longer codes have lower values. base64[i] is the lowest code
+        // of length min_sym_len + i padded to 64 bits, so the length of a code is found by comparing the next 64
+        // bits with these values.
+        let lengths = (max_sym_len - self.min_sym_len) as usize + 1;
+        let mut base64 = vec![0u64; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = read_u16_le(bytes, self.lowest_sym + 2 * i) as u64;
+            let next_lowest = read_u16_le(bytes, self.lowest_sym + 2 * (i + 1)) as u64;
+            base64[i] = base64[i + 1].wrapping_add(lowest).wrapping_sub(next_lowest) / 2;
+        }
+        for (i, base) in base64.iter_mut().enumerate() {
+            *base = 64u32.checked_sub(i as u32 + self.min_sym_len as u32).and_then(|shift| base.checked_shl(shift)).unwrap_or(0);
+        }
+        self.base64 = base64;
+        pos += lengths * 2;
+
+        let symbols = read_u16_le(bytes, pos) as usize;
+        pos += 2;
+        self.btree = pos;
+        if bytes.len() < pos + 3 * symbols {
+            return None
+        }
+        self.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols {
+            if !visited[symbol] {
+                self.symlen[symbol] = self.set_symlen(bytes, symbol, &mut visited)?;
+            }
+        }
+        Some(pos + 3 * symbols + (symbols & 1))
+    }
+    fn decompress(&self, bytes: &[u8], idx: u64) -> Option<i32> {
+        // the value of the position with the given index
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            return Some(self.min_sym_len as i32)
+        }
+        // The sparse index tells for every span values in which block the value in the middle of the span is and
+        // where it is in the block, from there we walk to the block with our value. A block holds one more value
+        // than its block length says.
+        let k = idx / self.span;
+        if k >= self.sparse_index_size {
+            return None
+        }
+        let entry = self.sparse_index + 6 * k as usize;
+        let mut block = read_u32_le(bytes, entry) as u64;
+        let mut offset = read_u16_le(bytes, entry + 4) as i64 + (idx % self.span) as i64 - (self.span / 2) as i64;
+        let block_length = |block: u64| read_u16_le(bytes, self.block_length + 2 * block as usize) as i64;
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block) + 1;
+        }
+        while offset > block_length(block) {
+            offset -= block_length(block) + 1;
+            block += 1;
+            if block >= self.block_length_size {
+                return None
+            }
+        }
+
+        // read symbols from the start of the block until we reach the one that contains our value
+        let mut pos = self.data + (block * self.block_size) as usize;
+        let mut buffer = read_u64_be(bytes, pos);
+        pos += 8;
+        let mut buffer_size = 64;
+        let mut symbol;
+        loop {
+            let mut len = 0;
+            while len + 1 < self.base64.len() && buffer < self.base64[len] {
+                len += 1;
+            }
+            let shift = 64u32.saturating_sub(len as u32 + self.min_sym_len as u32);
+            symbol = (buffer.wrapping_sub(self.base64[len]).checked_shr(shift).unwrap_or(0) as u16)
+                .wrapping_add(read_u16_le(bytes, self.lowest_sym + 2 * len)) as usize;
+            let expands = *self.symlen.get(symbol)? as i64 + 1;
+            if offset < expands {
+                break
+            }
+            offset -= expands;
+            let len = len as u32 + self.min_sym_len as u32;
+            buffer = buffer.checked_shl(len).unwrap_or(0);
+            buffer_size -= len as i32;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (read_u32_be(bytes, pos) as u64) << (64 - buffer_size);
+                pos += 4;
+            }
+        }
+        // the symbol expands into a pair of symbols that lie next to each other, go down until we are at a value
+        while self.symlen[symbol] != 0 {
+            let left = self.left(bytes, symbol);
+            let left_expands = *self.symlen.get(left)? as i64 + 1;
+            if offset < left_expands {
+                symbol = left;
+            }
+            else {
+                offset -= left_expands;
+                symbol = self.right(bytes, symbol);
+            }
+            if symbol >= self.symlen.len() {
+                return None
+            }
+        }
+        Some(self.left(bytes, symbol) as i32)
+    }
+}
+
+struct Table {
+    bytes: Vec<u8>,
+    kind: TableKind,
+    piece_count: usize,
+    has_pawns: bool,
+    // whether a side has a piece other than the king that it has only one of
+    has_unique_pieces: bool,
+    // the pawns of the leading color first, that is the color with fewer pawns
+    pawn_count: [usize; 2],
+    // both sides have the same pieces, then only white to move is stored
+    symmetric: bool,
+    sides: usize,
+    // per side and per file of the leading pawn, without pawns there is only one file
+    items: Vec<PairsData>,
+    // start of the values of the dtz tables that are stored in a map
+    map: usize
+}
+
+fn parse_material(name: &str) -> Option<(Vec<PieceType>, Vec<PieceType>)> {
+    let (white, black) = name.split_once('v')?;
+    let side = |pieces: &str| -> Option<Vec<PieceType>> {
+        if !pieces.starts_with('K') {
+            return None
+        }
+        pieces.chars().map(|piece| match piece {
+            'K' | 'Q' | 'R' | 'B' | 'N' | 'P' => Some(PieceType::from_char(piece.to_ascii_lowercase())),
+            _ => None
+        }).collect()
+    };
+    let white = side(white)?;
+    let black = side(black)?;
+    if white.len() + black.len() > TB_PIECES || white.iter().chain(black.iter()).filter(|piece| **piece == PieceType::King).count() != 2 {
+        return None
+    }
+    Some((white, black))
+}
+
+impl Table {
+    fn load(path: &PathBuf, kind: TableKind, name: &str) -> Option<Table> {
+        let bytes = fs::read(path).ok()?;
+        // every table file is a multiple of 64 bytes plus 16
+        if bytes.len() % 64 != 16 || bytes[0..4] != kind.magic() {
+            return None
+        }
+        let (white, black) = parse_material(name)?;
+        let count = |pieces: &Vec<PieceType>, piece_type: PieceType| pieces.iter().filter(|piece| **piece == piece_type).count();
+        let has_unique_pieces = [&white, &black].iter().any(|pieces| {
+            [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen].iter()
+                .any(|piece_type| count(pieces, *piece_type) == 1)
+        });
+        // the leading color is the one with fewer pawns, but with at least one
+        let white_pawns = count(&white, PieceType::Pawn);
+        let black_pawns = count(&black, PieceType::Pawn);
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let pawn_count = if white_leads {[white_pawns, black_pawns]} else {[black_pawns, white_pawns]};
+        let mut table = Table {
+            bytes: Vec::new(),
+            kind,
+            piece_count: white.len() + black.len(),
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces,
+            pawn_count,
+            symmetric: white == black,
+            sides: if kind == TableKind::Wdl && white != black {2} else {1},
+            items: Vec::new(),
+            map: 0
+        };
+        table.parse(&bytes)?;
+        table.bytes = bytes;
+        Some(table)
+    }
+
+    fn item(&self, stm: usize, file: usize) -> &PairsData {
+        &self.items[(stm % self.sides) * 4 + if self.has_pawns {file} else {0}]
+    }
+
+    fn parse(&mut self, bytes: &[u8]) -> Option<()> {
+        let flags = *bytes.get(4)?;
+        if (flags & 2 != 0) != self.has_pawns {
+            return None
+        }
+        let mut pos = 5;
+        let max_file = if self.has_pawns {3} else {0};
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        self.items = vec![PairsData::default(); self.sides * 4];
+        for file in 0..=max_file {
+            // the order in which the groups of pieces are encoded, per side
+            let first = *bytes.get(pos)?;
+            let second = if both_pawns {*bytes.get(pos + 1)?} else {0xFF};
+            let order = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+            pos += 1 + both_pawns as usize;
+            for k in 0..self.piece_count {
+                let piece = *bytes.get(pos)?;
+                for side in 0..self.sides {
+                    self.items[side * 4 + file].pieces[k] = if side == 0 {piece & 0xF} else {piece >> 4};
+                }
+                pos += 1;
+            }
+            for (side, order) in order.into_iter().enumerate().take(self.sides) {
+                let mut item = self.items[side * 4 + file].clone();
+                self.set_groups(&mut item, order, file);
+                self.items[side * 4 + file] = item;
+            }
+        }
+        pos += pos & 1;
+        for file in 0..=max_file {
+            for side in 0..self.sides {
+                pos = self.items[side * 4 + file].set_sizes(bytes, pos)?;
+            }
+        }
+        if self.kind == TableKind::Dtz {
+            pos = self.set_dtz_map(bytes, pos, max_file)?;
+        }
+        for file in 0..=max_file {
+            for side in 0..self.sides {
+                let item = &mut self.items[side * 4 + file];
+                item.sparse_index = pos;
+                pos += 6 * item.sparse_index_size as usize;
+            }
+        }
+        for file in 0..=max_file {
+            for side in 0..self.sides {
+                let item = &mut self.items[side * 4 + file];
+                item.block_length = pos;
+                pos += 2 * item.block_length_size as usize;
+            }
+        }
+        for file in 0..=max_file {
+            for side in 0..self.sides {
+                let item = &mut self.items[side * 4 + file];
+                // the compressed blocks are aligned to 64 bytes
+                pos = (pos + 0x3F) & !0x3F;
+                item.data = pos;
+                pos += (item.num_blocks * item.block_size) as usize;
+            }
+        }
+        if pos > bytes.len() {
+            return None
+        }
+        Some(())
+    }
+
+    fn set_groups(&self, item: &mut PairsData, order: [u8; 2], file: usize) {
+        // Pieces are encoded in groups, the leading pieces or pawns first, then the other pawns and then the other
+        // pieces with equal pieces together. The order of the groups in the index is stored in the file, the
+        // index of a group is multiplied by the number of placements of all groups that come after it.
+        let encoding = encoding();
+        let mut n = 0;
+        let mut first_len: i32 = if self.has_pawns {0} else if self.has_unique_pieces {3} else {2};
+        item.group_len[0] = 1;
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || item.pieces[i] == item.pieces[i - 1] {
+                item.group_len[n] += 1;
+            }
+            else {
+                n += 1;
+                item.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        item.group_len[n] = 0;
+
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = if both_pawns {2} else {1};
+        let mut free_squares = 64 - item.group_len[0] - if both_pawns {item.group_len[1]} else {0};
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                item.group_idx[0] = idx;
+                idx *= if self.has_pawns {
+                    encoding.lead_pawns_size[item.group_len[0]][file]
+                }
+                else if self.has_unique_pieces {31332} else {462};
+            }
+            else if k == order[1] as usize {
+                item.group_idx[1] = idx;
+                idx *= encoding.binomial[item.group_len[1]][48 - item.group_len[0]];
+            }
+            else {
+                item.group_idx[next] = idx;
+                idx *= encoding.binomial[item.group_len[next]][free_squares];
+                free_squares -= item.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        item.group_idx[n] = idx;
+    }
+
+    fn set_dtz_map(&mut self, bytes: &[u8], mut pos: usize, max_file: usize) -> Option<usize> {
+        // dtz values can be stored as an index into a map, with a part of the map per result
+        self.map = pos;
+        for file in 0..=max_file {
+            let flags = self.items[file].flags;
+            if flags & FLAG_MAPPED == 0 {
+                continue
+            }
+            if flags & FLAG_WIDE != 0 {
+                pos += pos & 1;
+                for i in 0..4 {
+                    self.items[file].map_idx[i] = (pos - self.map) / 2 + 1;
+                    pos += 2 * read_u16_le(bytes, pos) as usize + 2;
+                }
+            }
+            else {
+                for i in 0..4 {
+                    self.items[file].map_idx[i] = pos - self.map + 1;
+                    pos += *bytes.get(pos)? as usize + 1;
+                }
+            }
+        }
+        Some(pos + (pos & 1))
+    }
+
+    fn map_score(&self, file: usize, value: i32, wdl: Wdl) -> i32 {
+        match self.kind {
+            TableKind::Wdl => value - 2,
+            TableKind::Dtz => {
+                let item = self.item(0, file);
+                let mut value = value;
+                if item.flags & FLAG_MAPPED != 0 {
+                    let map_idx = item.map_idx[[1, 3, 0, 2, 0][(wdl.value() + 2) as usize]] + value as usize;
+                    value = if item.flags & FLAG_WIDE != 0 {
+                        read_u16_le(&self.bytes, self.map + 2 * map_idx) as i32
+                    }
+                    else {
+                        self.bytes.get(self.map + map_idx).copied().unwrap_or(0) as i32
+                    };
+                }
+                // the values are stored in moves or in plies, we always want plies
+                if (wdl == Wdl::Win && item.flags & FLAG_WIN_PLIES == 0) || (wdl == Wdl::Loss && item.flags & FLAG_LOSS_PLIES == 0)
+                    || wdl == Wdl::CursedWin || wdl == Wdl::BlessedLoss {
+                    value *= 2;
+                }
+                value + 1
+            }
+        }
+    }
+
+    fn probe(&self, position: &Position, black_stronger: bool, wdl: Wdl) -> TableProbe {
+        let encoding = encoding();
+        // Tables are made for white being the stronger side and for symmetric material only with white to move,
+        // otherwise we swap the colors and flip the board.
+        let black_to_move = matches!(position.to_move, ToMove::Black);
+        let flip = black_stronger || (self.symmetric && black_to_move);
+        let flip_color = if flip {8} else {0};
+        let flip_squares = if flip {56} else {0};
+        let stm = (flip ^ black_to_move) as usize;
+
+        let board_pieces = board_pieces(position);
+        let mut squares = [0usize; TB_PIECES];
+        let mut pieces = [0u8; TB_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut lead_pawn_code = 0;
+        let mut file = 0;
+        if self.has_pawns {
+            // the first piece of every file is a pawn of the leading color
+            lead_pawn_code = self.item(0, 0).pieces[0] ^ flip_color;
+            for (square, piece) in board_pieces.iter() {
+                if *piece == lead_pawn_code {
+                    squares[size] = square ^ flip_squares;
+                    pieces[size] = lead_pawn_code ^ flip_color;
+                    size += 1;
+                }
+            }
+            lead_pawns = size;
+            let lead = (0..lead_pawns).max_by_key(|i| encoding.map_pawns[squares[*i]]).unwrap_or(0);
+            squares.swap(0, lead);
+            let lead_file = squares[0] & 7;
+            file = lead_file.min(7 - lead_file);
+        }
+        // dtz tables only store one side to move, the other side has to be found with a search
+        if self.kind == TableKind::Dtz {
+            let flags = self.item(stm, file).flags;
+            let both_sides_stored = self.symmetric && !self.has_pawns;
+            if (flags & FLAG_STM) as usize != stm && !both_sides_stored {
+                return TableProbe::ChangeStm
+            }
+        }
+        for (square, piece) in board_pieces.iter() {
+            if self.has_pawns && *piece == lead_pawn_code {
+                continue
+            }
+            if size == TB_PIECES {
+                return TableProbe::Fail
+            }
+            squares[size] = square ^ flip_squares;
+            pieces[size] = piece ^ flip_color;
+            size += 1;
+        }
+        if size != self.piece_count {
+            return TableProbe::Fail
+        }
+        let item = self.item(stm, file);
+
+        // put the pieces in the order of the table
+        for i in lead_pawns..size.saturating_sub(1) {
+            for j in i + 1..size {
+                if item.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break
+                }
+            }
+        }
+        // mirror such that the leading piece is on the queen side
+        if squares[0] & 7 > 3 {
+            for square in squares[..size].iter_mut() {
+                *square ^= 7;
+            }
+        }
+
+        let mut idx;
+        if self.has_pawns {
+            idx = encoding.lead_pawn_idx[lead_pawns][squares[0]];
+            squares[1..lead_pawns].sort_by_key(|square| encoding.map_pawns[*square]);
+            for (i, square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                idx += encoding.binomial[i][encoding.map_pawns[*square]];
+            }
+        }
+        else {
+            // without pawns we also mirror the leading piece into the lower half and below the a1-h8 diagonal
+            if squares[0] >> 3 > 3 {
+                for square in squares[..size].iter_mut() {
+                    *square ^= 56;
+                }
+            }
+            for i in 0..item.group_len[0] {
+                let off = off_a1h8(squares[i]);
+                if off == 0 {
+                    continue
+                }
+                if off > 0 {
+                    for square in squares[i..size].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break
+            }
+            idx = if self.has_unique_pieces {
+                // the first three pieces are encoded together
+                let adjust1 = (squares[1] > squares[0]) as u64;
+                let adjust2 = (squares[2] > squares[0]) as u64 + (squares[2] > squares[1]) as u64;
+                let rank = |square: usize| (square >> 3) as u64;
+                if off_a1h8(squares[0]) != 0 {
+                    (encoding.map_a1d1d4[squares[0]] as u64 * 63 + squares[1] as u64 - adjust1) * 62 + squares[2] as u64 - adjust2
+                }
+                else if off_a1h8(squares[1]) != 0 {
+                    (6 * 63 + rank(squares[0]) * 28 + encoding.map_b1h1h7[squares[1]]) * 62 + squares[2] as u64 - adjust2
+                }
+                else if off_a1h8(squares[2]) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62 + rank(squares[0]) * 7 * 28 + (rank(squares[1]) - adjust1) * 28
+                        + encoding.map_b1h1h7[squares[2]]
+                }
+                else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank(squares[0]) * 7 * 6 + (rank(squares[1]) - adjust1) * 6
+                        + (rank(squares[2]) - adjust2)
+                }
+            }
+            else {
+                encoding.map_kk[encoding.map_a1d1d4[squares[0]]][squares[1]]
+            };
+        }
+
+        // the other groups are encoded as combinations of the squares that are still free
+        idx *= item.group_idx[0];
+        let mut group_start = item.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while item.group_len[next] != 0 {
+            let len = item.group_len[next];
+            if group_start + len > size {
+                return TableProbe::Fail
+            }
+            squares[group_start..group_start + len].sort();
+            let mut n = 0;
+            for i in 0..len {
+                let square = squares[group_start + i];
+                let adjust = squares[..group_start].iter().filter(|other| square > **other).count();
+                let Some(free) = square.checked_sub(adjust + if remaining_pawns {8} else {0}) else {
+                    return TableProbe::Fail
+                };
+                n += encoding.binomial[i + 1][free];
+            }
+            remaining_pawns = false;
+            idx += n * item.group_idx[next];
+            group_start += len;
+            next += 1;
+        }
+        match item.decompress(&self.bytes, idx) {
+            Some(value) => TableProbe::Value(self.map_score(file, value, wdl)),
+            None => TableProbe::Fail
+        }
+    }
+}
+
+enum TableProbe {
+    Value(i32),
+    // the dtz table stores the other side to move
+    ChangeStm,
+    Fail
+}
+
+fn board_pieces(position: &Position) -> Vec<(usize, u8)> {
+    // every piece as the square counted from a1 and the piece code of the files, 1 to 6 for white pawn to
+    // king and 9 to 14 for black
+    let mut pieces = Vec::new();
+    for (color, side) in [(0, &position.white_pieces), (8, &position.black_pieces)] {
+        let bitboards = [side.get_bb_pawns(), side.get_bb_knights(), side.get_bb_bishops(), side.get_bb_rooks(),
+            side.get_bb_queens(), side.get_bb_king()];
+        for (code, mut bitboard) in bitboards.into_iter().enumerate() {
+            while bitboard != 0 {
+                let index = bitboard.trailing_zeros() as usize;
+                bitboard &= bitboard - 1;
+                pieces.push((index ^ 56, code as u8 + 1 + color));
+            }
+        }
+    }
+    pieces
+}
+
+fn material_name(pieces: &Pieces) -> String {
+    // the pieces of one side the way they are written in the file names
+    let mut name = String::from("K");
+    for (letter, bitboard) in [('Q', pieces.get_bb_queens()), ('R', pieces.get_bb_rooks()), ('B', pieces.get_bb_bishops()),
+        ('N', pieces.get_bb_knights()), ('P', pieces.get_bb_pawns())] {
+        for _ in 0..bitboard.count_ones() {
+            name.push(letter);
+        }
+    }
+    name
+}
+
+fn piece_count(position: &Position) -> usize {
+    [&position.white_pieces, &position.black_pieces].iter().map(|pieces| {
+        (pieces.get_bb_pawns() | pieces.get_bb_knights() | pieces.get_bb_bishops() | pieces.get_bb_rooks()
+            | pieces.get_bb_queens() | pieces.get_bb_king()).count_ones() as usize
+    }).sum()
+}
+
+fn is_capture(position: &Position, new_move: &Move) -> bool {
+    let (friendly, enemy) = match position.to_move {
+        ToMove::White => (&position.white_pieces, &position.black_pieces),
+        ToMove::Black => (&position.black_pieces, &position.white_pieces)
+    };
+    enemy.detect_piece_type(new_move.to) != PieceType::EmptySquare
+        || (position.es_target == Some(new_move.to) && friendly.detect_piece_type(new_move.from) == PieceType::Pawn)
+}
+
+fn is_pawn_move(position: &Position, new_move: &Move) -> bool {
+    let friendly = match position.to_move {
+        ToMove::White => &position.white_pieces,
+        ToMove::Black => &position.black_pieces
+    };
+    friendly.detect_piece_type(new_move.from) == PieceType::Pawn
+}
+
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    // the dtz of a position where the best move is a capture or a pawn move
+    match wdl {
+        Wdl::Loss => -1,
+        Wdl::BlessedLoss => -101,
+        Wdl::Draw => 0,
+        Wdl::CursedWin => 101,
+        Wdl::Win => 1
+    }
+}
+
+// the files of one material combination, each one is read when it is needed for the first time
+struct TableFiles {
+    wdl_path: Option<PathBuf>,
+    dtz_path: Option<PathBuf>,
+    wdl: OnceLock<Option<Table>>,
+    dtz: OnceLock<Option<Table>>
+}
+
+#[pyclass]
+pub struct Tablebase {
+    // by the name of the material like KRvK
+    tables: HashMap<String, TableFiles>,
+    // the most pieces of any table we have, positions with more pieces are never probed
+    max_pieces: usize
+}
+
+impl Tablebase {
+    pub fn open(paths: &str) -> Result<Tablebase, Error> {
+        // reads the names of the tables in the given directories, separated like the PATH variable
+        let mut tables: HashMap<String, TableFiles> = HashMap::new();
+        for directory in env::split_paths(paths) {
+            for entry in fs::read_dir(&directory)? {
+                let path = entry?.path();
+                let (Some(name), Some(extension)) = (path.file_stem().and_then(|name| name.to_str()),
+                    path.extension().and_then(|extension| extension.to_str())) else {
+                    continue
+                };
+                let Some(kind) = [TableKind::Wdl, TableKind::Dtz].into_iter().find(|kind| kind.extension() == extension) else {
+                    continue
+                };
+                if parse_material(name).is_none() {
+                    continue
+                }
+                let files = tables.entry(name.to_string()).or_insert_with(|| TableFiles {
+                    wdl_path: None, dtz_path: None, wdl: OnceLock::new(), dtz: OnceLock::new()
+                });
+                match kind {
+                    TableKind::Wdl => files.wdl_path = Some(path.clone()),
+                    TableKind::Dtz => files.dtz_path = Some(path.clone())
+                }
+            }
+        }
+        if tables.is_empty() {
+            return Err(Error::new(ErrorKind::NotFound, "no syzygy tables found"))
+        }
+        // without the wdl table the dtz table is of no use
+        tables.retain(|_, files| files.wdl_path.is_some());
+        let max_pieces = tables.keys().map(|name| name.len() - 1).max().unwrap_or(0);
+        Ok(Tablebase { tables, max_pieces })
+    }
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+    pub fn can_probe(&self, position: &Position) -> bool {
+        // the tables know nothing about castling
+        !(position.white_kingside_castle || position.white_queenside_castle || position.black_kingside_castle
+            || position.black_queenside_castle) && piece_count(position) <= self.max_pieces
+    }
+
+    fn probe_table(&self, kind: TableKind, position: &Position, wdl: Wdl) -> TableProbe {
+        if piece_count(position) == 2 {
+            return TableProbe::Value(0)
+        }
+        let white = material_name(&position.white_pieces);
+        let black = material_name(&position.black_pieces);
+        let (name, files, black_stronger) = match self.tables.get_key_value(&format!("{}v{}", white, black)) {
+            Some((name, files)) => (name, files, false),
+            None => match self.tables.get_key_value(&format!("{}v{}", black, white)) {
+                Some((name, files)) => (name, files, true),
+                None => return TableProbe::Fail
+            }
+        };
+        let (path, table) = match kind {
+            TableKind::Wdl => (&files.wdl_path, &files.wdl),
+            TableKind::Dtz => (&files.dtz_path, &files.dtz)
+        };
+        let table = table.get_or_init(|| path.as_ref().and_then(|path| Table::load(path, kind, name)));
+        match table {
+            Some(table) => table.probe(position, black_stronger, wdl),
+            None => TableProbe::Fail
+        }
+    }
+
+    fn search(&self, chessboard: &mut Chessboard, check_zeroing_moves: bool) -> Option<(Wdl, bool)> {
+        // Captures are not in the tables, so they are searched first. Returns the result and whether the best
+        // move is a capture or with check_zeroing_moves also a pawn move.
+        let position = *chessboard.get_position();
+        let moves = chessboard.all_moves();
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for new_move in moves.iter() {
+            let zeroing = is_capture(&position, new_move) || (check_zeroing_moves && is_pawn_move(&position, new_move));
+            if !zeroing {
+                continue
+            }
+            searched += 1;
+            chessboard.move_piece(new_move).unwrap();
+            let result = self.search(chessboard, false);
+            chessboard.undo();
+            let value = result?.0.flip();
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true))
+                }
+            }
+        }
+        // when every legal move was searched the table is not needed, it could even be wrong with en passant
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = if all_searched {
+            best
+        }
+        else {
+            match self.probe_table(TableKind::Wdl, &position, Wdl::Draw) {
+                TableProbe::Value(value) => Wdl::from_value(value),
+                _ => return None
+            }
+        };
+        if best >= value {
+            return Some((best, best > Wdl::Draw || all_searched))
+        }
+        Some((value, false))
+    }
+
+    fn dtz(&self, chessboard: &mut Chessboard) -> Option<i32> {
+        let (wdl, zeroing) = self.search(chessboard, true)?;
+        // draws are not stored in the dtz tables
+        if wdl == Wdl::Draw {
+            return Some(0)
+        }
+        if zeroing {
+            return Some(dtz_before_zeroing(wdl))
+        }
+        let position = *chessboard.get_position();
+        match self.probe_table(TableKind::Dtz, &position, wdl) {
+            TableProbe::Value(dtz) => {
+                let rule50 = if wdl == Wdl::CursedWin || wdl == Wdl::BlessedLoss {100} else {0};
+                return Some((dtz + rule50) * wdl.value().signum())
+            }
+            TableProbe::Fail => return None,
+            TableProbe::ChangeStm => ()
+        }
+        // the table has the other side to move, so we look at our moves and take the best one
+        let mut min_dtz = i32::MAX;
+        for new_move in chessboard.all_moves() {
+            let zeroing = is_capture(&position, &new_move) || is_pawn_move(&position, &new_move);
+            chessboard.move_piece(&new_move).unwrap();
+            // for a zeroing move we want the dtz before it, which only needs the result after it
+            let result = if zeroing {
+                self.search(chessboard, false).map(|(wdl, _)| -dtz_before_zeroing(wdl))
+            }
+            else {
+                self.dtz(chessboard).map(|dtz| -dtz)
+            };
+            let mates = result == Some(1) && chessboard.is_in_check() && chessboard.all_moves().is_empty();
+            chessboard.undo();
+            let mut dtz = result?;
+            if mates {
+                min_dtz = 1;
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == wdl.value().signum() {
+                min_dtz = dtz;
+            }
+        }
+        // without legal moves we are mated
+        Some(if min_dtz == i32::MAX {-1} else {min_dtz})
+    }
+
+    pub fn probe_wdl(&self, chessboard: &mut Chessboard) -> Option<Wdl> {
+        // the result of the position for the side to move, None when it is not in the tables
+        if !self.can_probe(chessboard.get_position()) {
+            return None
+        }
+        self.search(chessboard, false).map(|(wdl, _)| wdl)
+    }
+    pub fn probe_dtz(&self, chessboard: &mut Chessboard) -> Option<i32> {
+        // the plies to the next capture or pawn move with perfect play, positive when the side to move wins,
+        // negative when it loses and zero for draws, wins and losses that are cursed by the 50 move rule are
+        // 100 further away
+        if !self.can_probe(chessboard.get_position()) {
+            return None
+        }
+        self.dtz(chessboard)
+    }
+    pub fn rank_root_moves(&self, chessboard: &mut Chessboard) -> Option<Vec<(Move, i32, i32)>> {
+        // Every legal move with its rank and the dtz after it counted from the current position. Moves that win
+        // within the 50 move rule all get the same rank, so that the search can choose the quickest mate among
+        // them, after that the moves that win or lose in the fewest plies are ranked higher.
+        if !self.can_probe(chessboard.get_position()) {
+            return None
+        }
+        let halfmove_clock = chessboard.get_position().halfmove_clock as i32;
+        let mut ranked = Vec::new();
+        for new_move in chessboard.all_moves() {
+            chessboard.move_piece(&new_move).unwrap();
+            let result = if chessboard.get_position().halfmove_clock == 0 {
+                self.search(chessboard, false).map(|(wdl, _)| dtz_before_zeroing(wdl.flip()))
+            }
+            else {
+                self.dtz(chessboard).map(|dtz| -dtz - dtz.signum())
+            };
+            let mates = result == Some(2) && chessboard.is_in_check() && chessboard.all_moves().is_empty();
+            chessboard.undo();
+            let dtz = if mates {1} else {result?};
+            let rank = if dtz > 0 {
+                if dtz + halfmove_clock <= 99 {MAX_DTZ} else {MAX_DTZ - (dtz + halfmove_clock)}
+            }
+            else if dtz < 0 {
+                if -dtz * 2 + halfmove_clock < 100 {-MAX_DTZ} else {-MAX_DTZ + (-dtz + halfmove_clock)}
+            }
+            else {0};
+            ranked.push((new_move, rank, dtz));
+        }
+        Some(ranked)
+    }
+    pub fn best_move(&self, chessboard: &mut Chessboard) -> Option<Move> {
+        // the move with the best rank, of equally ranked moves the one that wins fastest or loses slowest
+        let ranked = self.rank_root_moves(chessboard)?;
+        ranked.iter().max_by_key(|(_, rank, dtz)| (*rank, -*dtz)).map(|(new_move, _, _)| *new_move)
+    }
+}
+
+#[pymethods]
+impl Tablebase {
+    #[staticmethod]
+    pub fn load(paths: &str) -> PyResult<Tablebase> {
+        Tablebase::open(paths).map_err(|err| PyIOError::new_err(err.to_string()))
+    }
+    pub fn get_max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+    pub fn get_wdl(&self, chessboard: &mut Chessboard) -> Option<i32> {
+        // -2 loss, -1 blessed loss, 0 draw, 1 cursed win and 2 win
+        self.probe_wdl(chessboard).map(|wdl| wdl.value())
+    }
+    pub fn get_dtz(&self, chessboard: &mut Chessboard) -> Option<i32> {
+        self.probe_dtz(chessboard)
+    }
+    pub fn get_root_moves(&self, chessboard: &mut Chessboard) -> Option<Vec<(String, i32)>> {
+        // the legal moves with their dtz, best first
+        let mut ranked = self.rank_root_moves(chessboard)?;
+        ranked.sort_by_key(|(_, rank, dtz)| (-*rank, *dtz));
+        Some(ranked.iter().map(|(new_move, _, dtz)| (new_move.to_string(), *dtz)).collect())
+    }
+    pub fn get_best_move(&self, chessboard: &mut Chessboard) -> Option<String> {
+        self.best_move(chessboard).map(|best_move| best_move.to_string())
+    }
+    pub fn __len__(&self) -> usize {
+        self.tables.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use super::*;
+    use crate::test_helper::*;
+
+    // KQvK, KRvK and KPvK from the standard 3-4-5 piece Syzygy set, both .rtbw and .rtbz
+    const TABLES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/syzygy");
+
+    fn tablebase() -> Tablebase {
+        for name in ["KQvK", "KRvK", "KPvK"] {
+            for extension in ["rtbw", "rtbz"] {
+                let filepath = format!("{}/{}.{}", TABLES, name, extension);
+                assert!(Path::new(&filepath).is_file(), "{} is missing, see {}/README.md", filepath, TABLES);
+            }
+        }
+        Tablebase::open(TABLES).expect("the syzygy tables can be read")
+    }
+
+    #[test]
+    #[ignore = "needs the KQvK, KRvK and KPvK tables in testdata/syzygy"]
+    fn wdl_of_known_positions() {
+        let tablebase = tablebase();
+        let positions = [
+            ("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", Wdl::Win),
+            ("4k3/8/8/8/8/8/8/3QK3 b - - 0 1", Wdl::Loss),
+            ("8/8/8/8/8/2k5/8/KR6 b - - 0 1", Wdl::Loss),
+            // the king takes the rook
+            ("8/8/8/8/8/8/k7/1R5K b - - 0 1", Wdl::Draw),
+            ("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", Wdl::Win),
+            ("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1", Wdl::Loss),
+            // stalemate
+            ("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1", Wdl::Draw),
+            // the black king reaches the corner in front of the rook pawn
+            ("k7/8/8/8/8/8/P7/7K w - - 0 1", Wdl::Draw)
+        ];
+        for (fen, wdl) in positions {
+            assert_eq!(tablebase.probe_wdl(&mut chessboard(fen)), Some(wdl), "{}", fen);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs the KQvK, KRvK and KPvK tables in testdata/syzygy"]
+    fn dtz_of_known_positions() {
+        let tablebase = tablebase();
+        let positions = [
+            // Qa8 mates
+            ("7k/8/6K1/8/8/8/8/Q7 w - - 0 1", 1),
+            // Ra8 mates
+            ("7k/8/6K1/8/8/8/8/R7 w - - 0 1", 1),
+            // the pawn move zeroes
+            ("8/8/8/8/8/k7/4P3/4K3 w - - 0 1", 1),
+            ("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1", 0),
+            ("k7/8/8/8/8/8/P7/7K w - - 0 1", 0)
+        ];
+        for (fen, dtz) in positions {
+            assert_eq!(tablebase.probe_dtz(&mut chessboard(fen)), Some(dtz), "{}", fen);
+        }
+        // without pawns the dtz is the distance to mate, at most mate in 10 in KQvK and mate in 16 in KRvK.
+        // The dtz tables may round up by one ply
+        for (fen, longest) in [("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", 20), ("8/8/8/8/8/2k5/8/KR6 w - - 0 1", 32)] {
+            let dtz = tablebase.probe_dtz(&mut chessboard(fen)).unwrap();
+            assert!(dtz > 0 && dtz <= longest, "{} has dtz {}", fen, dtz);
+        }
+    }
+
+    #[test]
+    fn missing_tables_are_an_error() {
+        let directory = std::env::temp_dir().join("rustchess_no_syzygy_tables");
+        fs::create_dir_all(&directory).unwrap();
+        assert!(Tablebase::open(directory.to_str().unwrap()).is_err());
+        assert!(Tablebase::open("no/such/directory").is_err());
+    }
+}
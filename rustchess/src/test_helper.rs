@@ -0,0 +1,20 @@
+use std::sync::OnceLock;
+use crate::chessboard::Chessboard;
+
+// Shared setup for the unit tests. The lookup tables are read from the working directory, which is the
+// same for every thread of the process, so they are loaded once from the root of the repository and
+// every test gets a copy of that board.
+
+static EMPTY_BOARD: OnceLock<Chessboard> = OnceLock::new();
+
+pub fn chessboard(fen: &str) -> Chessboard {
+    let mut chessboard = EMPTY_BOARD.get_or_init(|| {
+        std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/..")).expect("couldn't find the lookup tables");
+        Chessboard::new()
+    }).clone();
+    chessboard.load_fen(fen.to_string());
+    chessboard
+}
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+pub const KIWIPETE_FEN: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
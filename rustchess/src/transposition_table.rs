@@ -0,0 +1,193 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use crate::chessboard_helper::*;
+use crate::search_helper::*;
+
+// The transposition table remembers the result of every searched node by the zobrist hash of its position.
+// It is shared by all search threads without any locking. Every entry is stored in two atomic words, the
+// data and the hash xor the data. When two threads write the same slot at the same time the words of the
+// entry no longer match and the probe simply treats the entry as missing.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    // the score is exact, the real score is at least the score, the real score is at most the score
+    Exact,
+    Lower,
+    Upper
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TtEntry {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound
+}
+
+struct TtSlot {
+    check: AtomicU64,
+    data: AtomicU64
+}
+
+pub struct TranspositionTable {
+    slots: Vec<TtSlot>,
+    // the number of slots is a power of two, so the index is the hash masked with this
+    mask: u64,
+    // increased with every new search, old entries are the first to be overwritten
+    generation: AtomicU8
+}
+
+fn encode_move(new_move: Option<Move>) -> u64 {
+    // 6 bits from, 6 bits to, 3 bits promotion and 1 bit that tells whether there is a move at all
+    match new_move {
+        None => 0,
+        Some(new_move) => {
+            let promotion = match new_move.on_promotion {
+                None => 0,
+                Some(PiecePromotes::Rook) => 1,
+                Some(PiecePromotes::Knight) => 2,
+                Some(PiecePromotes::Bishop) => 3,
+                Some(PiecePromotes::Queen) => 4
+            };
+            1 << 15 | promotion << 12 | (new_move.to as u64) << 6 | new_move.from as u64
+        }
+    }
+}
+
+fn decode_move(bits: u64) -> Option<Move> {
+    if bits & 1 << 15 == 0 {
+        return None
+    }
+    let on_promotion = match (bits >> 12) & 7 {
+        1 => Some(PiecePromotes::Rook),
+        2 => Some(PiecePromotes::Knight),
+        3 => Some(PiecePromotes::Bishop),
+        4 => Some(PiecePromotes::Queen),
+        _ => None
+    };
+    Some(Move { from: (bits & 63) as u8, to: ((bits >> 6) & 63) as u8, on_promotion })
+}
+
+fn encode(entry: &TtEntry, generation: u8) -> u64 {
+    // bits 0-15 move, 16-47 score, 48-55 depth, 56-57 bound, 58-63 generation
+    let bound = match entry.bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2
+    };
+    encode_move(entry.best_move) | (entry.score as u32 as u64) << 16 | (entry.depth as u64) << 48
+        | bound << 56 | ((generation & 63) as u64) << 58
+}
+
+fn decode(data: u64) -> (TtEntry, u8) {
+    let bound = match (data >> 56) & 3 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        _ => Bound::Upper
+    };
+    let entry = TtEntry {
+        best_move: decode_move(data & 0xFFFF),
+        score: ((data >> 16) & 0xFFFF_FFFF) as u32 as i32,
+        depth: ((data >> 48) & 0xFF) as u8,
+        bound
+    };
+    (entry, (data >> 58) as u8)
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        // use the largest power of two number of slots that fits into the given size
+        let bytes = size_mb.max(1) * 1024 * 1024;
+        let mut count = 1;
+        while count * 2 * std::mem::size_of::<TtSlot>() <= bytes {
+            count *= 2;
+        }
+        let slots = (0..count).map(|_| TtSlot { check: AtomicU64::new(0), data: AtomicU64::new(0) }).collect();
+        TranspositionTable { slots, mask: count as u64 - 1, generation: AtomicU8::new(0) }
+    }
+    pub fn size_mb(&self) -> usize {
+        (self.slots.len() * std::mem::size_of::<TtSlot>()).div_ceil(1024 * 1024)
+    }
+    pub fn clear(&self) {
+        // forgets every entry, an empty slot has data 0
+        for slot in self.slots.iter() {
+            slot.check.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn probe(&self, hash: u64, ply: usize) -> Option<TtEntry> {
+        let slot = &self.slots[(hash & self.mask) as usize];
+        let data = slot.data.load(Ordering::Relaxed);
+        if data == 0 || slot.check.load(Ordering::Relaxed) ^ data != hash {
+            return None
+        }
+        let (mut entry, _) = decode(data);
+        // mate and tablebase scores are stored relative to the node, turn them back into a distance from the root
+        if is_decisive_score(entry.score) {
+            entry.score -= entry.score.signum() * ply as i32;
+        }
+        Some(entry)
+    }
+    pub fn store(&self, hash: u64, ply: usize, mut entry: TtEntry) {
+        let slot = &self.slots[(hash & self.mask) as usize];
+        let generation = self.generation.load(Ordering::Relaxed) & 63;
+        let old_data = slot.data.load(Ordering::Relaxed);
+        if old_data != 0 {
+            let (old_entry, old_generation) = decode(old_data);
+            let same_position = slot.check.load(Ordering::Relaxed) ^ old_data == hash;
+            // keep deeper results of the current search, unless the new result is exact
+            if old_generation == generation && old_entry.depth > entry.depth && entry.bound != Bound::Exact {
+                return
+            }
+            // a result for the same position without a move should not forget the move we already know
+            if same_position && entry.best_move.is_none() {
+                entry.best_move = old_entry.best_move;
+            }
+        }
+        if is_decisive_score(entry.score) {
+            entry.score += entry.score.signum() * ply as i32;
+        }
+        let data = encode(&entry, generation);
+        slot.data.store(data, Ordering::Relaxed);
+        slot.check.store(hash ^ data, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored_and_probed(score: i32, store_ply: usize, probe_ply: usize) -> i32 {
+        let tt = TranspositionTable::new(1);
+        let best_move = Some(Move { from: 52, to: 36, on_promotion: None });
+        tt.store(0x1234, store_ply, TtEntry { best_move, score, depth: 4, bound: Bound::Exact });
+        let entry = tt.probe(0x1234, probe_ply).unwrap();
+        assert_eq!(entry.best_move, best_move);
+        entry.score
+    }
+
+    #[test]
+    fn scores_keep_their_distance_to_the_node() {
+        // a mate or tablebase win found 5 plies below the root is 2 plies further away when reached at ply 3
+        assert_eq!(stored_and_probed(MATE_SCORE - 7, 5, 3), MATE_SCORE - 5);
+        assert_eq!(stored_and_probed(-MATE_SCORE + 7, 5, 3), -MATE_SCORE + 5);
+        assert_eq!(stored_and_probed(TB_WIN_SCORE - 5, 5, 3), TB_WIN_SCORE - 3);
+        assert_eq!(stored_and_probed(-TB_WIN_SCORE + 5, 5, 3), -TB_WIN_SCORE + 3);
+        // normal scores do not depend on the ply
+        assert_eq!(stored_and_probed(150, 5, 3), 150);
+        assert_eq!(stored_and_probed(-150, 5, 3), -150);
+    }
+
+    #[test]
+    fn cleared_table_is_empty() {
+        let tt = TranspositionTable::new(1);
+        tt.store(0x1234, 0, TtEntry { best_move: None, score: 10, depth: 1, bound: Bound::Lower });
+        assert!(tt.probe(0x1234, 0).is_some());
+        assert!(tt.probe(0x4321, 0).is_none());
+        tt.clear();
+        assert!(tt.probe(0x1234, 0).is_none());
+    }
+}
@@ -0,0 +1,307 @@
+use pyo3::prelude::*;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::thread;
+use crate::chessboard::*;
+use crate::chessboard_helper::*;
+use crate::chess_computer::{BasicTreeSearchComputer, RecieveAndReturnMove};
+use crate::eval_params::EvalParams;
+use crate::evaluation::*;
+use crate::lookuptables::LoadMoves;
+
+// Texel tuning: the evaluation of a position is turned into an expected game result with a sigmoid, and the
+// parameters are changed one at a time for as long as that lowers the mean squared difference between the
+// expected and the real results of a large set of positions taken from played games.
+
+struct TuningPosition {
+    position: Position,
+    // the result of the game the position was taken from seen from white, 1.0 is a win and 0.5 a draw
+    result: f64
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct TuningReport {
+    #[pyo3(get)]
+    pub k: f64,
+    #[pyo3(get)]
+    pub error_before: f64,
+    #[pyo3(get)]
+    pub error_after: f64,
+    // the number of quiet positions that were used
+    #[pyo3(get)]
+    pub positions: usize,
+    // the number of positions that were skipped because they were not quiet or could not be read
+    #[pyo3(get)]
+    pub skipped: usize,
+    #[pyo3(get)]
+    pub iterations: usize,
+    // the error after every iteration, the first one is the error after iteration 1
+    #[pyo3(get)]
+    pub errors: Vec<f64>
+}
+
+fn parse_result(token: &str) -> Option<f64> {
+    // understands results written as 1-0, 0-1, 1/2-1/2 and as 1.0, 0.5 and 0.0, with or without quotes or brackets,
+    // plain integers are not accepted since they cannot be told apart from the move clocks
+    let token = token.trim_matches(|c: char| c == '"' || c == '[' || c == ']' || c == ';' || c == '(' || c == ')');
+    match token {
+        "1-0" | "1.0" => Some(1.0),
+        "0-1" | "0.0" => Some(0.0),
+        "1/2-1/2" | "0.5" => Some(0.5),
+        _ => None
+    }
+}
+
+fn is_valid_board(board: &str) -> bool {
+    // load_fen trusts its input, so we check the board part of the fen before we hand it over. Pawns on the
+    // first or last rank are rejected as well, the evaluation looks at the square in front of every pawn
+    let ranks: Vec<&str> = board.split('/').collect();
+    ranks.len() == 8 && !ranks[0].contains(['p', 'P']) && !ranks[7].contains(['p', 'P']) && ranks.iter().all(|rank| {
+        let mut squares = 0;
+        for c in rank.chars() {
+            match c {
+                '1'..='8' => squares += c.to_digit(10).unwrap(),
+                'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => squares += 1,
+                _ => return false
+            }
+        }
+        squares == 8
+    }) && board.matches('K').count() == 1 && board.matches('k').count() == 1
+}
+
+fn parse_line(line: &str) -> Option<(String, f64)> {
+    // A line holds a fen or epd followed somewhere by the result, for example
+    // "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1 [0.5]" or an epd with c9 "1-0";
+    // the json lines written by the data generator are understood as well
+    if line.trim_start().starts_with('{') {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        let fen = value.get("fen")?.as_str()?;
+        let result = value.get("result")?.as_f64()?;
+        return parse_line(&format!("{} {:.1}", fen, result))
+    }
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 4 || !is_valid_board(parts[0]) {
+        return None
+    }
+    let result = parts[4..].iter().rev().find_map(|token| parse_result(token))?;
+    // only the first four fields are used, the move clocks do not matter for the evaluation
+    Some((parts[..4].join(" "), result))
+}
+
+fn load_positions(dataset: &str, params: &EvalParams) -> Result<(Vec<TuningPosition>, usize), std::io::Error> {
+    // reads all labelled positions and keeps only the quiet ones, because the static evaluation
+    // cannot judge positions in the middle of an exchange. Quiet is judged with the parameters we start from
+    let reader = BufReader::new(fs::File::open(dataset)?);
+    let mut chessboard = Chessboard::new_start();
+    let mut computer = BasicTreeSearchComputer::new();
+    computer.set_eval_params(params.clone()).expect("the tree search accepts evaluation parameters");
+    // the tuned evaluation never looks at the bitbases, so they must not decide what is quiet either
+    computer.set_option("bitbases", "false").expect("the tree search has the bitbases option");
+    let mut positions = Vec::new();
+    let mut skipped = 0;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue
+        }
+        let (fen, result) = match parse_line(&line) {
+            Some(res) => res,
+            None => {
+                skipped += 1;
+                continue
+            }
+        };
+        chessboard.load_fen(fen);
+        if !computer.is_quiet(&mut chessboard) {
+            skipped += 1;
+            continue
+        }
+        positions.push(TuningPosition { position: *chessboard.get_position(), result });
+    }
+    Ok((positions, skipped))
+}
+
+fn sigmoid(score: f64, k: f64) -> f64 {
+    // the expected result of a position with the given score in centipawns
+    1.0 / (1.0 + 10f64.powf(-k * score / 400.0))
+}
+
+fn mean_error(positions: &[TuningPosition], moves: &LoadMoves, params: &EvalParams, k: f64, threads: usize) -> f64 {
+    // the mean squared difference between the game results and the results the evaluation predicts
+    if positions.is_empty() {
+        return 0.0
+    }
+    let chunk_size = positions.len().div_ceil(threads.max(1));
+    let total: f64 = thread::scope(|scope| {
+        let workers: Vec<_> = positions.chunks(chunk_size).map(|chunk| {
+            scope.spawn(move || {
+                let mut pawn_table = PawnHashTable::new();
+                chunk.iter().map(|tuning_position| {
+                    let score = evaluate(&tuning_position.position, moves, params, &mut pawn_table, false) as f64;
+                    (tuning_position.result - sigmoid(score, k)).powi(2)
+                }).sum::<f64>()
+            })
+        }).collect();
+        workers.into_iter().map(|worker| worker.join().expect("tuning thread panicked")).sum()
+    });
+    total / positions.len() as f64
+}
+
+fn fit_k(positions: &[TuningPosition], moves: &LoadMoves, params: &EvalParams, threads: usize) -> f64 {
+    // golden section search for the scaling constant that fits the current evaluation best
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (0.0, 10.0);
+    for _ in 0..30 {
+        let left = high - ratio * (high - low);
+        let right = low + ratio * (high - low);
+        if mean_error(positions, moves, params, left, threads) < mean_error(positions, moves, params, right, threads) {
+            high = right;
+        }
+        else {
+            low = left;
+        }
+    }
+    (low + high) / 2.0
+}
+
+pub fn tune(dataset: &str, output: &str, start: EvalParams, parameters: Option<Vec<String>>, max_iterations: usize,
+    threads: usize, progress: &mut dyn FnMut(usize, f64)) -> Result<(EvalParams, TuningReport), std::io::Error> {
+    // Local search: every parameter is moved by one in both directions and the change is kept if the error drops.
+    // A pass over all parameters is one iteration, we stop when an iteration no longer improves anything.
+    // progress is called with the iteration and the error after it, iteration 0 is the error we start from.
+    let (positions, skipped) = load_positions(dataset, &start)?;
+    let chessboard = Chessboard::new_start();
+    let moves = chessboard.get_pseudo_moves();
+    let mut params = start;
+    let k = fit_k(&positions, moves, &params, threads);
+    let error_before = mean_error(&positions, moves, &params, k, threads);
+    progress(0, error_before);
+
+    let names = params.names();
+    // only the parameters whose name starts with one of the given prefixes are tuned, all of them by default
+    let tuned: Vec<usize> = (0..names.len()).filter(|i| match &parameters {
+        Some(prefixes) => prefixes.iter().any(|prefix| names[*i].starts_with(prefix.as_str())),
+        None => true
+    }).collect();
+    let mut values = params.values();
+    let mut best_error = error_before;
+    let mut iterations = 0;
+    let mut errors = Vec::new();
+    while iterations < max_iterations {
+        iterations += 1;
+        let mut improved = false;
+        for i in tuned.iter() {
+            for step in [1, -1] {
+                values[*i] += step;
+                let mut candidate = params.clone();
+                candidate.set_values(&values).expect("values have the same layout as the parameters");
+                let error = mean_error(&positions, moves, &candidate, k, threads);
+                if error < best_error {
+                    best_error = error;
+                    params = candidate;
+                    improved = true;
+                    break
+                }
+                values[*i] -= step;
+            }
+        }
+        errors.push(best_error);
+        progress(iterations, best_error);
+        if !improved {
+            break
+        }
+    }
+    params.save_file(output)?;
+    let report = TuningReport { k, error_before, error_after: best_error, positions: positions.len(), skipped, iterations,
+        errors };
+    Ok((params, report))
+}
+
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (dataset, output, max_iterations=100, parameters=None, threads=1, start=None, progress=None))]
+pub fn tune_evaluation(py: Python, dataset: &str, output: &str, max_iterations: usize, parameters: Option<Vec<String>>,
+    threads: usize, start: Option<EvalParams>, progress: Option<PyObject>) -> PyResult<TuningReport> {
+    // Tunes the evaluation parameters on a file with one labelled fen or epd per line and writes the tuned
+    // parameters to output as json. Starts from the default parameters unless start is given. progress is
+    // called with the iteration and the error after it, iteration 0 is the error before tuning.
+    if threads == 0 {
+        return Err(PyValueError::new_err("threads must be at least 1"))
+    }
+    let start = start.unwrap_or_default();
+    // an error raised by the callback is kept and returned once tuning is done
+    let mut callback_error = None;
+    let mut report_progress = |iteration: usize, error: f64| {
+        if let (Some(callback), None) = (&progress, &callback_error) {
+            callback_error = Python::with_gil(|py| callback.call1(py, (iteration, error)).err());
+        }
+    };
+    let (_, report) = py.allow_threads(|| tune(dataset, output, start, parameters, max_iterations, threads, &mut report_progress))
+        .map_err(|err| PyIOError::new_err(err.to_string()))?;
+    match callback_error {
+        Some(err) => Err(err),
+        None => Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::*;
+
+    #[test]
+    fn lines_of_every_format() {
+        assert_eq!(parse_line("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1 [0.5]"),
+            Some(("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq -".to_string(), 0.5)));
+        assert_eq!(parse_line("4k3/8/8/8/8/8/4P3/4K3 w - - c9 \"1-0\";"),
+            Some(("4k3/8/8/8/8/8/4P3/4K3 w - -".to_string(), 1.0)));
+        assert_eq!(parse_line(r#"{"fen": "4k3/4p3/8/8/8/8/8/4K3 b - - 3 40", "score": -80, "move": "e7e5", "result": 0.0}"#),
+            Some(("4k3/4p3/8/8/8/8/8/4K3 b - -".to_string(), 0.0)));
+        // seven ranks, an unknown piece, two white kings
+        assert_eq!(parse_line("4k3/8/8/8/8/8/4K3 w - - 0 1 1-0"), None);
+        assert_eq!(parse_line("4k3/8/8/8/8/8/4X3/4K3 w - - 0 1 1-0"), None);
+        assert_eq!(parse_line("4k3/8/8/8/8/8/4K3/4K3 w - - 0 1 1-0"), None);
+        // the 1 could just as well be the move number
+        assert_eq!(parse_line("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"), None);
+        assert_eq!(parse_line("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 1"), None);
+        // pawns can not stand on the first or last rank
+        assert_eq!(parse_line("P3k3/8/8/8/8/8/8/4K3 w - - 0 1 [1.0]"), None);
+        assert_eq!(parse_line("4k3/8/8/8/8/8/8/p3K3 w - - 0 1 [0.0]"), None);
+    }
+
+    #[test]
+    fn tuning_lowers_the_error() {
+        chessboard(START_FEN);
+        let lines = [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1 [0.5]",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - c9 \"1/2-1/2\";",
+            r#"{"fen": "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", "score": 50, "move": "e2e4", "result": 1.0}"#,
+            "4k3/8/8/8/8/8/PPPP4/4K3 w - - 0 1 1-0",
+            "4k3/pppp4/8/8/8/8/8/4K3 w - - 0 1 0-1",
+            // the pawn takes the queen, so the evaluation of the position means nothing
+            "4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1 [1.0]",
+            "not a position 1-0",
+            "",
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"
+        ];
+        let dataset = std::env::temp_dir().join("rustchess_tuner_dataset.txt");
+        let output = std::env::temp_dir().join("rustchess_tuner_params.json");
+        let (dataset, output) = (dataset.to_str().unwrap(), output.to_str().unwrap());
+        fs::write(dataset, lines.join("\n")).unwrap();
+        let mut reported = Vec::new();
+        let tuned = tune(dataset, output, EvalParams::default(), Some(vec!["pawn_value".to_string()]), 3, 2,
+            &mut |iteration, error| reported.push((iteration, error)));
+        let loaded = EvalParams::load_file(output);
+        fs::remove_file(dataset).unwrap();
+        fs::remove_file(output).unwrap();
+        let (params, report) = tuned.unwrap();
+        assert_eq!(report.positions, 5);
+        assert_eq!(report.skipped, 3);
+        assert!(report.error_after <= report.error_before);
+        assert_eq!(report.errors.len(), report.iterations);
+        assert_eq!(reported[0], (0, report.error_before));
+        assert_eq!(loaded.unwrap(), params);
+    }
+}
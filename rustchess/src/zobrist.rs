@@ -0,0 +1,185 @@
+use crate::bitboard_helper::*;
+use crate::chessboard_helper::*;
+
+// Zobrist hashing gives every (piece, square) combination, castling right, en passant file and the side
+// to move a random number. The hash of a position is the xor of the numbers of everything that is
+// present, so two different positions almost never end up with the same hash.
+
+pub struct ZobristKeys {
+    // indexed as [color * 6 + piece][square] with pieces in the order pawn, knight, bishop, rook, queen, king
+    pieces: [[u64; 64]; 12],
+    // white kingside, white queenside, black kingside, black queenside
+    castling: [u64; 4],
+    en_passant: [u64; 8],
+    black_to_move: u64
+}
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    // small random generator that can run at compile time, returns the new state and the random number
+    let state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_keys() -> ZobristKeys {
+    // the keys are generated from a fixed seed, so hashes are the same every time the engine runs
+    let mut state = 0x1234_5678_9ABC_DEF0;
+    let mut keys = ZobristKeys { pieces: [[0; 64]; 12], castling: [0; 4], en_passant: [0; 8], black_to_move: 0 };
+    let mut piece = 0;
+    while piece < 12 {
+        let mut square = 0;
+        while square < 64 {
+            let (new_state, key) = splitmix64(state);
+            state = new_state;
+            keys.pieces[piece][square] = key;
+            square += 1;
+        }
+        piece += 1;
+    }
+    let mut i = 0;
+    while i < 4 {
+        let (new_state, key) = splitmix64(state);
+        state = new_state;
+        keys.castling[i] = key;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 8 {
+        let (new_state, key) = splitmix64(state);
+        state = new_state;
+        keys.en_passant[i] = key;
+        i += 1;
+    }
+    let (_, key) = splitmix64(state);
+    keys.black_to_move = key;
+    keys
+}
+
+pub static ZOBRIST: ZobristKeys = generate_keys();
+
+fn piece_bitboards(pieces: &Pieces) -> [u64; 6] {
+    [pieces.get_bb_pawns(), pieces.get_bb_knights(), pieces.get_bb_bishops(),
+        pieces.get_bb_rooks(), pieces.get_bb_queens(), pieces.get_bb_king()]
+}
+
+fn pieces_hash(pieces: &Pieces, color_offset: usize, only_pawns: bool) -> u64 {
+    let bitboards = piece_bitboards(pieces);
+    let mut hash = 0;
+    for (piece, bb) in bitboards.iter().enumerate() {
+        if only_pawns && piece > 0 {
+            break
+        }
+        for square in bb_to_vec(*bb) {
+            hash ^= ZOBRIST.pieces[color_offset + piece][square as usize];
+        }
+    }
+    hash
+}
+
+fn castle_rights(position: &Position) -> [bool; 4] {
+    [position.white_kingside_castle, position.white_queenside_castle,
+        position.black_kingside_castle, position.black_queenside_castle]
+}
+
+fn state_hash(position: &Position) -> u64 {
+    // everything but the pieces: castling rights, en passant file and the side to move
+    let mut hash = 0;
+    for (i, right) in castle_rights(position).iter().enumerate() {
+        if *right {
+            hash ^= ZOBRIST.castling[i];
+        }
+    }
+    if let Some(target) = position.es_target {
+        hash ^= ZOBRIST.en_passant[(target % 8) as usize];
+    }
+    if let ToMove::Black = position.to_move {
+        hash ^= ZOBRIST.black_to_move;
+    }
+    hash
+}
+
+pub fn hash_position(position: &Position) -> u64 {
+    pieces_hash(&position.white_pieces, 0, false) ^ pieces_hash(&position.black_pieces, 6, false) ^ state_hash(position)
+}
+
+pub fn update_hash(hash: u64, old: &Position, new: &Position) -> u64 {
+    // the hash of new given the hash of old, only the squares that changed are xored in or out.
+    // A move changes at most four squares, so this is much cheaper than hashing the whole position
+    let mut hash = hash ^ state_hash(old) ^ state_hash(new);
+    let sides = [(&old.white_pieces, &new.white_pieces, 0), (&old.black_pieces, &new.black_pieces, 6)];
+    for (old_pieces, new_pieces, color_offset) in sides {
+        let old_bitboards = piece_bitboards(old_pieces);
+        let new_bitboards = piece_bitboards(new_pieces);
+        for piece in 0..6 {
+            for square in bb_to_vec(old_bitboards[piece] ^ new_bitboards[piece]) {
+                hash ^= ZOBRIST.pieces[color_offset + piece][square as usize];
+            }
+        }
+    }
+    hash
+}
+
+pub fn hash_pawns(position: &Position) -> u64 {
+    // hash of only the pawns, used to cache the evaluation of the pawn structure
+    pieces_hash(&position.white_pieces, 0, true) ^ pieces_hash(&position.black_pieces, 6, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use super::*;
+    use crate::test_helper::*;
+
+    #[test]
+    fn incremental_hash_matches_full_hash() {
+        // random games from positions with castling, en passant and promotions, played forward and taken back
+        let mut rng = StdRng::seed_from_u64(30);
+        for fen in [START_FEN, KIWIPETE_FEN, "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"] {
+            let mut chessboard = chessboard(fen);
+            let start_hash = chessboard.get_hash();
+            assert_eq!(start_hash, hash_position(chessboard.get_position()));
+            let mut played = 0;
+            for ply in 0..200 {
+                let moves = chessboard.all_moves();
+                let new_move = match moves.choose(&mut rng) {
+                    Some(new_move) => *new_move,
+                    None => break
+                };
+                if ply % 7 == 3 && !chessboard.is_in_check() {
+                    chessboard.move_null();
+                }
+                else {
+                    chessboard.move_piece(&new_move).unwrap();
+                }
+                played += 1;
+                assert_eq!(chessboard.get_hash(), hash_position(chessboard.get_position()), "{}", chessboard.get_fen());
+            }
+            for _ in 0..played {
+                chessboard.undo();
+                assert_eq!(chessboard.get_hash(), hash_position(chessboard.get_position()));
+            }
+            assert_eq!(chessboard.get_hash(), start_hash);
+        }
+    }
+
+    #[test]
+    fn transpositions_have_the_same_hash() {
+        let mut first = chessboard(START_FEN);
+        let mut second = chessboard(START_FEN);
+        for notation in ["g1f3", "g8f6", "b1c3"] {
+            first.move_piece(&Move::from_string(notation).unwrap()).unwrap();
+        }
+        for notation in ["b1c3", "g8f6", "g1f3"] {
+            second.move_piece(&Move::from_string(notation).unwrap()).unwrap();
+        }
+        assert_eq!(first.get_hash(), second.get_hash());
+        // the same pieces with the other side to move
+        second.move_null();
+        assert_ne!(first.get_hash(), second.get_hash());
+    }
+}